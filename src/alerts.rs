@@ -0,0 +1,511 @@
+//! Alert rules: conditions on a process that have to hold for a while
+//! before the rule fires, a cooldown, and what to do about it. A rule can
+//! watch:
+//!
+//! - usage: CPU, resident memory, disk I/O, thread count
+//! - identity: the process name and how long it has been running
+//! - the monitors: a leak flagged by [`crate::leaks`], a hang flagged by
+//!   [`crate::hung`]
+//!
+//! The TUI and the tray both run the same [`Alerts`] over every snapshot.
+
+use crate::ProcessInfo;
+use crate::command::parse_duration;
+use crate::config::Config;
+use crate::events::format_runtime;
+use crate::kill::Target;
+use crate::record::parse_size;
+use crate::source::{ProcessSource, Signal};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// An `[[alerts]]` block as written in the config file. Every condition is
+/// optional, but a rule needs at least one; all of them have to hold.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertConfig {
+    pub name: String,
+    /// Process name, `*` and `?` wildcards, like `procsnipe kill`.
+    pub process: Option<String>,
+    /// CPU percentage above this.
+    pub cpu: Option<f32>,
+    /// Resident memory above this size, e.g. `"2GB"`.
+    pub memory: Option<String>,
+    /// Disk reads plus writes above this size per second.
+    pub io: Option<String>,
+    pub threads: Option<u32>,
+    pub older_than: Option<String>,
+    pub younger_than: Option<String>,
+    /// Flagged by the leak monitor (or not, with `false`).
+    pub leak: Option<bool>,
+    /// Flagged by the hung monitor (or not, with `false`).
+    pub hung: Option<bool>,
+    /// How long the condition has to hold before the rule fires.
+    #[serde(rename = "for")]
+    pub sustained: Option<String>,
+    /// Quiet time after the rule fires. Defaults to a minute.
+    pub cooldown: Option<String>,
+    /// `notify`, `log [PATH]`, `renice N`, `suspend`, `kill`, `run COMMAND`.
+    /// Defaults to `["notify"]`.
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Show it: the footer in the TUI, the console in tray mode.
+    Notify,
+    /// Append a line to this file, or `alerts.log` next to the config.
+    Log(Option<PathBuf>),
+    Renice(i32),
+    Suspend,
+    Kill,
+    /// A shell command, with `PROCSNIPE_PID`, `PROCSNIPE_NAME`,
+    /// `PROCSNIPE_RULE` and `PROCSNIPE_ALERT` set.
+    Run(String),
+}
+
+impl Action {
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (word, rest) = s.split_once(' ').unwrap_or((s, ""));
+        let rest = rest.trim();
+        Ok(match word {
+            "notify" if rest.is_empty() => Action::Notify,
+            "log" if rest.is_empty() => Action::Log(None),
+            "log" => Action::Log(Some(PathBuf::from(rest))),
+            "renice" => match rest.parse() {
+                Ok(nice) if (-20..=19).contains(&nice) => Action::Renice(nice),
+                _ => bail!("'{}': renice takes a nice value from -20 to 19", s),
+            },
+            "suspend" if rest.is_empty() => Action::Suspend,
+            "kill" if rest.is_empty() => Action::Kill,
+            "run" if !rest.is_empty() => Action::Run(rest.to_string()),
+            "run" => bail!("'run' needs a command"),
+            _ => bail!(
+                "unknown action '{}' (notify, log [PATH], renice N, suspend, kill, run COMMAND)",
+                s
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Condition {
+    pub process: Option<Target>,
+    pub cpu: Option<f32>,
+    /// Bytes.
+    pub memory: Option<u64>,
+    /// Bytes per second.
+    pub io: Option<u64>,
+    pub threads: Option<u32>,
+    pub older_than: Option<Duration>,
+    pub younger_than: Option<Duration>,
+    pub leak: Option<bool>,
+    pub hung: Option<bool>,
+}
+
+impl Condition {
+    fn is_empty(&self) -> bool {
+        self.process.is_none()
+            && self.cpu.is_none()
+            && self.memory.is_none()
+            && self.io.is_none()
+            && self.threads.is_none()
+            && self.older_than.is_none()
+            && self.younger_than.is_none()
+            && self.leak.is_none()
+            && self.hung.is_none()
+    }
+
+    /// `io` is the process's disk rate since the last check, if known.
+    /// Anything the condition asks about that isn't known doesn't match.
+    fn matches(&self, p: &ProcessInfo, io: Option<f64>, now_ms: u64) -> bool {
+        let age = p.age(now_ms / 1000);
+        self.process.as_ref().is_none_or(|t| t.matches(p))
+            && self.cpu.is_none_or(|min| p.cpu_usage > min)
+            && self.memory.is_none_or(|min| p.memory > min)
+            && self
+                .io
+                .is_none_or(|min| io.is_some_and(|rate| rate > min as f64))
+            && self.threads.is_none_or(|min| p.threads > min)
+            && self
+                .older_than
+                .is_none_or(|min| age.is_some_and(|age| age > min))
+            && self
+                .younger_than
+                .is_none_or(|max| age.is_some_and(|age| age < max))
+            && self.leak.is_none_or(|leak| p.leak.is_some() == leak)
+            && self.hung.is_none_or(|hung| p.hung.is_some() == hung)
+    }
+
+    /// What was measured for the parts of the condition, e.g.
+    /// `cpu 93.1%, 612 threads`.
+    fn describe(&self, p: &ProcessInfo, io: Option<f64>, now_ms: u64) -> String {
+        let mut parts = Vec::new();
+        if self.cpu.is_some() {
+            parts.push(format!("cpu {:.1}%", p.cpu_usage));
+        }
+        if self.memory.is_some() {
+            parts.push(format!("mem {} MB", p.memory / 1024 / 1024));
+        }
+        if let Some(rate) = io.filter(|_| self.io.is_some()) {
+            parts.push(match rate as u64 {
+                rate if rate < 1024 * 1024 => format!("io {} KB/s", rate / 1024),
+                rate => format!("io {} MB/s", rate / 1024 / 1024),
+            });
+        }
+        if self.threads.is_some() {
+            parts.push(format!("{} threads", p.threads));
+        }
+        if (self.older_than.is_some() || self.younger_than.is_some())
+            && let Some(age) = p.age(now_ms / 1000)
+        {
+            parts.push(format!("up {}", format_runtime(age.as_millis() as u64)));
+        }
+        if let Some(rate) = p.leak.filter(|_| self.leak.is_some()) {
+            parts.push(format!("leaking {:.1} MB/min", rate));
+        }
+        if let Some(hang) = p.hung.as_ref().filter(|_| self.hung.is_some()) {
+            parts.push(hang.to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name: String,
+    pub condition: Condition,
+    pub sustained: Duration,
+    pub cooldown: Duration,
+    pub actions: Vec<Action>,
+}
+
+/// Used when a rule doesn't say.
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(60);
+
+impl Rule {
+    /// The tray's old check, for configs without any `[[alerts]]`: notify
+    /// as soon as anything goes above `percent`.
+    pub fn cpu_above(percent: f32, cooldown: Duration) -> Self {
+        Self {
+            name: "high cpu".to_string(),
+            condition: Condition {
+                cpu: Some(percent),
+                ..Condition::default()
+            },
+            sustained: Duration::ZERO,
+            cooldown,
+            actions: vec![Action::Notify],
+        }
+    }
+
+    pub fn from_config(cfg: &AlertConfig) -> Result<Self> {
+        let name = cfg.name.trim();
+        if name.is_empty() {
+            bail!("alert without a name");
+        }
+        let rule = |e: anyhow::Error| anyhow!("alert '{}': {:#}", name, e);
+        let duration = |s: &Option<String>| s.as_deref().map(parse_duration).transpose();
+        let size = |s: &Option<String>| s.as_deref().map(parse_size).transpose();
+
+        if cfg.cpu.is_some_and(|cpu| cpu <= 0.0) {
+            return Err(rule(anyhow!("cpu must be above 0")));
+        }
+        let condition = Condition {
+            process: cfg.process.as_deref().map(Target::parse),
+            cpu: cfg.cpu,
+            memory: size(&cfg.memory).map_err(rule)?,
+            io: size(&cfg.io).map_err(rule)?,
+            threads: cfg.threads,
+            older_than: duration(&cfg.older_than).map_err(rule)?,
+            younger_than: duration(&cfg.younger_than).map_err(rule)?,
+            leak: cfg.leak,
+            hung: cfg.hung,
+        };
+        if condition.is_empty() {
+            return Err(rule(anyhow!(
+                "needs a condition (process, cpu, memory, io, threads, older_than, younger_than, leak or hung)"
+            )));
+        }
+        let actions = if cfg.actions.is_empty() {
+            vec![Action::Notify]
+        } else {
+            cfg.actions
+                .iter()
+                .map(|a| Action::parse(a))
+                .collect::<Result<_>>()
+                .map_err(rule)?
+        };
+        Ok(Self {
+            name: name.to_string(),
+            condition,
+            sustained: duration(&cfg.sustained).map_err(rule)?.unwrap_or_default(),
+            cooldown: duration(&cfg.cooldown)
+                .map_err(rule)?
+                .unwrap_or(DEFAULT_COOLDOWN),
+            actions,
+        })
+    }
+}
+
+/// Parse every `[[alerts]]` block.
+pub fn load_rules(configs: &[AlertConfig]) -> Result<Vec<Rule>> {
+    configs.iter().map(Rule::from_config).collect()
+}
+
+/// A rule firing for one process.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    /// Unix milliseconds.
+    pub time_ms: u64,
+    pub rule: String,
+    pub pid: u32,
+    pub name: String,
+    /// What was measured, and for how long.
+    pub reason: String,
+    /// The rule asks for it to be shown.
+    pub notify: bool,
+    /// What the other actions did, or why they didn't.
+    pub outcomes: Vec<String>,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.rule, self.name, self.pid)?;
+        if !self.reason.is_empty() {
+            write!(f, " {}", self.reason)?;
+        }
+        for outcome in &self.outcomes {
+            write!(f, ", {}", outcome)?;
+        }
+        Ok(())
+    }
+}
+
+/// A process, told apart from a later one with the same PID.
+type Key = (u32, u64);
+
+fn key(p: &ProcessInfo) -> Key {
+    (p.pid, p.start_time)
+}
+
+/// The rules plus what they've seen so far.
+#[derive(Default)]
+pub struct Alerts {
+    rules: Vec<Rule>,
+    /// Per rule: since when (Unix ms) each process has matched without a
+    /// break.
+    matching: Vec<HashMap<Key, u64>>,
+    last_fired: Vec<Option<u64>>,
+    /// Disk totals and when they were read, for I/O rates.
+    io: HashMap<Key, (u64, u64)>,
+    last_ms: Option<u64>,
+}
+
+impl Alerts {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        let mut alerts = Self::default();
+        alerts.set_rules(rules);
+        alerts
+    }
+
+    /// Swap the rules, e.g. after a config reload. Starts over: durations
+    /// and cooldowns count from now.
+    pub fn set_rules(&mut self, rules: Vec<Rule>) {
+        self.matching = vec![HashMap::new(); rules.len()];
+        self.last_fired = vec![None; rules.len()];
+        self.rules = rules;
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Run every rule over a snapshot taken at `time` and carry out the
+    /// actions of those that fire, through `source`. Processes need to be
+    /// marked first, and run past the leak and hung monitors for `leak` and
+    /// `hung`: protected ones are never reniced, suspended or killed.
+    pub fn check<S: ProcessSource + ?Sized>(
+        &mut self,
+        processes: &[ProcessInfo],
+        time: SystemTime,
+        source: &mut S,
+    ) -> Vec<Alert> {
+        let now = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        if self.last_ms.is_some_and(|last| now < last) {
+            let rules = std::mem::take(&mut self.rules);
+            self.set_rules(rules);
+            self.io.clear();
+        }
+        self.last_ms = Some(now);
+
+        let rates = self.io_rates(processes, now);
+        let mut alerts = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            let earlier = std::mem::take(&mut self.matching[i]);
+            let mut due = Vec::new();
+            for p in processes {
+                let io = rates.get(&key(p)).copied();
+                if !rule.condition.matches(p, io, now) {
+                    continue;
+                }
+                let since = earlier.get(&key(p)).copied().unwrap_or(now);
+                self.matching[i].insert(key(p), since);
+                if now - since >= rule.sustained.as_millis() as u64 {
+                    due.push((p, io, now - since));
+                }
+            }
+            let cooling = self.last_fired[i]
+                .is_some_and(|last| now - last < rule.cooldown.as_millis() as u64);
+            if due.is_empty() || cooling {
+                continue;
+            }
+            self.last_fired[i] = Some(now);
+            for (p, io, held) in due {
+                let mut reason = rule.condition.describe(p, io, now);
+                if !rule.sustained.is_zero() {
+                    reason = format!("{} for {}", reason, format_runtime(held))
+                        .trim()
+                        .to_string();
+                }
+                let mut alert = Alert {
+                    time_ms: now,
+                    rule: rule.name.clone(),
+                    pid: p.pid,
+                    name: p.name.clone(),
+                    reason,
+                    notify: false,
+                    outcomes: Vec::new(),
+                };
+                act(rule, p, &mut alert, source);
+                alerts.push(alert);
+            }
+        }
+        alerts
+    }
+
+    /// Bytes per second since the last check, for processes seen then.
+    fn io_rates(&mut self, processes: &[ProcessInfo], now: u64) -> HashMap<Key, f64> {
+        let mut rates = HashMap::new();
+        let earlier = std::mem::take(&mut self.io);
+        for p in processes {
+            if let Some(&(bytes, ms)) = earlier.get(&key(p))
+                && now > ms
+            {
+                let rate = p.io_bytes.saturating_sub(bytes) as f64 * 1000.0 / (now - ms) as f64;
+                rates.insert(key(p), rate);
+            }
+            self.io.insert(key(p), (p.io_bytes, now));
+        }
+        rates
+    }
+}
+
+/// Carry out the rule's actions for one process. The log line is written
+/// last so it says how the rest went.
+fn act<S: ProcessSource + ?Sized>(rule: &Rule, p: &ProcessInfo, alert: &mut Alert, source: &mut S) {
+    let mut logs = Vec::new();
+    for action in &rule.actions {
+        let outcome = match action {
+            Action::Notify => {
+                alert.notify = true;
+                continue;
+            }
+            Action::Log(path) => {
+                logs.push(path);
+                continue;
+            }
+            Action::Renice(_) | Action::Suspend | Action::Kill if p.is_protected => {
+                "protected, left alone".to_string()
+            }
+            Action::Renice(nice) => match source.renice(p.pid, *nice) {
+                Ok(()) => format!("reniced to {}", nice),
+                Err(err) => format!("couldn't renice: {:#}", err),
+            },
+            Action::Suspend => match source.signal(p.pid, Signal::Stop) {
+                Ok(()) => "suspended".to_string(),
+                Err(err) => format!("couldn't suspend: {:#}", err),
+            },
+            Action::Kill => match source.kill(p.pid) {
+                Ok(()) => "killed".to_string(),
+                Err(err) => format!("couldn't kill: {:#}", err),
+            },
+            Action::Run(command) => match run(command, alert) {
+                Ok(()) => format!("ran {}", command),
+                Err(err) => format!("{:#}", err),
+            },
+        };
+        if !alert.outcomes.contains(&outcome) {
+            alert.outcomes.push(outcome);
+        }
+    }
+    for path in logs {
+        if let Err(err) = log(path.as_ref(), alert) {
+            alert.outcomes.push(format!("{:#}", err));
+        }
+    }
+}
+
+/// Where `log` without a path writes: next to the default config file.
+pub fn default_log() -> Option<PathBuf> {
+    Config::default_path().and_then(|p| Some(p.parent()?.join("alerts.log")))
+}
+
+fn log(path: Option<&PathBuf>, alert: &Alert) -> Result<()> {
+    let path = match path {
+        Some(path) => path.clone(),
+        None => default_log().context("no config directory for alerts.log")?,
+    };
+    let time = chrono::DateTime::from_timestamp_millis(alert.time_ms as i64)
+        .unwrap_or_default()
+        .with_timezone(&chrono::Local);
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("couldn't create {}", dir.display()))?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{} {}", time.format("%Y-%m-%d %H:%M:%S"), alert))
+        .with_context(|| format!("couldn't write to {}", path.display()))
+}
+
+/// Start `command` in the background; a thread waits for it so it doesn't
+/// linger as a zombie.
+fn run(command: &str, alert: &Alert) -> Result<()> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    let mut child = cmd
+        .env("PROCSNIPE_PID", alert.pid.to_string())
+        .env("PROCSNIPE_NAME", &alert.name)
+        .env("PROCSNIPE_RULE", &alert.rule)
+        .env("PROCSNIPE_ALERT", alert.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("couldn't run {}", command))?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
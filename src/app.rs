@@ -0,0 +1,1462 @@
+//! Interactive TUI state and event handling.
+
+use crate::alerts::{self, Alerts};
+use crate::collector::{Collector, Snapshot};
+use crate::command::{self, Command, CommandLine, Setting};
+use crate::config::{Config, ConfigWatcher};
+use crate::connector::ProcConnector;
+use crate::diff::{self, Diff};
+use crate::events::EventLog;
+use crate::filter::{self, Expr, ParseError, quote};
+use crate::glyphs;
+use crate::hung::HungMonitor;
+use crate::keymap::{Action, Chord, Keymap, Outcome};
+use crate::kill::Target;
+use crate::leaks::LeakMonitor;
+use crate::menu::{ContextMenu, MenuItem};
+use crate::output;
+use crate::process;
+use crate::record::{Frame, Recorder};
+use crate::replay::{self, Player};
+use crate::source::ProcessSource;
+use crate::theme::Theme;
+use crate::ui::ui;
+use crate::view::{self, Column, View};
+use crate::{ProcessInfo, SortMode};
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::Terminal;
+use ratatui::backend::Backend;
+use ratatui::layout::Rect;
+use ratatui::widgets::ListState;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// What the keyboard is currently driving.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    Search,
+    Help,
+    Views,
+    SaveView,
+    Details,
+    /// What changed since the `:diff` baseline.
+    Diff,
+    Menu,
+    Pid,
+    Command,
+}
+
+/// Where snapshots come from.
+pub enum Feed {
+    /// Scanning the system on a background thread.
+    Live(Collector),
+    /// Playing back a recording. Nothing can be killed.
+    Replay(Player),
+}
+
+impl Feed {
+    fn latest(&mut self) -> Option<Snapshot> {
+        match self {
+            Feed::Live(collector) => collector.latest(),
+            Feed::Replay(player) => player.latest(),
+        }
+    }
+}
+
+/// The TUI state. Generic over where processes come from so tests and
+/// embedders can drive it with a [`FakeSource`](crate::source::FakeSource).
+pub struct App<S> {
+    pub(crate) mode: Mode,
+    /// Everything collected at the last refresh, unfiltered.
+    pub(crate) snapshot: Vec<ProcessInfo>,
+    /// `snapshot` after the view/search filter and sort; what the list shows.
+    pub(crate) processes: Vec<ProcessInfo>,
+    pub(crate) list_state: ListState,
+    pub(crate) search_query: String,
+    pub(crate) filter: Option<Expr>,
+    pub(crate) filter_error: Option<ParseError>,
+    /// Query and filter of the last search, for `n`/`N`.
+    pub(crate) last_search: Option<(String, Expr)>,
+    pub(crate) pid_input: String,
+    pub(crate) command_line: CommandLine,
+    pub(crate) sort_mode: SortMode,
+    /// Flip the sort mode's usual direction (`:sort cpu asc`).
+    pub(crate) sort_reversed: bool,
+    pub(crate) views: Vec<View>,
+    pub(crate) active_view: usize,
+    pub(crate) view_state: ListState,
+    pub(crate) view_name: String,
+    pub(crate) status_message: Option<String>,
+    pub(crate) config: Config,
+    pub(crate) config_watcher: ConfigWatcher,
+    pub(crate) keymap: Keymap,
+    pub(crate) theme: Theme,
+    pub(crate) refresh_rate: Duration,
+    /// Frozen list: new snapshots are ignored until resumed.
+    pub(crate) paused: bool,
+    pub(crate) feed: Feed,
+    /// When the current snapshot was collected, and how long that took.
+    pub(crate) last_snapshot: Instant,
+    pub(crate) collect_took: Duration,
+    /// Wall-clock time of the current snapshot (recorded time in a replay).
+    pub(crate) snapshot_time: SystemTime,
+    /// Used for kills, renice and details; the collector thread scans its
+    /// own clone.
+    pub(crate) source: S,
+    pub(crate) last_key_time: Option<Instant>,
+    /// Window for ignoring repeated non-navigation keys.
+    pub(crate) debounce: Duration,
+    /// Process list areas from the last draw, for mouse hit-testing.
+    pub(crate) list_area: Rect,
+    pub(crate) header_area: Rect,
+    /// The replay scrubber bar, when there is one.
+    pub(crate) timeline_area: Rect,
+    /// Time and row of the last left click, to spot double-clicks.
+    pub(crate) last_click: Option<(Instant, usize)>,
+    pub(crate) details_pid: Option<u32>,
+    /// Extra rows from the source for the details view.
+    pub(crate) details_extra: Vec<(&'static str, String)>,
+    pub(crate) menu: Option<ContextMenu>,
+    /// Where every snapshot gets written, with `--record`.
+    pub(crate) recorder: Option<Recorder>,
+    /// What `:diff` compares the current snapshot against, and a label for
+    /// it (the time it was marked, or the file it came from).
+    pub(crate) diff_base: Option<(String, Vec<ProcessInfo>)>,
+    /// Starts and exits seen across snapshots, for the event pane.
+    pub(crate) events: EventLog,
+    pub(crate) show_events: bool,
+    /// The config's `[[alerts]]`; not checked in a replay.
+    pub(crate) alerts: Alerts,
+    pub(crate) leaks: LeakMonitor,
+    pub(crate) hung: HungMonitor,
+}
+
+/// Longest wait for input before redrawing, so new snapshots and the
+/// stale marker show up promptly.
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+/// Two clicks on the same row within this window open the details view.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+/// Rows moved per scroll wheel notch.
+const SCROLL_ROWS: isize = 3;
+/// Width of the `>> ` highlight symbol in front of every row.
+pub(crate) const HIGHLIGHT_WIDTH: u16 = 3;
+/// Ignore a repeated non-navigation key within this window.
+const DEBOUNCE: Duration = Duration::from_millis(150);
+/// Refresh intervals stepped through by the faster/slower keys.
+const REFRESH_STEPS: [u64; 9] = [100, 250, 500, 1000, 2000, 5000, 10_000, 30_000, 60_000];
+
+impl<S: ProcessSource + Clone + 'static> App<S> {
+    /// The live TUI, collecting from `source` on a background thread.
+    pub fn new(
+        source: S,
+        config: Config,
+        views: Vec<View>,
+        keymap: Keymap,
+        theme: Theme,
+        config_watcher: ConfigWatcher,
+    ) -> Self {
+        let collector = Collector::spawn(source.clone(), config.refresh_rate());
+        let feed = Feed::Live(collector);
+        Self::with_feed(source, feed, config, views, keymap, theme, config_watcher)
+    }
+
+    /// Playing back a recording. Nothing is collected, and `source` is
+    /// never signalled.
+    pub fn replaying(
+        source: S,
+        player: Player,
+        config: Config,
+        views: Vec<View>,
+        keymap: Keymap,
+        theme: Theme,
+        config_watcher: ConfigWatcher,
+    ) -> Self {
+        let feed = Feed::Replay(player);
+        Self::with_feed(source, feed, config, views, keymap, theme, config_watcher)
+    }
+
+    fn with_feed(
+        source: S,
+        feed: Feed,
+        config: Config,
+        views: Vec<View>,
+        keymap: Keymap,
+        theme: Theme,
+        config_watcher: ConfigWatcher,
+    ) -> Self {
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+        let mut view_state = ListState::default();
+        view_state.select(Some(0));
+        let sort_mode = views.first().map(|v| v.sort).unwrap_or(SortMode::Cpu);
+
+        Self {
+            mode: Mode::Normal,
+            snapshot: Vec::new(),
+            processes: Vec::new(),
+            list_state,
+            search_query: String::new(),
+            filter: None,
+            filter_error: None,
+            last_search: None,
+            pid_input: String::new(),
+            command_line: CommandLine::default(),
+            sort_mode,
+            sort_reversed: false,
+            views,
+            active_view: 0,
+            view_state,
+            view_name: String::new(),
+            status_message: None,
+            refresh_rate: config.refresh_rate(),
+            paused: false,
+            feed,
+            last_snapshot: Instant::now(),
+            collect_took: Duration::ZERO,
+            snapshot_time: SystemTime::now(),
+            // The config was validated when it was loaded.
+            alerts: Alerts::new(alerts::load_rules(&config.alerts).unwrap_or_default()),
+            leaks: LeakMonitor::new(config.leaks.clone()),
+            hung: HungMonitor::new(config.hung.clone()),
+            config,
+            config_watcher,
+            keymap,
+            theme,
+            source,
+            last_key_time: None,
+            debounce: DEBOUNCE,
+            list_area: Rect::default(),
+            header_area: Rect::default(),
+            timeline_area: Rect::default(),
+            last_click: None,
+            details_pid: None,
+            details_extra: Vec::new(),
+            menu: None,
+            recorder: None,
+            diff_base: None,
+            events: EventLog::new(),
+            show_events: false,
+        }
+    }
+
+    /// Run a normal-mode action. Returns true when the app should quit.
+    fn run_action(&mut self, action: Action) -> Result<bool> {
+        let count = self.keymap.take_count();
+        // Saturated so a count of a few billion just runs to the end.
+        let n = count.map_or(1, |n| isize::try_from(n).unwrap_or(isize::MAX));
+        match action {
+            Action::Quit => return Ok(true),
+            Action::Help => self.mode = Mode::Help,
+            Action::Search => {
+                self.mode = Mode::Search;
+                self.search_query.clear();
+                self.on_query_changed();
+            }
+            // A plain j/k wraps around; with a count it stops at the ends.
+            Action::Down if count.is_none() => self.next(),
+            Action::Up if count.is_none() => self.previous(),
+            Action::Down => self.move_selection(n),
+            Action::Up => self.move_selection(-n),
+            Action::PageDown => self.move_selection(self.page_rows().saturating_mul(n)),
+            Action::PageUp => self.move_selection(self.page_rows().saturating_mul(-n)),
+            Action::HalfPageDown => {
+                self.move_selection((self.page_rows() / 2).max(1).saturating_mul(n))
+            }
+            Action::HalfPageUp => {
+                self.move_selection((self.page_rows() / 2).max(1).saturating_mul(-n))
+            }
+            Action::Top => self.jump_to_row(count.map_or(0, |n| n - 1)),
+            Action::Bottom => self.jump_to_row(count.map_or(usize::MAX, |n| n - 1)),
+            Action::NextMatch => self.jump_to_match(n),
+            Action::PrevMatch => self.jump_to_match(-n),
+            Action::JumpToPid => {
+                self.pid_input.clear();
+                self.mode = Mode::Pid;
+            }
+            Action::Command => {
+                self.command_line.open();
+                self.mode = Mode::Command;
+            }
+            Action::Faster => self.step_refresh(false),
+            Action::Slower => self.step_refresh(true),
+            Action::Pause => match &mut self.feed {
+                // Only the list freezes: collection goes on underneath so
+                // no start or exit is missed.
+                Feed::Live(collector) => {
+                    self.paused = !self.paused;
+                    if !self.paused {
+                        // Don't flag the old list as stale while the fresh one is collected.
+                        self.last_snapshot = Instant::now();
+                        collector.refresh_now();
+                    }
+                }
+                Feed::Replay(player) => player.toggle(),
+            },
+            Action::StepForward => self.with_player(|p| p.step(n)),
+            Action::StepBack => self.with_player(|p| p.step(-n)),
+            Action::ScrubForward => {
+                self.with_player(|p| p.scrub(p.progress() + 0.1 * n as f64));
+            }
+            Action::ScrubBack => self.with_player(|p| p.scrub(p.progress() - 0.1 * n as f64)),
+            Action::Kill => self.kill_selected()?,
+            Action::ToggleGames => self.toggle_games_view(),
+            Action::CycleSort => self.toggle_sort(),
+            Action::ViewPicker => {
+                self.view_state.select(Some(self.active_view));
+                self.mode = Mode::Views;
+            }
+            Action::Details => self.open_details(),
+            Action::Events => self.show_events = !self.show_events,
+            Action::Menu => {
+                let row = self.list_state.selected().unwrap_or(0);
+                let y = self.list_area.y + (row.saturating_sub(self.list_state.offset())) as u16;
+                self.open_menu(self.list_area.x + HIGHLIGHT_WIDTH, y + 1);
+            }
+            Action::View(n) => self.switch_view(n as usize - 1),
+        }
+        Ok(false)
+    }
+
+    fn on_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        let (x, y) = (mouse.column, mouse.row);
+        match self.mode {
+            Mode::Normal => match mouse.kind {
+                MouseEventKind::ScrollDown => self.move_selection(SCROLL_ROWS),
+                MouseEventKind::ScrollUp => self.move_selection(-SCROLL_ROWS),
+                MouseEventKind::Down(MouseButton::Left)
+                | MouseEventKind::Drag(MouseButton::Left)
+                    if let Some(fraction) = self.timeline_at(x, y) =>
+                {
+                    self.with_player(|p| p.scrub(fraction));
+                }
+                MouseEventKind::Down(MouseButton::Left) => {
+                    if let Some(column) = self.column_at(x, y) {
+                        self.sort_by(column.sort_mode());
+                    } else if let Some(row) = self.row_at(x, y) {
+                        self.list_state.select(Some(row));
+                        let double = self
+                            .last_click
+                            .is_some_and(|(t, r)| r == row && t.elapsed() < DOUBLE_CLICK);
+                        if double {
+                            self.last_click = None;
+                            self.open_details();
+                        } else {
+                            self.last_click = Some((Instant::now(), row));
+                        }
+                    }
+                }
+                MouseEventKind::Down(MouseButton::Right) => {
+                    if let Some(row) = self.row_at(x, y) {
+                        self.list_state.select(Some(row));
+                        self.open_menu(x, y);
+                    }
+                }
+                _ => {}
+            },
+            Mode::Menu => match mouse.kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    let item = self.menu.as_ref().and_then(|m| m.item_at(x, y));
+                    match item {
+                        Some(i) => self.run_menu_item(MenuItem::ALL[i])?,
+                        None => self.close_menu(),
+                    }
+                }
+                MouseEventKind::Down(_) => self.close_menu(),
+                MouseEventKind::ScrollDown => {
+                    if let Some(menu) = &mut self.menu {
+                        menu.next();
+                    }
+                }
+                MouseEventKind::ScrollUp => {
+                    if let Some(menu) = &mut self.menu {
+                        menu.previous();
+                    }
+                }
+                _ => {}
+            },
+            Mode::Details | Mode::Diff => {
+                if let MouseEventKind::Down(_) = mouse.kind {
+                    self.mode = Mode::Normal;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// How far along the replay scrubber a screen position is, 0.0 to 1.0.
+    fn timeline_at(&self, x: u16, y: u16) -> Option<f64> {
+        let area = self.timeline_area;
+        if y != area.y || x < area.x || x >= area.right() {
+            return None;
+        }
+        Some((x - area.x) as f64 / area.width.saturating_sub(1).max(1) as f64)
+    }
+
+    /// Visible row index under a screen position.
+    fn row_at(&self, x: u16, y: u16) -> Option<usize> {
+        let area = self.list_area;
+        if x < area.x || x >= area.right() || y < area.y || y >= area.bottom() {
+            return None;
+        }
+        let row = self.list_state.offset() + (y - area.y) as usize;
+        (row < self.processes.len()).then_some(row)
+    }
+
+    /// Column whose header is under a screen position.
+    fn column_at(&self, x: u16, y: u16) -> Option<Column> {
+        let area = self.header_area;
+        if y != area.y || x < area.x + HIGHLIGHT_WIDTH || x >= area.right() {
+            return None;
+        }
+        let mut start = area.x + HIGHLIGHT_WIDTH;
+        for &column in self.columns() {
+            if x < start + column.width() {
+                return Some(column);
+            }
+            start += column.width();
+        }
+        None
+    }
+
+    /// Move the selection without wrapping around.
+    fn move_selection(&mut self, delta: isize) {
+        if self.processes.is_empty() {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        let last = self.processes.len() as isize - 1;
+        self.list_state
+            .select(Some(current.saturating_add(delta).clamp(0, last) as usize));
+    }
+
+    /// Rows visible in the list, the step for page up/down.
+    fn page_rows(&self) -> isize {
+        (self.list_area.height as isize).max(1)
+    }
+
+    /// Select row `row`, clamped to the list.
+    fn jump_to_row(&mut self, row: usize) {
+        if !self.processes.is_empty() {
+            self.list_state
+                .select(Some(row.min(self.processes.len() - 1)));
+        }
+    }
+
+    /// Remember the search being closed so `n`/`N` can come back to it.
+    fn finish_search(&mut self) {
+        if let Some(filter) = &self.filter {
+            self.last_search = Some((self.search_query.clone(), filter.clone()));
+        }
+    }
+
+    /// Move `steps` matches of the last search forward (or back when
+    /// negative), wrapping around the list like vim's `n`/`N`.
+    fn jump_to_match(&mut self, steps: isize) {
+        let Some((query, filter)) = &self.last_search else {
+            self.status_message = Some("no previous search".to_string());
+            return;
+        };
+        let matches: Vec<usize> = self
+            .processes
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| filter.matches(p))
+            .map(|(i, _)| i)
+            .collect();
+        if matches.is_empty() {
+            self.status_message = Some(format!("no matches for: {}", query));
+            return;
+        }
+
+        let current = self.list_state.selected().unwrap_or(0);
+        let len = matches.len() as isize;
+        // Index of the first match after (or before) the current row.
+        let start = if steps > 0 {
+            let next = matches.partition_point(|&i| i <= current) as isize;
+            next.saturating_add(steps) - 1
+        } else {
+            let prev = matches.partition_point(|&i| i < current) as isize - 1;
+            prev.saturating_add(steps) + 1
+        };
+        let row = matches[start.rem_euclid(len) as usize];
+        self.list_state.select(Some(row));
+        self.status_message = Some(format!(
+            "match {}/{} for: {}",
+            start.rem_euclid(len) + 1,
+            len,
+            query
+        ));
+    }
+
+    fn jump_to_pid(&mut self, pid: u32) {
+        if let Some(row) = self.processes.iter().position(|p| p.pid == pid) {
+            self.list_state.select(Some(row));
+        } else if self.snapshot.iter().any(|p| p.pid == pid) {
+            self.status_message = Some(format!("PID {} is hidden by the current filter", pid));
+        } else {
+            self.status_message = Some(format!("no process with PID {}", pid));
+        }
+    }
+
+    /// Move the refresh interval one step slower or faster. Replays change
+    /// speed instead.
+    fn step_refresh(&mut self, slower: bool) {
+        if let Feed::Replay(player) = &mut self.feed {
+            let speed = player.change_speed(!slower);
+            self.status_message = Some(format!("playing at x{}", speed));
+            return;
+        }
+        let current = self.refresh_rate.as_millis() as u64;
+        let next = if slower {
+            REFRESH_STEPS.iter().find(|&&ms| ms > current)
+        } else {
+            REFRESH_STEPS.iter().rev().find(|&&ms| ms < current)
+        };
+        if let Some(&ms) = next {
+            self.set_refresh_rate(Duration::from_millis(ms));
+        }
+        self.status_message = Some(format!(
+            "refreshing every {}",
+            command::format_duration(self.refresh_rate)
+        ));
+    }
+
+    pub fn set_refresh_rate(&mut self, rate: Duration) {
+        self.refresh_rate = rate;
+        if let Feed::Live(collector) = &self.feed {
+            collector.set_interval(rate);
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// The rows on screen: filtered and sorted.
+    pub fn processes(&self) -> &[ProcessInfo] {
+        &self.processes
+    }
+
+    /// Write every snapshot from now on to `recorder`.
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Append every start/exit event to `path` as well as showing it.
+    pub fn log_events_to(&mut self, path: &Path) -> Result<()> {
+        self.events.persist_to(path)
+    }
+
+    /// Get exact starts and exits from the kernel instead of only
+    /// comparing snapshots.
+    pub fn attach_connector(&mut self, connector: ProcConnector) {
+        self.events.attach(connector);
+    }
+
+    pub fn events(&self) -> &EventLog {
+        &self.events
+    }
+
+    /// The recording being replayed, if any.
+    pub fn player(&self) -> Option<&Player> {
+        match &self.feed {
+            Feed::Replay(player) => Some(player),
+            Feed::Live(_) => None,
+        }
+    }
+
+    /// Run `f` on the replay, or complain that there isn't one.
+    fn with_player(&mut self, f: impl FnOnce(&mut Player)) {
+        match &mut self.feed {
+            Feed::Replay(player) => f(player),
+            Feed::Live(_) => self.status_message = Some("not replaying a recording".to_string()),
+        }
+    }
+
+    /// True (and says so in the footer) when replaying, where nothing can
+    /// be signalled.
+    fn read_only(&mut self) -> bool {
+        let replaying = self.player().is_some();
+        if replaying {
+            self.status_message = Some("replay is read-only: nothing to kill here".to_string());
+        }
+        replaying
+    }
+
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message.as_deref()
+    }
+
+    /// Change how long a repeated non-navigation key is ignored for. Zero
+    /// turns debouncing off, e.g. for scripted input.
+    pub fn set_debounce(&mut self, window: Duration) {
+        self.debounce = window;
+    }
+
+    /// The collector is behind: no snapshot for well over the interval.
+    pub(crate) fn is_stale(&self) -> bool {
+        matches!(self.feed, Feed::Live(_))
+            && !self.paused
+            && self.last_snapshot.elapsed() > self.refresh_rate * 2 + Duration::from_secs(1)
+    }
+
+    fn sort_by(&mut self, mode: SortMode) {
+        self.sort_mode = mode;
+        self.sort_reversed = false;
+        self.apply_view();
+    }
+
+    /// Run a `:` command line. Returns true when the app should quit.
+    fn run_command(&mut self, line: &str) -> Result<bool> {
+        let command = match command::parse(line) {
+            Ok(command) => command,
+            Err(err) => {
+                self.status_message = Some(err.to_string());
+                return Ok(false);
+            }
+        };
+        match command {
+            Command::Quit => return Ok(true),
+            Command::Help => self.mode = Mode::Help,
+            Command::Kill(_) | Command::Renice { .. } if self.read_only() => {}
+            Command::Kill(targets) if targets.is_empty() => self.kill_selected()?,
+            Command::Kill(targets) => self.kill_targets(&targets),
+            Command::Sort { mode, descending } => {
+                self.sort_by(mode);
+                if let Some(descending) = descending {
+                    self.sort_reversed = descending != mode.descending_by_default();
+                    self.apply_view();
+                }
+            }
+            Command::Filter(query) => {
+                self.search_query = query;
+                self.on_query_changed();
+                match &self.filter_error {
+                    Some(err) => self.status_message = Some(format!("bad filter: {}", err)),
+                    None => self.finish_search(),
+                }
+            }
+            Command::Renice { nice, pid } => {
+                let target = pid.or_else(|| self.selected_process().map(|p| p.pid));
+                let protected = target
+                    .and_then(|pid| self.snapshot.iter().find(|p| p.pid == pid))
+                    .filter(|p| p.is_protected);
+                self.status_message = Some(match (target, protected) {
+                    (_, Some(p)) => format!("{} ({}) is protected, not renicing it", p.name, p.pid),
+                    (Some(pid), None) => match self.source.renice(pid, nice) {
+                        Ok(()) => format!("reniced {} to {}", pid, nice),
+                        Err(err) => format!("renice {} failed: {:#}", pid, err),
+                    },
+                    (None, None) => "no process selected".to_string(),
+                });
+            }
+            Command::ExportCsv(path) => {
+                self.status_message = Some(match write_csv(&path, &self.processes) {
+                    Ok(()) => format!(
+                        "exported {} processes to {}",
+                        self.processes.len(),
+                        path.display()
+                    ),
+                    Err(err) => format!("export failed: {:#}", err),
+                });
+            }
+            Command::Set(Setting::Refresh(rate)) => {
+                self.set_refresh_rate(rate);
+                self.status_message = Some(format!(
+                    "refreshing every {}",
+                    command::format_duration(rate)
+                ));
+            }
+            Command::Set(Setting::Theme(name)) => {
+                let mut theme = self.config.theme.clone();
+                theme.name = name;
+                match theme.resolve() {
+                    Ok(theme) => self.theme = theme,
+                    Err(err) => self.status_message = Some(err.to_string()),
+                }
+            }
+            Command::Set(Setting::Ascii(ascii)) => glyphs::set_ascii(ascii),
+            Command::View(name) => match view::find(&self.views, &name) {
+                Some(i) => self.switch_view(i),
+                None => self.status_message = Some(format!("no view '{}'", name)),
+            },
+            Command::Pid(pid) => self.jump_to_pid(pid),
+            Command::Seek(seek) => {
+                let mut result = Ok(());
+                self.with_player(|p| result = p.seek(seek));
+                if let Err(err) = result {
+                    self.status_message = Some(err.to_string());
+                }
+            }
+            Command::Diff(None) => match self.diff_base {
+                Some(_) => self.mode = Mode::Diff,
+                None => {
+                    self.status_message =
+                        Some("nothing to compare with yet: :diff mark or :diff PATH".to_string())
+                }
+            },
+            Command::Diff(Some(arg)) if arg == "mark" => {
+                let ms = self
+                    .snapshot_time
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0);
+                let label = replay::clock(ms);
+                self.status_message = Some(format!("diff baseline set at {}", label));
+                self.diff_base = Some((label, self.snapshot.clone()));
+            }
+            Command::Diff(Some(spec)) => match diff::load(&spec) {
+                Ok(frame) => {
+                    let label = match frame.time_ms {
+                        0 => spec,
+                        ms => format!("{} ({})", spec, replay::clock(ms)),
+                    };
+                    self.diff_base = Some((label, frame.processes));
+                    self.mode = Mode::Diff;
+                }
+                Err(err) => self.status_message = Some(format!("{:#}", err)),
+            },
+        }
+        Ok(false)
+    }
+
+    /// What changed between the `:diff` baseline and the current snapshot,
+    /// with the baseline's label.
+    pub(crate) fn diff(&self) -> Option<(&str, Diff)> {
+        let (label, base) = self.diff_base.as_ref()?;
+        Some((label, Diff::new(base, &self.snapshot)))
+    }
+
+    /// Kill processes by PID or name (case-insensitive, `.exe` optional,
+    /// `*`/`?` wildcards), skipping protected ones, and report what
+    /// happened in the footer.
+    fn kill_targets(&mut self, targets: &[String]) {
+        let mut killed = 0;
+        let mut failed = 0;
+        let mut skipped = Vec::new();
+        let mut missing = Vec::new();
+        for target in targets {
+            let wanted = Target::parse(target);
+            let matched: Vec<ProcessInfo> = self
+                .snapshot
+                .iter()
+                .filter(|p| wanted.matches(p))
+                .cloned()
+                .collect();
+            if matched.is_empty() {
+                missing.push(target.as_str());
+            }
+            for p in matched {
+                if p.is_protected {
+                    skipped.push(format!("{} ({})", p.name, p.pid));
+                } else if self.kill_pid(p.pid) {
+                    killed += 1;
+                } else {
+                    failed += 1;
+                }
+            }
+        }
+
+        let mut parts = vec![format!("killed {}", killed)];
+        if failed > 0 {
+            parts.push(format!("{} failed (permissions?)", failed));
+        }
+        if !skipped.is_empty() {
+            parts.push(format!("skipped protected: {}", skipped.join(", ")));
+        }
+        if !missing.is_empty() {
+            parts.push(format!("no match: {}", missing.join(", ")));
+        }
+        self.status_message = Some(parts.join(" | "));
+    }
+
+    fn open_details(&mut self) {
+        if let Some(pid) = self.selected_process().map(|p| p.pid) {
+            self.show_details(pid);
+        }
+    }
+
+    /// What the source knows beyond the snapshot. Nothing in a replay: the
+    /// PID may belong to something else by now.
+    fn live_details(&mut self, pid: u32) -> Vec<(&'static str, String)> {
+        match self.feed {
+            Feed::Live(_) => self.source.details(pid),
+            Feed::Replay(_) => Vec::new(),
+        }
+    }
+
+    fn show_details(&mut self, pid: u32) {
+        self.details_extra = self.live_details(pid);
+        self.details_pid = Some(pid);
+        self.mode = Mode::Details;
+    }
+
+    /// Kill one process. False if it's gone or we lack the rights.
+    fn kill_pid(&mut self, pid: u32) -> bool {
+        let killed = self.source.kill(pid).is_ok();
+        if killed {
+            // Drop the row right away rather than at the next interval.
+            if let Feed::Live(collector) = &self.feed {
+                collector.refresh_now();
+            }
+        }
+        killed
+    }
+
+    fn open_menu(&mut self, x: u16, y: u16) {
+        if let Some(p) = self.selected_process() {
+            self.menu = Some(ContextMenu::new(p.pid, p.name.clone(), (x, y)));
+            self.mode = Mode::Menu;
+        }
+    }
+
+    fn close_menu(&mut self) {
+        self.menu = None;
+        self.mode = Mode::Normal;
+    }
+
+    fn run_menu_item(&mut self, item: MenuItem) -> Result<()> {
+        let Some(menu) = self.menu.take() else {
+            return Ok(());
+        };
+        self.mode = Mode::Normal;
+
+        // The list may have refreshed under the menu; act on the PID it was opened for.
+        let row = self.processes.iter().position(|p| p.pid == menu.pid);
+        match item {
+            MenuItem::Details => self.show_details(menu.pid),
+            MenuItem::Kill => match row {
+                Some(row) => {
+                    self.list_state.select(Some(row));
+                    self.kill_selected()?;
+                }
+                None => self.status_message = Some(format!("{} already exited", menu.name)),
+            },
+            MenuItem::OnlyThisName | MenuItem::HideThisName => {
+                let term = format!("name={}", quote(&menu.name));
+                let term = if item == MenuItem::HideThisName {
+                    format!("!{}", term)
+                } else {
+                    term
+                };
+                self.search_query = match self.search_query.trim() {
+                    "" => term,
+                    q => format!("{} {}", q, term),
+                };
+                self.on_query_changed();
+            }
+        }
+        Ok(())
+    }
+
+    /// Label/value rows for the details view, from the current snapshot.
+    pub(crate) fn process_details(&self, pid: u32) -> Option<Vec<(&'static str, String)>> {
+        let info = self.snapshot.iter().find(|p| p.pid == pid)?;
+        let mut rows = vec![
+            ("PID", info.pid.to_string()),
+            ("Name", info.name.clone()),
+            ("User", info.user.clone()),
+            ("Status", info.status.clone()),
+            ("CPU", format!("{:.1}%", info.cpu_usage)),
+            ("Memory", format!("{} MB", info.memory / 1024 / 1024)),
+            ("Game", if info.is_game { "yes" } else { "no" }.to_string()),
+            (
+                "Protected",
+                if info.is_protected { "yes" } else { "no" }.to_string(),
+            ),
+        ];
+
+        rows.extend(self.details_extra.iter().cloned());
+
+        Some(rows)
+    }
+
+    /// Take the newest snapshot from the collector, if one arrived.
+    pub fn receive_snapshot(&mut self) {
+        if let Err(err) = self.events.poll() {
+            self.status_message = Some(format!("event log stopped: {:#}", err));
+        }
+        let Some(mut snapshot) = self.feed.latest() else {
+            return;
+        };
+        // Pausing only freezes the list: the collector keeps scanning, so
+        // starts and exits are still logged, leaks and hangs tracked and
+        // alerts checked on every snapshot.
+        if let Err(err) = self.events.update(&snapshot.processes, snapshot.time) {
+            self.status_message = Some(format!("event log stopped: {:#}", err));
+        }
+        self.config.mark(&mut snapshot.processes);
+        let live = self.player().is_none();
+        let leaks = self.leaks.update(&mut snapshot.processes, snapshot.time);
+        // A replay's PIDs aren't this machine's, so nothing to ask about.
+        let source = &mut self.source;
+        let hung = self
+            .hung
+            .update(&mut snapshot.processes, snapshot.time, |pid| {
+                live.then(|| source.wchan(pid)).flatten()
+            });
+        let notices: Vec<String> = leaks
+            .iter()
+            .map(|leak| leak.to_string())
+            .chain(hung.iter().map(|hung| hung.to_string()))
+            .collect();
+        if let Some(first) = notices.first() {
+            self.status_message = Some(match notices.len() {
+                1 => format!("{} {}", glyphs::get().warning, first),
+                n => format!("{} {} (+{} more)", glyphs::get().warning, first, n - 1),
+            });
+        }
+        if live {
+            self.check_alerts(&snapshot.processes, snapshot.time);
+        }
+        if let Some(recorder) = &mut self.recorder {
+            let frame = Frame::new(
+                snapshot.time,
+                snapshot.took,
+                snapshot.totals,
+                snapshot.processes.clone(),
+            );
+            if let Err(err) = recorder.record(&frame) {
+                self.status_message = Some(format!("recording stopped: {:#}", err));
+                self.recorder = None;
+            }
+        }
+        // While frozen the list keeps showing the snapshot it was paused on.
+        if self.paused {
+            return;
+        }
+        self.snapshot = snapshot.processes;
+        self.last_snapshot = snapshot.collected_at;
+        self.collect_took = snapshot.took;
+        self.snapshot_time = snapshot.time;
+        if self.mode == Mode::Details
+            && let Some(pid) = self.details_pid
+        {
+            self.details_extra = self.live_details(pid);
+        }
+        self.apply_view();
+    }
+
+    /// Run the alert rules; the footer shows what fired, newest first.
+    fn check_alerts(&mut self, processes: &[ProcessInfo], time: SystemTime) {
+        let fired = self.alerts.check(processes, time, &mut self.source);
+        let shown: Vec<String> = fired
+            .iter()
+            .filter(|a| a.notify || !a.outcomes.is_empty())
+            .map(|a| a.to_string())
+            .collect();
+        if let Some(first) = shown.first() {
+            let warning = glyphs::get().warning;
+            self.status_message = Some(match shown.len() {
+                1 => format!("{} {}", warning, first),
+                n => format!("{} {} (+{} more)", warning, first, n - 1),
+            });
+        }
+    }
+
+    /// Flag games and protected processes again, after the config changed.
+    /// New snapshots are marked as they come in.
+    fn mark_processes(&mut self) {
+        self.config.mark(&mut self.snapshot);
+    }
+
+    /// Rebuild the visible list from the last snapshot. Cheap enough to run
+    /// on every keystroke, so filter/sort changes don't wait for a refresh.
+    /// The selection stays on the same PID when it's still visible.
+    fn apply_view(&mut self) {
+        let selected_pid = self.selected_process().map(|p| p.pid);
+
+        self.processes = process::select(
+            &self.snapshot,
+            self.active_filter().as_ref(),
+            self.sort_mode,
+            self.sort_reversed,
+        );
+
+        let index = selected_pid
+            .and_then(|pid| self.processes.iter().position(|p| p.pid == pid))
+            .unwrap_or(0);
+        self.list_state.select(Some(index));
+    }
+
+    pub fn selected_process(&self) -> Option<&ProcessInfo> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.processes.get(i))
+    }
+
+    /// Called when the search query changes: re-filter immediately and move
+    /// the selection to the row whose name best matches the typed terms
+    /// (exact name, then prefix, then substring; ties go to the top row).
+    fn on_query_changed(&mut self) {
+        self.update_filter();
+        self.apply_view();
+
+        let terms = self
+            .filter
+            .as_ref()
+            .map(|f| f.name_terms())
+            .unwrap_or_default();
+        let score = |name: &str| -> usize {
+            let name = name.to_lowercase();
+            terms
+                .iter()
+                .map(|t| {
+                    if name == *t || name.strip_suffix(".exe") == Some(t) {
+                        3
+                    } else if name.starts_with(t) {
+                        2
+                    } else if name.contains(t) {
+                        1
+                    } else {
+                        0
+                    }
+                })
+                .sum()
+        };
+
+        let mut best = (0, 0);
+        for (i, p) in self.processes.iter().enumerate() {
+            let s = score(&p.name);
+            if s > best.1 {
+                best = (i, s);
+            }
+        }
+        self.list_state.select(Some(best.0));
+    }
+
+    /// Re-parse the search query. On error the last valid filter stays active
+    /// and the error is shown inline in the footer.
+    fn update_filter(&mut self) {
+        match filter::parse(&self.search_query) {
+            Ok(expr) => {
+                self.filter = expr;
+                self.filter_error = None;
+            }
+            Err(err) => self.filter_error = Some(err),
+        }
+    }
+
+    /// The active view's filter combined with the search query.
+    fn active_filter(&self) -> Option<Expr> {
+        let view = self
+            .views
+            .get(self.active_view)
+            .and_then(|v| v.filter.clone());
+        match (view, self.filter.clone()) {
+            (Some(v), Some(f)) => Some(v.and(f)),
+            (v, f) => v.or(f),
+        }
+    }
+
+    pub(crate) fn columns(&self) -> &[Column] {
+        self.views
+            .get(self.active_view)
+            .map(|v| v.columns.as_slice())
+            .unwrap_or(&Column::DEFAULT)
+    }
+
+    fn switch_view(&mut self, index: usize) {
+        if let Some(view) = self.views.get(index) {
+            self.active_view = index;
+            self.sort_mode = view.sort;
+            self.sort_reversed = false;
+            self.view_state.select(Some(index));
+            self.apply_view();
+            self.list_state.select(Some(0));
+        }
+    }
+
+    /// `g` flips between the built-in games view and "all".
+    fn toggle_games_view(&mut self) {
+        let games = self.views.iter().position(|v| v.name == "games");
+        match games {
+            Some(i) if i != self.active_view => self.switch_view(i),
+            _ => self.switch_view(0),
+        }
+    }
+
+    /// Save the current view filter, search, sort and columns under `name`.
+    fn save_current_view(&mut self) {
+        let name = self.view_name.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let base = self.views.get(self.active_view);
+        let query = match (
+            base.map(|v| v.query.as_str()).unwrap_or(""),
+            self.search_query.trim(),
+        ) {
+            ("", q) | (q, "") => q.to_string(),
+            (v, q) => format!("({}) && ({})", v, q),
+        };
+
+        let result = View::new(&name, &query, self.sort_mode, self.columns()).and_then(|view| {
+            let path = Config::save_view(self.config_watcher.path(), &view)?;
+            self.config_watcher.sync();
+            Ok((view, path))
+        });
+
+        self.status_message = Some(match result {
+            Ok((view, path)) => {
+                let index = match self.views.iter().position(|v| v.name == view.name) {
+                    Some(i) => {
+                        self.views[i] = view;
+                        i
+                    }
+                    None => {
+                        self.views.push(view);
+                        self.views.len() - 1
+                    }
+                };
+                self.search_query.clear();
+                self.update_filter();
+                self.switch_view(index);
+                format!("saved view '{}' to {}", name, path.display())
+            }
+            Err(err) => format!("couldn't save view: {}", err),
+        });
+    }
+
+    fn next_view(&mut self) {
+        let i = self.view_state.selected().unwrap_or(0);
+        self.view_state
+            .select(Some((i + 1) % self.views.len().max(1)));
+    }
+
+    fn previous_view(&mut self) {
+        let i = self.view_state.selected().unwrap_or(0);
+        let len = self.views.len().max(1);
+        self.view_state.select(Some((i + len - 1) % len));
+    }
+
+    /// Apply config file edits while running. A broken file keeps the
+    /// previous settings and reports the error in the footer.
+    pub fn reload_config(&mut self) {
+        let Some(result) = self.config_watcher.poll() else {
+            return;
+        };
+        let loaded = result.and_then(|config| {
+            let views = view::load_views(&config.views)?;
+            let keymap = Keymap::from_config(&config.keys)?;
+            let theme = config.theme.resolve()?;
+            let rules = alerts::load_rules(&config.alerts)?;
+            Ok((config, views, keymap, theme, rules))
+        });
+        match loaded {
+            Ok((config, views, keymap, theme, rules)) => {
+                self.alerts.set_rules(rules);
+                self.leaks.set_config(config.leaks.clone());
+                self.hung.set_config(config.hung.clone());
+                self.keymap = keymap;
+                self.theme = theme;
+                let active = self.views.get(self.active_view).map(|v| v.name.clone());
+                self.views = views;
+                self.active_view = active
+                    .and_then(|name| self.views.iter().position(|v| v.name == name))
+                    .unwrap_or(0);
+                self.view_state.select(Some(self.active_view));
+                // Keep an interval picked at runtime unless the file changed it.
+                if config.refresh_ms != self.config.refresh_ms {
+                    self.set_refresh_rate(config.refresh_rate());
+                }
+                self.config = config;
+                self.mark_processes();
+                self.apply_view();
+                self.status_message = Some("config reloaded".to_string());
+            }
+            Err(err) => self.status_message = Some(format!("config error: {:#}", err)),
+        }
+    }
+
+    fn kill_selected(&mut self) -> Result<()> {
+        if self.read_only() {
+            return Ok(());
+        }
+        if let Some(selected) = self.list_state.selected()
+            && let Some(process) = self.processes.get(selected)
+        {
+            if process.is_protected {
+                self.status_message = Some(format!(
+                    "{} ({}) is protected, not killing it",
+                    process.name, process.pid
+                ));
+                return Ok(());
+            }
+            // Process might require admin privileges or be protected
+            // Silently fail rather than crashing
+            self.kill_pid(process.pid);
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) {
+        if self.processes.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i >= self.processes.len() - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    fn previous(&mut self) {
+        if self.processes.is_empty() {
+            return;
+        }
+        let i = match self.list_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    self.processes.len() - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.list_state.select(Some(i));
+    }
+
+    fn toggle_sort(&mut self) {
+        self.sort_mode = match self.sort_mode {
+            SortMode::Name => SortMode::Cpu,
+            SortMode::Cpu => SortMode::Memory,
+            SortMode::Memory => SortMode::Name,
+            SortMode::Pid | SortMode::User | SortMode::Status | SortMode::Leak | SortMode::Hung => {
+                SortMode::Name
+            }
+        };
+        self.sort_reversed = false;
+        self.apply_view();
+    }
+
+    /// Handle one terminal event. Returns true when the app should quit.
+    pub fn on_event(&mut self, event: Event) -> Result<bool> {
+        match event {
+            Event::Key(key) => self.on_key(key),
+            Event::Mouse(mouse) => {
+                self.on_mouse(mouse)?;
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Fire a key sequence that has waited out its timeout. Returns true
+    /// when the app should quit.
+    pub fn on_timeout(&mut self) -> Result<bool> {
+        match self.keymap.timeout() {
+            Some(action) => self.run_action(action),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether the last key was pressed too recently for another.
+    fn debounced(&self) -> bool {
+        self.last_key_time
+            .is_some_and(|t| t.elapsed() < self.debounce)
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Result<bool> {
+        // Only process Press events, ignore Release and Repeat to prevent double-clicks
+        if key.kind != event::KeyEventKind::Press {
+            return Ok(false);
+        }
+
+        if self.mode == Mode::Normal {
+            self.status_message = None;
+
+            // Debounce to prevent accidental double-presses (except for navigation).
+            // Done per action rather than per key so sequences like `gg` work.
+            if let Outcome::Action(action) = self.keymap.feed(Chord::from_event(key)) {
+                if !action.is_navigation() && self.debounced() {
+                    return Ok(false);
+                }
+                self.last_key_time = Some(Instant::now());
+                if self.run_action(action)? {
+                    return Ok(true);
+                }
+            }
+            return Ok(false);
+        }
+
+        // Debounce to prevent accidental double-presses (except for navigation
+        // and typing: a search, view name, PID or command)
+        let is_navigation = matches!(
+            self.mode,
+            Mode::Search | Mode::SaveView | Mode::Pid | Mode::Command
+        ) || matches!(
+            key.code,
+            KeyCode::Char('j') | KeyCode::Char('k') | KeyCode::Up | KeyCode::Down
+        );
+        if !is_navigation && self.debounced() {
+            return Ok(false);
+        }
+        self.last_key_time = Some(Instant::now());
+
+        match self.mode {
+            Mode::Normal => {}
+            Mode::Search => match key.code {
+                KeyCode::Esc => {
+                    self.finish_search();
+                    self.mode = Mode::Normal;
+                    self.search_query.clear();
+                    self.on_query_changed();
+                }
+                KeyCode::Char(c) => {
+                    self.search_query.push(c);
+                    self.on_query_changed();
+                }
+                KeyCode::Backspace => {
+                    self.search_query.pop();
+                    self.on_query_changed();
+                }
+                KeyCode::Enter => {
+                    self.finish_search();
+                    self.mode = Mode::Normal;
+                }
+                _ => {}
+            },
+            Mode::Pid => match key.code {
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Char(c) if c.is_ascii_digit() => self.pid_input.push(c),
+                KeyCode::Backspace => {
+                    self.pid_input.pop();
+                }
+                KeyCode::Enter => {
+                    self.mode = Mode::Normal;
+                    match self.pid_input.parse() {
+                        Ok(pid) => self.jump_to_pid(pid),
+                        Err(_) => {
+                            self.status_message = Some(format!("not a PID: {}", self.pid_input))
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Mode::Command => match key.code {
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Enter => {
+                    self.mode = Mode::Normal;
+                    let line = self.command_line.submit();
+                    if !line.is_empty() && self.run_command(&line)? {
+                        return Ok(true);
+                    }
+                }
+                KeyCode::Tab => self.command_line.complete(&self.snapshot, &self.views),
+                KeyCode::Up => self.command_line.history_prev(),
+                KeyCode::Down => self.command_line.history_next(),
+                KeyCode::Backspace if self.command_line.input.is_empty() => {
+                    self.mode = Mode::Normal
+                }
+                KeyCode::Backspace => self.command_line.pop(),
+                KeyCode::Char(c) => self.command_line.push(c),
+                _ => {}
+            },
+            Mode::Help => {
+                let toggled =
+                    self.keymap.feed(Chord::from_event(key)) == Outcome::Action(Action::Help);
+                if key.code == KeyCode::Esc || toggled {
+                    self.keymap.reset();
+                    self.mode = Mode::Normal;
+                }
+            }
+            Mode::Views => match key.code {
+                KeyCode::Esc | KeyCode::Char('v') => self.mode = Mode::Normal,
+                KeyCode::Char('j') | KeyCode::Down => self.next_view(),
+                KeyCode::Char('k') | KeyCode::Up => self.previous_view(),
+                KeyCode::Enter => {
+                    self.switch_view(self.view_state.selected().unwrap_or(0));
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Char(c @ '1'..='9') => {
+                    self.switch_view(c as usize - '1' as usize);
+                    self.mode = Mode::Normal;
+                }
+                KeyCode::Char('n') => {
+                    self.view_name.clear();
+                    self.mode = Mode::SaveView;
+                }
+                _ => {}
+            },
+            Mode::SaveView => match key.code {
+                KeyCode::Esc => self.mode = Mode::Views,
+                KeyCode::Char(c) => self.view_name.push(c),
+                KeyCode::Backspace => {
+                    self.view_name.pop();
+                }
+                KeyCode::Enter => {
+                    self.save_current_view();
+                    self.mode = Mode::Normal;
+                }
+                _ => {}
+            },
+            Mode::Details | Mode::Diff => match key.code {
+                KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.mode = Mode::Normal,
+                _ => {}
+            },
+            Mode::Menu => match key.code {
+                KeyCode::Esc | KeyCode::Char('m') => self.close_menu(),
+                KeyCode::Char('j') | KeyCode::Down => {
+                    if let Some(menu) = &mut self.menu {
+                        menu.next();
+                    }
+                }
+                KeyCode::Char('k') | KeyCode::Up => {
+                    if let Some(menu) = &mut self.menu {
+                        menu.previous();
+                    }
+                }
+                KeyCode::Enter => {
+                    if let Some(item) = self.menu.as_ref().map(|m| m.selected()) {
+                        self.run_menu_item(item)?;
+                    }
+                }
+                _ => {}
+            },
+        }
+        Ok(false)
+    }
+}
+
+/// Draw and handle input until the user quits.
+pub fn run_app<B: Backend, S: ProcessSource + Clone + 'static>(
+    terminal: &mut Terminal<B>,
+    app: &mut App<S>,
+) -> Result<()> {
+    loop {
+        app.reload_config();
+        app.receive_snapshot();
+        // Keys already queued belong to the sequence, however long the refresh took.
+        if !event::poll(Duration::ZERO)? && app.on_timeout()? {
+            return Ok(());
+        }
+        terminal.draw(|f| ui(f, app))?;
+
+        if event::poll(FRAME_INTERVAL)? && app.on_event(event::read()?)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Write processes as CSV with a header row.
+fn write_csv(path: &Path, processes: &[ProcessInfo]) -> Result<()> {
+    let write = || -> std::io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        output::write_csv(&mut out, processes)?;
+        out.flush()
+    };
+    write().with_context(|| format!("couldn't write {}", path.display()))
+}
@@ -0,0 +1,84 @@
+//! `--batch`: print a snapshot every refresh instead of drawing the TUI,
+//! like `top -b`. Runs on the same collector thread as the TUI, so what
+//! lands in a CI log is exactly what the UI would have shown.
+
+use crate::collector::Collector;
+use crate::command::format_duration;
+use crate::config::Config;
+use crate::output::{self, Format};
+use crate::process;
+use crate::record::{Frame, Recorder};
+use crate::source::ProcessSource;
+use crate::view::View;
+use anyhow::{Result, bail};
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+pub struct Batch {
+    /// `Table` or `Ndjson`.
+    pub format: Format,
+    /// Stop after this many snapshots; run until killed when `None`.
+    pub iterations: Option<u64>,
+    /// Rows per snapshot.
+    pub limit: Option<usize>,
+    /// Also write every snapshot, unfiltered, here.
+    pub recorder: Option<Recorder>,
+}
+
+pub fn run<S: ProcessSource + 'static>(
+    source: S,
+    config: &Config,
+    view: &View,
+    interval: Duration,
+    batch: &mut Batch,
+    out: &mut dyn Write,
+) -> Result<()> {
+    if !matches!(batch.format, Format::Table | Format::Ndjson) {
+        bail!("batch mode prints table or ndjson");
+    }
+    let collector = Collector::spawn(source, interval);
+    let started = Instant::now();
+    let mut count = 0;
+
+    while batch.iterations.is_none_or(|n| count < n) {
+        let Some(snapshot) = collector.next() else {
+            bail!("process collection stopped");
+        };
+        count += 1;
+        let mut processes = snapshot.processes;
+        config.mark(&mut processes);
+        if let Some(recorder) = &mut batch.recorder {
+            let frame = Frame::new(
+                snapshot.time,
+                snapshot.took,
+                snapshot.totals,
+                processes.clone(),
+            );
+            recorder.record(&frame)?;
+        }
+        let mut shown = process::select(&processes, view.filter.as_ref(), view.sort, false);
+        if let Some(limit) = batch.limit {
+            shown.truncate(limit);
+        }
+
+        if batch.format == Format::Table {
+            writeln!(
+                out,
+                "--- #{} +{} | {} processes | scan took {}",
+                count,
+                format_duration(started.elapsed()),
+                processes.len(),
+                format_duration(snapshot.took)
+            )?;
+            output::write(out, Format::Table, &shown, &view.columns)?;
+            writeln!(out)?;
+        } else {
+            let frame = Frame::new(snapshot.time, snapshot.took, snapshot.totals, shown);
+            serde_json::to_writer(&mut *out, &frame)?;
+            writeln!(out)?;
+        }
+        // Someone is probably tailing this.
+        out.flush()?;
+    }
+    Ok(())
+}
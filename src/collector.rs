@@ -0,0 +1,120 @@
+//! Background process collection. A worker thread owns a [`ProcessSource`],
+//! scans it on the configured interval and sends each finished snapshot over
+//! a channel, so a slow scan never blocks drawing or input.
+
+use crate::ProcessInfo;
+use crate::source::{ProcessSource, Totals};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// One complete collection pass. `is_game`/`is_protected` are left false;
+/// the app marks them with its current config.
+pub struct Snapshot {
+    pub processes: Vec<ProcessInfo>,
+    pub totals: Totals,
+    pub collected_at: Instant,
+    /// Wall clock time of the same moment, for anything written down.
+    pub time: SystemTime,
+    /// How long the refresh took.
+    pub took: Duration,
+}
+
+enum Control {
+    Interval(Duration),
+    Pause(bool),
+    RefreshNow,
+}
+
+/// Handle to the worker. Dropping it stops the thread.
+pub struct Collector {
+    control: Sender<Control>,
+    snapshots: Receiver<Snapshot>,
+}
+
+impl Collector {
+    pub fn spawn<S: ProcessSource + 'static>(source: S, interval: Duration) -> Self {
+        let (control_tx, control_rx) = mpsc::channel();
+        let (snapshot_tx, snapshot_rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("collector".to_string())
+            .spawn(move || run(source, interval, control_rx, snapshot_tx))
+            .expect("failed to spawn collector thread");
+        Self {
+            control: control_tx,
+            snapshots: snapshot_rx,
+        }
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        let _ = self.control.send(Control::Interval(interval));
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let _ = self.control.send(Control::Pause(paused));
+    }
+
+    /// Collect now instead of waiting out the interval.
+    pub fn refresh_now(&self) {
+        let _ = self.control.send(Control::RefreshNow);
+    }
+
+    /// Newest snapshot published since the last call, skipping older ones.
+    pub fn latest(&self) -> Option<Snapshot> {
+        self.snapshots.try_iter().last()
+    }
+
+    /// Wait for the next snapshot. `None` if the worker is gone.
+    pub fn next(&self) -> Option<Snapshot> {
+        self.snapshots.recv().ok()
+    }
+}
+
+fn run<S: ProcessSource>(
+    mut source: S,
+    mut interval: Duration,
+    control: Receiver<Control>,
+    snapshots: Sender<Snapshot>,
+) {
+    let mut paused = false;
+
+    loop {
+        let started = Instant::now();
+        // A failed scan just isn't published; the UI marks the list stale.
+        if !paused && let Ok(processes) = source.snapshot() {
+            let snapshot = Snapshot {
+                processes,
+                totals: source.totals(),
+                collected_at: Instant::now(),
+                time: SystemTime::now(),
+                took: started.elapsed(),
+            };
+            if snapshots.send(snapshot).is_err() {
+                return;
+            }
+        }
+
+        // Sleep until the next pass, still reacting to control messages.
+        loop {
+            let wait = if paused {
+                Duration::from_secs(3600)
+            } else {
+                (started + interval).saturating_duration_since(Instant::now())
+            };
+            match control.recv_timeout(wait) {
+                Ok(Control::Interval(new)) => interval = new,
+                Ok(Control::Pause(p)) => {
+                    let resumed = paused && !p;
+                    paused = p;
+                    if resumed {
+                        break;
+                    }
+                }
+                Ok(Control::RefreshNow) => break,
+                Err(RecvTimeoutError::Timeout) if paused => {}
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
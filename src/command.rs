@@ -0,0 +1,398 @@
+//! Ex-style command line opened with `:`. Parsing, tab completion and
+//! history live here; `App::run_command` carries the commands out.
+//!
+//! Commands can be shortened to any unambiguous prefix (`:q`, `:so cpu`).
+
+use crate::view::View;
+use crate::{ProcessInfo, SortMode};
+use anyhow::{Result, anyhow, bail};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Command names, also the candidates for completing the first word.
+pub const COMMANDS: [&str; 12] = [
+    "diff", "export", "filter", "help", "kill", "pid", "quit", "renice", "seek", "set", "sort",
+    "view",
+];
+
+const SORT_COLUMNS: [&str; 8] = [
+    "cpu", "hung", "leak", "mem", "name", "pid", "status", "user",
+];
+const SETTINGS: [&str; 3] = ["ascii", "refresh", "theme"];
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// PIDs or process names; empty means the selected row.
+    Kill(Vec<String>),
+    Sort {
+        mode: SortMode,
+        /// `None` keeps the column's usual direction.
+        descending: Option<bool>,
+    },
+    Filter(String),
+    Renice {
+        nice: i32,
+        pid: Option<u32>,
+    },
+    ExportCsv(PathBuf),
+    Set(Setting),
+    View(String),
+    Pid(u32),
+    /// Jump within a replay.
+    Seek(Seek),
+    /// `None` shows what changed since the baseline; `mark` makes the
+    /// current snapshot the baseline; anything else is a file to load it
+    /// from (`PATH[@TIME]`).
+    Diff(Option<String>),
+    Help,
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Setting {
+    Refresh(Duration),
+    Theme(String),
+    Ascii(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seek {
+    /// Local time of day, in seconds since midnight.
+    Time(u32),
+    /// How far into the recording, 0-100.
+    Percent(f64),
+}
+
+/// One-line usage for each command, shown when arguments are wrong.
+fn usage(name: &str) -> &'static str {
+    match name {
+        "diff" => "diff [mark | PATH[@HH:MM|@N%]]",
+        "export" => "export csv PATH",
+        "filter" => "filter QUERY (empty clears)",
+        "kill" => "kill [PID|NAME ...]",
+        "pid" => "pid PID",
+        "renice" => "renice NICE [PID]",
+        "seek" => "seek HH:MM[:SS] | N%",
+        "set" => "set refresh 500ms | set theme NAME | set ascii on|off",
+        "sort" => "sort pid|name|user|status|cpu|mem|leak|hung [asc|desc]",
+        "view" => "view NAME|NUMBER",
+        _ => "",
+    }
+}
+
+/// Expand a possibly shortened command name.
+fn resolve(word: &str) -> Result<&'static str> {
+    let word = word.to_lowercase();
+    if let Some(exact) = COMMANDS.iter().find(|c| **c == word) {
+        return Ok(exact);
+    }
+    let matches: Vec<&str> = COMMANDS
+        .iter()
+        .copied()
+        .filter(|c| c.starts_with(&word))
+        .collect();
+    match matches.as_slice() {
+        [one] => Ok(one),
+        [] => bail!("unknown command '{}' (try {})", word, COMMANDS.join(", ")),
+        many => bail!("'{}' is ambiguous: {}", word, many.join(", ")),
+    }
+}
+
+pub fn parse(line: &str) -> Result<Command> {
+    let line = line.trim().trim_start_matches(':').trim_start();
+    let (word, rest) = match line.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (line, ""),
+    };
+    if word.is_empty() {
+        bail!("empty command");
+    }
+    let name = resolve(word)?;
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let bad = || anyhow!("usage: {}", usage(name));
+
+    let command = match name {
+        "quit" => Command::Quit,
+        "help" => Command::Help,
+        "kill" => Command::Kill(args.iter().map(|a| a.to_string()).collect()),
+        "filter" => Command::Filter(rest.to_string()),
+        "view" if !rest.is_empty() => Command::View(rest.to_string()),
+        "pid" => match args.as_slice() {
+            [pid] => Command::Pid(pid.parse().map_err(|_| bad())?),
+            _ => return Err(bad()),
+        },
+        "sort" => {
+            let (column, order) = match args.as_slice() {
+                [column] => (column, None),
+                [column, order] => (column, Some(order)),
+                _ => return Err(bad()),
+            };
+            let mode = SortMode::from_name(column)
+                .ok_or_else(|| anyhow!("unknown column '{}' ({})", column, usage(name)))?;
+            let descending = match order.map(|o| o.to_lowercase()) {
+                None => None,
+                Some(o) if o == "desc" => Some(true),
+                Some(o) if o == "asc" => Some(false),
+                Some(_) => return Err(bad()),
+            };
+            Command::Sort { mode, descending }
+        }
+        "renice" => {
+            let (nice, pid) = match args.as_slice() {
+                [nice] => (nice, None),
+                [nice, pid] => (nice, Some(pid.parse().map_err(|_| bad())?)),
+                _ => return Err(bad()),
+            };
+            let nice: i32 = nice.trim_start_matches('+').parse().map_err(|_| bad())?;
+            if !(-20..=19).contains(&nice) {
+                bail!("nice value {} is out of range (-20 to 19)", nice);
+            }
+            Command::Renice { nice, pid }
+        }
+        "seek" => match args.as_slice() {
+            [at] => Command::Seek(parse_seek(at).ok_or_else(bad)?),
+            _ => return Err(bad()),
+        },
+        "diff" if rest.is_empty() => Command::Diff(None),
+        "diff" => Command::Diff(Some(rest.to_string())),
+        "export" => match args.as_slice() {
+            [format, path] if format.eq_ignore_ascii_case("csv") => {
+                Command::ExportCsv(PathBuf::from(path))
+            }
+            [format, _] => bail!("unknown export format '{}' (csv)", format),
+            _ => return Err(bad()),
+        },
+        "set" => match args.as_slice() {
+            ["refresh", value] => {
+                let rate = parse_duration(value)?;
+                if !(Duration::from_millis(100)..=Duration::from_secs(60)).contains(&rate) {
+                    bail!("refresh {} is out of range (100ms to 60s)", value);
+                }
+                Command::Set(Setting::Refresh(rate))
+            }
+            ["theme", theme] => Command::Set(Setting::Theme(theme.to_string())),
+            ["ascii", value] => Command::Set(Setting::Ascii(match *value {
+                "on" | "true" | "yes" => true,
+                "off" | "false" | "no" => false,
+                _ => return Err(bad()),
+            })),
+            _ => return Err(bad()),
+        },
+        _ => return Err(bad()),
+    };
+    Ok(command)
+}
+
+/// `21:04`, `21:04:30` or `50%`.
+pub fn parse_seek(s: &str) -> Option<Seek> {
+    if let Some(percent) = s.strip_suffix('%') {
+        let percent: f64 = percent.parse().ok()?;
+        return (0.0..=100.0)
+            .contains(&percent)
+            .then_some(Seek::Percent(percent));
+    }
+    let parts: Vec<u32> = s
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let (h, m, sec) = match parts.as_slice() {
+        [h, m] => (*h, *m, 0),
+        [h, m, sec] => (*h, *m, *sec),
+        _ => return None,
+    };
+    (h < 24 && m < 60 && sec < 60).then_some(Seek::Time(h * 3600 + m * 60 + sec))
+}
+
+/// `500ms`, `2s`, `1.5s`, `10m`, `2h`, `1d`, or a bare number of
+/// milliseconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim().to_lowercase();
+    let (number, scale) = if let Some(n) = s.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = s.strip_suffix('s') {
+        (n, 1000.0)
+    } else if let Some(n) = s.strip_suffix('m') {
+        (n, 60_000.0)
+    } else if let Some(n) = s.strip_suffix('h') {
+        (n, 3_600_000.0)
+    } else if let Some(n) = s.strip_suffix('d') {
+        (n, 86_400_000.0)
+    } else {
+        (s.as_str(), 1.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(n) if n.is_finite() && n >= 0.0 => Ok(Duration::from_millis((n * scale) as u64)),
+        _ => bail!("'{}' is not a duration (like 500ms or 2s)", s),
+    }
+}
+
+/// Short form for the header: `500ms`, `1s`, `2.5s`.
+pub fn format_duration(d: Duration) -> String {
+    let ms = d.as_millis();
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms.is_multiple_of(1000) {
+        format!("{}s", ms / 1000)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+/// Tab cycling state: the text before the word being completed and the
+/// candidates for it.
+struct Completion {
+    base: String,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+/// Text being edited on the `:` line plus its history.
+#[derive(Default)]
+pub struct CommandLine {
+    pub input: String,
+    history: Vec<String>,
+    /// Position while browsing history with up/down.
+    history_pos: Option<usize>,
+    /// What was typed before browsing history, restored past the newest entry.
+    draft: String,
+    completion: Option<Completion>,
+}
+
+impl CommandLine {
+    /// Start a fresh line, keeping history.
+    pub fn open(&mut self) {
+        self.input.clear();
+        self.history_pos = None;
+        self.completion = None;
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.completion = None;
+        self.input.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.completion = None;
+        self.input.pop();
+    }
+
+    /// Take the finished line and remember it.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.completion = None;
+        self.history_pos = None;
+        if !line.is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > HISTORY_LIMIT {
+                self.history.remove(0);
+            }
+        }
+        line
+    }
+
+    pub fn history_prev(&mut self) {
+        if self.history.is_empty() {
+            return;
+        }
+        let pos = match self.history_pos {
+            None => {
+                self.draft = self.input.clone();
+                self.history.len() - 1
+            }
+            Some(pos) => pos.saturating_sub(1),
+        };
+        self.history_pos = Some(pos);
+        self.input = self.history[pos].clone();
+        self.completion = None;
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(pos) = self.history_pos else {
+            return;
+        };
+        if pos + 1 < self.history.len() {
+            self.history_pos = Some(pos + 1);
+            self.input = self.history[pos + 1].clone();
+        } else {
+            self.history_pos = None;
+            self.input = std::mem::take(&mut self.draft);
+        }
+        self.completion = None;
+    }
+
+    /// Candidates of the completion in progress and the one inserted.
+    pub fn candidates(&self) -> Option<(&[String], usize)> {
+        self.completion
+            .as_ref()
+            .map(|c| (c.candidates.as_slice(), c.index))
+    }
+
+    /// Complete the word under the cursor. A single candidate is inserted
+    /// with a trailing space; several are cycled through on each press.
+    pub fn complete(&mut self, processes: &[ProcessInfo], views: &[View]) {
+        if let Some(c) = &mut self.completion {
+            c.index = (c.index + 1) % c.candidates.len();
+            self.input = format!("{}{}", c.base, c.candidates[c.index]);
+            return;
+        }
+
+        let split = self
+            .input
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let (base, word) = self.input.split_at(split);
+        let (base, word) = (base.to_string(), word.to_lowercase());
+        let words: Vec<&str> = base.split_whitespace().collect();
+
+        let pool: Vec<String> = match words.as_slice() {
+            [] => COMMANDS.iter().map(|c| c.to_string()).collect(),
+            [first, rest @ ..] => match (resolve(first), rest.len()) {
+                (Ok("kill"), _) => {
+                    let mut names: Vec<String> = processes.iter().map(|p| p.name.clone()).collect();
+                    names.sort_by_key(|n| n.to_lowercase());
+                    names.dedup();
+                    pids(processes).into_iter().chain(names).collect()
+                }
+                (Ok("pid"), 0) | (Ok("renice"), 1) => pids(processes),
+                (Ok("sort"), 0) => SORT_COLUMNS.iter().map(|s| s.to_string()).collect(),
+                (Ok("sort"), 1) => vec!["asc".to_string(), "desc".to_string()],
+                (Ok("export"), 0) => vec!["csv".to_string()],
+                (Ok("set"), 0) => SETTINGS.iter().map(|s| s.to_string()).collect(),
+                (Ok("set"), 1) if rest[0] == "theme" => crate::theme::BUILTIN_THEMES
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+                (Ok("set"), 1) if rest[0] == "ascii" => {
+                    vec!["on".to_string(), "off".to_string()]
+                }
+                (Ok("view"), 0) => views.iter().map(|v| v.name.clone()).collect(),
+                _ => Vec::new(),
+            },
+        };
+
+        let candidates: Vec<String> = pool
+            .into_iter()
+            .filter(|c| c.to_lowercase().starts_with(&word))
+            .collect();
+        match candidates.len() {
+            0 => {}
+            1 => self.input = format!("{}{} ", base, candidates[0]),
+            _ => {
+                self.input = format!("{}{}", base, candidates[0]);
+                self.completion = Some(Completion {
+                    base,
+                    candidates,
+                    index: 0,
+                });
+            }
+        }
+    }
+}
+
+fn pids(processes: &[ProcessInfo]) -> Vec<String> {
+    let mut pids: Vec<u32> = processes.iter().map(|p| p.pid).collect();
+    pids.sort_unstable();
+    pids.into_iter().map(|p| p.to_string()).collect()
+}
@@ -0,0 +1,371 @@
+//! User configuration, read from `<config dir>/procsnipe/config.toml` or the
+//! path given with `--config`. Every key is optional; the defaults match the
+//! behaviour procsnipe had before there was a config file. See
+//! `config.example.toml` for the documented schema.
+
+use crate::ProcessInfo;
+use crate::alerts::{AlertConfig, Rule};
+use crate::hung::HungConfig;
+use crate::keymap::Keymap;
+use crate::leaks::LeakConfig;
+use crate::theme::ThemeConfig;
+use crate::view::{View, ViewConfig};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_GAME_INDICATORS: &[&str] = &[
+    "game",
+    "steam",
+    "epic",
+    "uplay",
+    "origin",
+    "riot",
+    "valorant",
+    "league",
+    "csgo",
+    "cs2",
+    "dota",
+    "apex",
+    "fortnite",
+    "minecraft",
+    "roblox",
+    "gta",
+    "unity",
+    "unreal",
+    "dx11",
+    "dx12",
+    "vulkan",
+];
+
+/// Processes the kill key refuses to touch: killing any of them takes the
+/// session or the whole machine down with it. Matched case-insensitively
+/// against the full process name.
+pub const DEFAULT_PROTECTED: &[&str] = &[
+    "system",
+    "registry",
+    "smss.exe",
+    "csrss.exe",
+    "wininit.exe",
+    "winlogon.exe",
+    "services.exe",
+    "lsass.exe",
+    "dwm.exe",
+    "init",
+    "systemd",
+    "kthreadd",
+    "launchd",
+    "kernel_task",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How often the process list is re-collected, in milliseconds.
+    pub refresh_ms: u64,
+    pub colors: ColorThresholds,
+    pub tray: TrayConfig,
+    pub games: GameConfig,
+    pub leaks: LeakConfig,
+    pub hung: HungConfig,
+    pub theme: ThemeConfig,
+    /// Names that can't be killed or reniced from procsnipe. Replaces the default list.
+    pub protected: Vec<String>,
+    pub views: Vec<ViewConfig>,
+    /// Rules checked against every snapshot, in the TUI and the tray.
+    pub alerts: Vec<AlertConfig>,
+    /// Action name to key bindings, e.g. `kill = ["dd", "delete"]`.
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+/// CPU percentages at which rows turn yellow (`cpu_medium`) and red (`cpu_high`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorThresholds {
+    pub cpu_high: f32,
+    pub cpu_medium: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrayConfig {
+    /// A process above this CPU percentage triggers a tray notification.
+    /// Only used when there are no `[[alerts]]`; the TUI has no fallback.
+    pub cpu_alert: f32,
+    /// Minimum seconds between two notifications, likewise.
+    pub cooldown_secs: u64,
+    /// Seconds between background scans.
+    pub poll_secs: u64,
+}
+
+/// Substrings that mark a process as a game. `indicators` replaces the
+/// built-in list, `extra` is appended to it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub indicators: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            refresh_ms: 1000,
+            colors: ColorThresholds::default(),
+            tray: TrayConfig::default(),
+            games: GameConfig::default(),
+            leaks: LeakConfig::default(),
+            hung: HungConfig::default(),
+            theme: ThemeConfig::default(),
+            protected: DEFAULT_PROTECTED.iter().map(|s| s.to_string()).collect(),
+            views: Vec::new(),
+            alerts: Vec::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for ColorThresholds {
+    fn default() -> Self {
+        Self {
+            cpu_high: 50.0,
+            cpu_medium: 20.0,
+        }
+    }
+}
+
+impl Default for TrayConfig {
+    fn default() -> Self {
+        Self {
+            cpu_alert: 80.0,
+            cooldown_secs: 60,
+            poll_secs: 5,
+        }
+    }
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            indicators: DEFAULT_GAME_INDICATORS
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            extra: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Default location, e.g. `~/.config/procsnipe/config.toml` on Linux or
+    /// `%APPDATA%\procsnipe\config.toml` on Windows.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("procsnipe").join("config.toml"))
+    }
+
+    /// The file to use: `--config` if given, otherwise the default location.
+    pub fn resolve_path(explicit: Option<&Path>) -> Option<PathBuf> {
+        explicit.map(Path::to_path_buf).or_else(Self::default_path)
+    }
+
+    /// Load the config. A missing default file means defaults; a missing
+    /// `--config` file is an error since the user asked for it explicitly.
+    pub fn load(explicit: Option<&Path>) -> Result<Self> {
+        let Some(path) = Self::resolve_path(explicit) else {
+            return Ok(Self::default());
+        };
+        if !path.exists() {
+            if explicit.is_some() {
+                bail!("config file {} does not exist", path.display());
+            }
+            return Ok(Self::default());
+        }
+        Self::load_file(&path)
+    }
+
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let config: Config = toml::from_str(&text)
+            .with_context(|| format!("invalid config in {}", path.display()))?;
+        config
+            .validate()
+            .with_context(|| format!("invalid config in {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Checks serde can't express: ranges and relationships between keys.
+    pub fn validate(&self) -> Result<()> {
+        if !(100..=60_000).contains(&self.refresh_ms) {
+            bail!(
+                "refresh_ms = {} is out of range (100 to 60000 milliseconds)",
+                self.refresh_ms
+            );
+        }
+
+        let c = &self.colors;
+        if c.cpu_medium < 0.0 || c.cpu_high < 0.0 {
+            bail!("colors.cpu_medium and colors.cpu_high can't be negative");
+        }
+        if c.cpu_medium >= c.cpu_high {
+            bail!(
+                "colors.cpu_medium ({}) must be below colors.cpu_high ({})",
+                c.cpu_medium,
+                c.cpu_high
+            );
+        }
+
+        if self.tray.cpu_alert <= 0.0 {
+            bail!(
+                "tray.cpu_alert = {} must be above 0 (it's a CPU percentage)",
+                self.tray.cpu_alert
+            );
+        }
+        if self.tray.poll_secs == 0 {
+            bail!("tray.poll_secs must be at least 1");
+        }
+
+        for indicator in self.games.indicators.iter().chain(&self.games.extra) {
+            if indicator.trim().is_empty() {
+                bail!("games.indicators and games.extra can't contain empty strings");
+            }
+        }
+
+        if self.leaks.mb_per_min <= 0.0 {
+            bail!(
+                "leaks.mb_per_min = {} must be above 0 (set leaks.enabled = false to turn it off)",
+                self.leaks.mb_per_min
+            );
+        }
+        if self.leaks.window_mins == 0 {
+            bail!("leaks.window_mins must be at least 1");
+        }
+        for (name, secs) in [
+            ("blocked_secs", self.hung.blocked_secs),
+            ("zombie_secs", self.hung.zombie_secs),
+            ("stuck_secs", self.hung.stuck_secs),
+        ] {
+            if secs == 0 {
+                bail!(
+                    "hung.{} must be at least 1 (set hung.enabled = false to turn it off)",
+                    name
+                );
+            }
+        }
+
+        for view in &self.views {
+            View::from_config(view).context("in [[views]]")?;
+        }
+        for alert in &self.alerts {
+            Rule::from_config(alert).context("in [[alerts]]")?;
+        }
+
+        Keymap::from_config(&self.keys)?;
+        self.theme.resolve()?;
+
+        Ok(())
+    }
+
+    pub fn refresh_rate(&self) -> Duration {
+        Duration::from_millis(self.refresh_ms)
+    }
+
+    /// Lower-cased game indicators with `extra` applied.
+    pub fn game_indicators(&self) -> Vec<String> {
+        self.games
+            .indicators
+            .iter()
+            .chain(&self.games.extra)
+            .map(|s| s.to_lowercase())
+            .collect()
+    }
+
+    /// Set `is_game` and `is_protected` on freshly collected processes.
+    pub fn mark(&self, processes: &mut [ProcessInfo]) {
+        let indicators = self.game_indicators();
+        for p in processes {
+            p.is_game = is_game_process(&p.name, &indicators);
+            p.is_protected = self.is_protected(p.pid, &p.name);
+        }
+    }
+
+    /// PIDs 0 and 1, procsnipe itself, and anything on the protected list.
+    pub fn is_protected(&self, pid: u32, name: &str) -> bool {
+        pid <= 1
+            || pid == std::process::id()
+            || self.protected.iter().any(|p| p.eq_ignore_ascii_case(name))
+    }
+
+    /// Append a view to the config file. Appending keeps whatever comments
+    /// and layout the user already has.
+    pub fn save_view(path: Option<&Path>, view: &View) -> Result<PathBuf> {
+        let path = Self::resolve_path(path).context("no config directory on this system")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        file.write_all(view.to_toml().as_bytes())
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+/// True if any of `indicators` (lower-case) appears in `name`.
+pub fn is_game_process(name: &str, indicators: &[String]) -> bool {
+    let name_lower = name.to_lowercase();
+    indicators
+        .iter()
+        .any(|indicator| name_lower.contains(indicator.as_str()))
+}
+
+/// Polls the config file's modification time so changes can be picked up
+/// without restarting.
+pub struct ConfigWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(explicit: Option<&Path>) -> Self {
+        let path = Config::resolve_path(explicit);
+        let modified = path.as_deref().and_then(mtime);
+        Self { path, modified }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Forget about changes we made ourselves, e.g. after saving a view.
+    pub fn sync(&mut self) {
+        self.modified = self.path.as_deref().and_then(mtime);
+    }
+
+    /// Returns the freshly loaded config if the file changed since the last
+    /// call. A file that was deleted reverts to defaults.
+    pub fn poll(&mut self) -> Option<Result<Config>> {
+        let path = self.path.as_deref()?;
+        let modified = mtime(path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(match modified {
+            Some(_) => Config::load_file(path),
+            None => Ok(Config::default()),
+        })
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
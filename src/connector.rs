@@ -0,0 +1,361 @@
+//! Exact process starts and exits from the Linux proc connector: a netlink
+//! socket the kernel reports every fork, exec and exit on, as it happens.
+//! Catches what polling misses (a shell script that's gone in 5ms), with
+//! exact times and exit codes, for the [`EventLog`](crate::events::EventLog).
+//!
+//! Listening needs root (`CAP_NET_ADMIN`). When [`ProcConnector::open`]
+//! fails, the event log carries on comparing snapshots instead.
+
+use anyhow::Result;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, atomic::AtomicBool};
+
+/// One kernel report, for a process (threads are skipped).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcEvent {
+    /// Unix milliseconds.
+    pub time_ms: u64,
+    pub pid: u32,
+    /// From `/proc/<pid>/comm` as the event was read; gone if the process
+    /// was already reaped.
+    pub name: Option<String>,
+    pub kind: ProcEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProcEventKind {
+    /// A new process. Its name is still the parent's until it execs.
+    Fork { parent: u32 },
+    /// Now running a different program.
+    Exec,
+    /// Exited with `code`, or was killed by `signal`.
+    Exit {
+        code: Option<i32>,
+        signal: Option<i32>,
+    },
+}
+
+const NLMSG_HEADER: usize = 16;
+const NLMSG_DONE: u16 = 3;
+const CN_MSG_HEADER: usize = 20;
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_EVENT_NONE: u32 = 0;
+const PROC_EVENT_FORK: u32 = 1;
+const PROC_EVENT_EXEC: u32 = 2;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+/// Handle to the listening thread. Dropping it stops the thread.
+pub struct ProcConnector {
+    events: Receiver<ProcEvent>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ProcConnector {
+    /// Events received since the last call, oldest first.
+    pub fn drain(&self) -> Vec<ProcEvent> {
+        self.events.try_iter().collect()
+    }
+}
+
+impl Drop for ProcConnector {
+    fn drop(&mut self) {
+        self.stop.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl ProcConnector {
+    pub fn open() -> Result<Self> {
+        anyhow::bail!("the proc connector is Linux-only")
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProcConnector {
+    /// Subscribe and wait for the kernel to confirm. Fails when we aren't
+    /// allowed to listen.
+    pub fn open() -> Result<Self> {
+        use anyhow::{Context, bail};
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::{Duration, Instant};
+
+        let socket = linux::subscribe().context("couldn't listen to the proc connector")?;
+        // The kernel acks the subscription with an empty event, maybe after
+        // a few others; no ack in time means it didn't take.
+        let mut buf = vec![0u8; 64 * 1024];
+        let deadline = Instant::now() + Duration::from_secs(1);
+        let err = loop {
+            match linux::recv(&socket, &mut buf).context("couldn't read the proc connector")? {
+                Some(n) => {
+                    if let Some(err) = ack(&buf[..n]) {
+                        break err;
+                    }
+                }
+                None => bail!("no answer from the proc connector"),
+            }
+            if Instant::now() > deadline {
+                bail!("no answer from the proc connector");
+            }
+        };
+        if err != 0 {
+            bail!(
+                "the proc connector refused: {}",
+                std::io::Error::from_raw_os_error(err as i32)
+            );
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        thread::Builder::new()
+            .name("proc-connector".to_string())
+            .spawn(move || linux::listen(socket, tx, stopped))
+            .context("couldn't start the proc connector thread")?;
+        Ok(Self { events: rx, stop })
+    }
+}
+
+/// Decode one datagram. `boot_ms` is the Unix time of the monotonic clock's
+/// zero, which the kernel's timestamps count from. Names are left empty.
+pub fn parse(datagram: &[u8], boot_ms: u64) -> Vec<ProcEvent> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while let Some(header) = datagram.get(offset..offset + NLMSG_HEADER) {
+        let len = u32_at(header, 0) as usize;
+        let kind = u16::from_ne_bytes([header[4], header[5]]);
+        let Some(message) = datagram
+            .get(offset..offset + len)
+            .filter(|_| len >= NLMSG_HEADER)
+        else {
+            break;
+        };
+        if kind == NLMSG_DONE
+            && let Some(event) = proc_event(&message[NLMSG_HEADER..])
+            && let Some(event) = decode(event, boot_ms)
+        {
+            events.push(event);
+        }
+        // Messages are padded to 4 bytes.
+        offset += (len + 3) & !3;
+    }
+    events
+}
+
+/// The `proc_event` inside a connector message, if that's what it is.
+fn proc_event(cn_msg: &[u8]) -> Option<&[u8]> {
+    let header = cn_msg.get(..CN_MSG_HEADER)?;
+    if u32_at(header, 0) != CN_IDX_PROC || u32_at(header, 4) != CN_VAL_PROC {
+        return None;
+    }
+    let len = u16::from_ne_bytes([header[16], header[17]]) as usize;
+    cn_msg.get(CN_MSG_HEADER..CN_MSG_HEADER + len)
+}
+
+/// `what`, `cpu`, `timestamp_ns`, then the per-event fields from offset 16.
+fn decode(event: &[u8], boot_ms: u64) -> Option<ProcEvent> {
+    if event.len() < 32 {
+        return None;
+    }
+    let what = u32_at(event, 0);
+    let timestamp_ns = u64::from_ne_bytes(event[8..16].try_into().ok()?);
+    let (pid, kind) = match what {
+        // Only new processes, not new threads.
+        PROC_EVENT_FORK if u32_at(event, 24) == u32_at(event, 28) => (
+            u32_at(event, 28),
+            ProcEventKind::Fork {
+                parent: u32_at(event, 20),
+            },
+        ),
+        PROC_EVENT_EXEC => (u32_at(event, 20), ProcEventKind::Exec),
+        // Only the whole process going, not one of its threads.
+        PROC_EVENT_EXIT if u32_at(event, 16) == u32_at(event, 20) => {
+            // Same encoding as wait(2).
+            let status = u32_at(event, 24) as i32;
+            let kind = match status & 0x7f {
+                0 => ProcEventKind::Exit {
+                    code: Some((status >> 8) & 0xff),
+                    signal: None,
+                },
+                signal => ProcEventKind::Exit {
+                    code: None,
+                    signal: Some(signal),
+                },
+            };
+            (u32_at(event, 16), kind)
+        }
+        _ => return None,
+    };
+    Some(ProcEvent {
+        time_ms: boot_ms + timestamp_ns / 1_000_000,
+        pid,
+        name: None,
+        kind,
+    })
+}
+
+/// The error code of a subscription ack in `datagram`, if there is one.
+fn ack(datagram: &[u8]) -> Option<u32> {
+    let len = u32_at(datagram.get(..NLMSG_HEADER)?, 0) as usize;
+    let event = proc_event(datagram.get(NLMSG_HEADER..len)?)?;
+    (event.len() >= 20 && u32_at(event, 0) == PROC_EVENT_NONE).then(|| u32_at(event, 16))
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_ne_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{CN_IDX_PROC, CN_MSG_HEADER, CN_VAL_PROC, NLMSG_DONE, NLMSG_HEADER, ProcEvent};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::Sender;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const PROC_CN_MCAST_LISTEN: u32 = 1;
+    /// Room for bursts (a `make -j` forks thousands) before the kernel
+    /// starts dropping events.
+    const RECEIVE_BUFFER: libc::c_int = 4 * 1024 * 1024;
+
+    fn check(ret: libc::c_int) -> io::Result<libc::c_int> {
+        if ret < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(ret)
+        }
+    }
+
+    /// Open the socket, join the proc group and ask for events.
+    pub fn subscribe() -> io::Result<OwnedFd> {
+        // SAFETY: plain socket syscalls on a descriptor we own; every
+        // pointer passed is to a live, correctly sized local.
+        unsafe {
+            let fd = check(libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            ))?;
+            let socket = OwnedFd::from_raw_fd(fd);
+
+            let mut addr: libc::sockaddr_nl = std::mem::zeroed();
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups = CN_IDX_PROC;
+            check(libc::bind(
+                fd,
+                (&addr as *const libc::sockaddr_nl).cast(),
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            ))?;
+
+            // A second to answer, and the same timeout later so the
+            // listening thread notices when it should stop.
+            let timeout = libc::timeval {
+                tv_sec: 1,
+                tv_usec: 0,
+            };
+            check(libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                (&timeout as *const libc::timeval).cast(),
+                std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+            ))?;
+            // Best effort; the default is fine when this isn't allowed.
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_RCVBUF,
+                (&RECEIVE_BUFFER as *const libc::c_int).cast(),
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            );
+
+            let request = listen_request();
+            let sent = libc::send(fd, request.as_ptr().cast(), request.len(), 0);
+            if sent < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(socket)
+        }
+    }
+
+    /// nlmsghdr + cn_msg + `PROC_CN_MCAST_LISTEN`.
+    fn listen_request() -> Vec<u8> {
+        let len = NLMSG_HEADER + CN_MSG_HEADER + 4;
+        let mut msg = Vec::with_capacity(len);
+        msg.extend((len as u32).to_ne_bytes());
+        msg.extend(NLMSG_DONE.to_ne_bytes());
+        msg.extend(0u16.to_ne_bytes()); // flags
+        msg.extend(0u32.to_ne_bytes()); // seq
+        msg.extend(0u32.to_ne_bytes()); // port, filled in by the kernel
+        msg.extend(CN_IDX_PROC.to_ne_bytes());
+        msg.extend(CN_VAL_PROC.to_ne_bytes());
+        msg.extend(0u32.to_ne_bytes()); // seq
+        msg.extend(0u32.to_ne_bytes()); // ack
+        msg.extend(4u16.to_ne_bytes()); // data length
+        msg.extend(0u16.to_ne_bytes()); // flags
+        msg.extend(PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        msg
+    }
+
+    /// One datagram into `buf`: its length, or `None` on a timeout.
+    /// Events the kernel had to drop (`ENOBUFS`) are skipped; polling
+    /// picks those up.
+    pub fn recv(socket: &OwnedFd, buf: &mut [u8]) -> io::Result<Option<usize>> {
+        loop {
+            // SAFETY: `buf` is valid for writes of `buf.len()` bytes.
+            let n =
+                unsafe { libc::recv(socket.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if n >= 0 {
+                return Ok(Some(n as usize));
+            }
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EAGAIN | libc::ETIMEDOUT) => return Ok(None),
+                Some(libc::EINTR | libc::ENOBUFS) => {}
+                _ => return Err(err),
+            }
+        }
+    }
+
+    /// Unix time of the monotonic clock's zero. Worked out per datagram,
+    /// since it moves on suspend.
+    fn boot_ms() -> u64 {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // SAFETY: `ts` is a valid timespec to write to.
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+        let monotonic_ms = ts.tv_sec as u64 * 1000 + ts.tv_nsec as u64 / 1_000_000;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        now_ms.saturating_sub(monotonic_ms)
+    }
+
+    fn comm(pid: u32) -> Option<String> {
+        let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+        Some(comm.trim_end().to_string())
+    }
+
+    pub fn listen(socket: OwnedFd, events: Sender<ProcEvent>, stop: Arc<AtomicBool>) {
+        let mut buf = vec![0u8; 64 * 1024];
+        while !stop.load(Ordering::Relaxed) {
+            let n = match recv(&socket, &mut buf) {
+                Ok(Some(n)) => n,
+                Ok(None) => continue,
+                Err(_) => return,
+            };
+            for mut event in super::parse(&buf[..n], boot_ms()) {
+                event.name = comm(event.pid);
+                if events.send(event).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! What started, what exited and what grew between two snapshots. Used by
+//! `procsnipe diff` and `:diff` in the TUI.
+//!
+//! A process is the same one on both sides when the PID matches and it
+//! started at the same time; a reused PID shows up as one exit and one
+//! start.
+
+use crate::ProcessInfo;
+use crate::command::parse_seek;
+use crate::glyphs;
+use crate::output::{self, Format};
+use crate::record::{self, Frame};
+use crate::replay::Player;
+use crate::source::Totals;
+use crate::view::Column;
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// One process seen on both sides.
+#[derive(Debug, Clone)]
+pub struct Delta {
+    pub before: ProcessInfo,
+    pub after: ProcessInfo,
+}
+
+impl Delta {
+    /// Percentage points.
+    pub fn cpu(&self) -> f32 {
+        self.after.cpu_usage - self.before.cpu_usage
+    }
+
+    /// Bytes.
+    pub fn memory(&self) -> i64 {
+        self.after.memory as i64 - self.before.memory as i64
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Diff {
+    /// Only in the second snapshot, by PID.
+    pub started: Vec<ProcessInfo>,
+    /// Only in the first snapshot, by PID.
+    pub exited: Vec<ProcessInfo>,
+    /// In both, by PID.
+    pub changed: Vec<Delta>,
+}
+
+impl Diff {
+    pub fn new(before: &[ProcessInfo], after: &[ProcessInfo]) -> Self {
+        let mut earlier: HashMap<u32, &ProcessInfo> = before.iter().map(|p| (p.pid, p)).collect();
+        let mut diff = Diff::default();
+        for p in after {
+            match earlier.remove(&p.pid) {
+                Some(old) if same_process(old, p) => diff.changed.push(Delta {
+                    before: old.clone(),
+                    after: p.clone(),
+                }),
+                Some(old) => {
+                    diff.exited.push(old.clone());
+                    diff.started.push(p.clone());
+                }
+                None => diff.started.push(p.clone()),
+            }
+        }
+        diff.exited.extend(earlier.into_values().cloned());
+
+        diff.started.sort_by_key(|p| p.pid);
+        diff.exited.sort_by_key(|p| p.pid);
+        diff.changed.sort_by_key(|d| d.after.pid);
+        diff
+    }
+
+    /// The `n` biggest CPU changes either way, biggest first.
+    pub fn top_cpu(&self, n: usize) -> Vec<&Delta> {
+        let mut top: Vec<&Delta> = self.changed.iter().filter(|d| d.cpu() != 0.0).collect();
+        top.sort_by(|a, b| b.cpu().abs().total_cmp(&a.cpu().abs()));
+        top.truncate(n);
+        top
+    }
+
+    /// The `n` biggest memory changes either way, biggest first.
+    pub fn top_memory(&self, n: usize) -> Vec<&Delta> {
+        let mut top: Vec<&Delta> = self.changed.iter().filter(|d| d.memory() != 0).collect();
+        top.sort_by_key(|d| std::cmp::Reverse(d.memory().unsigned_abs()));
+        top.truncate(n);
+        top
+    }
+}
+
+/// Same PID and the same start time. Start times are whole seconds worked
+/// out from boot time and ticks, so allow them to be a second apart. Zero
+/// means unknown.
+pub fn same_process(a: &ProcessInfo, b: &ProcessInfo) -> bool {
+    a.pid == b.pid
+        && (a.start_time == 0 || b.start_time == 0 || a.start_time.abs_diff(b.start_time) <= 1)
+}
+
+/// One side of a diff from a file: `PATH`, `PATH@21:04` or `PATH@50%`.
+/// Recordings give the frame at that point (the last one without `@`);
+/// `procsnipe list -f json`/`-f ndjson` output works too, without `@`.
+/// A file that exists under the whole name is read as is, so paths with an
+/// `@` in them (`/tmp/me@host/rec.ndjson`) still work.
+pub fn load(spec: &str) -> Result<Frame> {
+    let (path, at) = match spec.rsplit_once('@') {
+        _ if Path::new(spec).is_file() => (Path::new(spec), None),
+        Some((path, at)) => match parse_seek(at) {
+            Some(seek) => (Path::new(path), Some(seek)),
+            None => bail!("bad point in time '{}' (like 21:04 or 50%)", at),
+        },
+        None => (Path::new(spec), None),
+    };
+    let text =
+        fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    let first = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+
+    if serde_json::from_str::<Frame>(first).is_ok() {
+        let mut player = Player::new(record::read(path)?)?;
+        match at {
+            Some(seek) => player.seek(seek)?,
+            None => player.step(player.len() as isize),
+        }
+        return Ok(player.frame().clone());
+    }
+
+    if at.is_some() {
+        bail!("{} is a single snapshot, not a recording", path.display());
+    }
+    let processes: Vec<ProcessInfo> = if text.trim_start().starts_with('[') {
+        serde_json::from_str(&text).map_err(anyhow::Error::from)
+    } else {
+        text.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| serde_json::from_str(l).map_err(anyhow::Error::from))
+            .collect()
+    }
+    .with_context(|| {
+        format!(
+            "{} isn't a recording or `procsnipe list -f json` output",
+            path.display()
+        )
+    })?;
+    Ok(Frame {
+        time_ms: 0,
+        took_ms: 0,
+        totals: Totals::default(),
+        processes,
+    })
+}
+
+#[derive(Serialize)]
+struct Change<'a> {
+    pid: u32,
+    name: &'a str,
+    cpu_before: f32,
+    cpu_after: f32,
+    memory_before: u64,
+    memory_after: u64,
+}
+
+impl<'a> From<&'a Delta> for Change<'a> {
+    fn from(d: &'a Delta) -> Self {
+        Change {
+            pid: d.after.pid,
+            name: &d.after.name,
+            cpu_before: d.before.cpu_usage,
+            cpu_after: d.after.cpu_usage,
+            memory_before: d.before.memory,
+            memory_after: d.after.memory,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    started: &'a [ProcessInfo],
+    exited: &'a [ProcessInfo],
+    cpu: Vec<Change<'a>>,
+    memory: Vec<Change<'a>>,
+}
+
+/// Print started and exited processes and the `top` biggest CPU and memory
+/// changes, as a table or JSON.
+pub fn write(out: &mut dyn Write, format: Format, diff: &Diff, top: usize) -> Result<()> {
+    match format {
+        Format::Json => {
+            let report = Report {
+                started: &diff.started,
+                exited: &diff.exited,
+                cpu: diff.top_cpu(top).into_iter().map(Change::from).collect(),
+                memory: diff.top_memory(top).into_iter().map(Change::from).collect(),
+            };
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+        Format::Table => {
+            for (title, processes) in [("started", &diff.started), ("exited", &diff.exited)] {
+                writeln!(out, "{}: {}", title, processes.len())?;
+                if !processes.is_empty() {
+                    output::write(out, Format::Table, processes, &Column::DEFAULT)?;
+                }
+                writeln!(out)?;
+            }
+            writeln!(out, "cpu: biggest changes")?;
+            for d in diff.top_cpu(top) {
+                writeln!(out, "{}", cpu_row(d))?;
+            }
+            writeln!(out)?;
+            writeln!(out, "memory: biggest changes")?;
+            for d in diff.top_memory(top) {
+                writeln!(out, "{}", memory_row(d))?;
+            }
+        }
+        Format::Ndjson | Format::Csv => bail!("diff prints table or json"),
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// `PID NAME 1.0% -> 35.5%  +34.5%`.
+pub fn cpu_row(d: &Delta) -> String {
+    change_row(
+        d,
+        &format!("{:.1}%", d.before.cpu_usage),
+        &format!("{:.1}%", d.after.cpu_usage),
+        &format!("{:+.1}%", d.cpu()),
+    )
+}
+
+/// `PID NAME 300 MB -> 850 MB  +550 MB`; changes under a megabyte are in
+/// KB.
+pub fn memory_row(d: &Delta) -> String {
+    change_row(
+        d,
+        &format!("{} MB", d.before.memory / 1024 / 1024),
+        &format!("{} MB", d.after.memory / 1024 / 1024),
+        &match d.memory() {
+            bytes if bytes.abs() < 1024 * 1024 => format!("{:+} KB", bytes / 1024),
+            bytes => format!("{:+} MB", bytes / 1024 / 1024),
+        },
+    )
+}
+
+fn change_row(d: &Delta, before: &str, after: &str, change: &str) -> String {
+    format!(
+        "{:<8} {:<30} {:>10} -> {:>10}  {}",
+        d.after.pid,
+        glyphs::fit(&d.after.name, 30),
+        before,
+        after,
+        change
+    )
+}
@@ -0,0 +1,353 @@
+//! Process starts and exits, worked out by comparing each snapshot with the
+//! one before. Feeds the event pane in the TUI (`e`) and, with `--events`,
+//! a file with one JSON line per event.
+//!
+//! Polling only sees what's alive at a refresh, so exit times are when the
+//! exit was noticed and runtimes are rounded up to the refresh interval.
+//! With a [`ProcConnector`] attached the kernel reports each fork and exit
+//! as it happens, with exit codes, and snapshots only fill in what it
+//! missed.
+
+use crate::ProcessInfo;
+use crate::connector::{ProcConnector, ProcEvent, ProcEventKind};
+use crate::diff;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Events kept in memory for the pane; the file keeps everything.
+pub const KEEP: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventKind {
+    Start,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Unix milliseconds.
+    pub time_ms: u64,
+    pub kind: EventKind,
+    pub pid: u32,
+    pub name: String,
+    pub parent: Option<u32>,
+    /// Exits only: how long it ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_ms: Option<u64>,
+    /// Exits only: the most memory it was seen using, in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_memory: Option<u64>,
+    /// Exits reported by the proc connector: the exit status...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// ...or the signal that killed it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+}
+
+/// What we remember about a running process between snapshots.
+struct Tracked {
+    process: ProcessInfo,
+    /// When it started as exactly as we know: the fork, its start time, or
+    /// failing both the first snapshot it was in.
+    started_ms: u64,
+    peak_memory: u64,
+    /// The start is in the log. Forks wait for the exec to get a name.
+    logged: bool,
+    /// Missing from the last snapshot while the connector is on; it gets one
+    /// more refresh for the exit event to turn up.
+    missed: bool,
+}
+
+#[derive(Default)]
+pub struct EventLog {
+    tracked: HashMap<u32, Tracked>,
+    events: VecDeque<Event>,
+    /// Time of the last snapshot; `None` until the first one, whose
+    /// processes were already running and aren't logged as starts.
+    last_ms: Option<u64>,
+    file: Option<(PathBuf, File)>,
+    connector: Option<ProcConnector>,
+    /// When the connector saw each PID exit, until a snapshot newer than
+    /// that comes in. An older one still lists the process, and mustn't
+    /// bring it back as a start.
+    exited: HashMap<u32, u64>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also append every event to `path` from now on.
+    pub fn persist_to(&mut self, path: &Path) -> Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("couldn't open {}", path.display()))?;
+        self.file = Some((path.to_path_buf(), file));
+        Ok(())
+    }
+
+    /// Take starts and exits from the kernel as they happen.
+    pub fn attach(&mut self, connector: ProcConnector) {
+        self.connector = Some(connector);
+    }
+
+    /// Events come from the proc connector rather than polling alone.
+    pub fn is_exact(&self) -> bool {
+        self.connector.is_some()
+    }
+
+    /// Oldest first.
+    pub fn events(&self) -> &VecDeque<Event> {
+        &self.events
+    }
+
+    /// Log whatever the connector reported since the last call.
+    pub fn poll(&mut self) -> Result<()> {
+        let Some(connector) = &self.connector else {
+            return Ok(());
+        };
+        let mut result = Ok(());
+        for event in connector.drain() {
+            let recorded = self.record(event);
+            result = result.and(recorded);
+        }
+        result
+    }
+
+    /// Log one event from the proc connector.
+    pub fn record(&mut self, event: ProcEvent) -> Result<()> {
+        let name = event.name.unwrap_or_else(|| "?".to_string());
+        let mut new = Vec::new();
+        match event.kind {
+            ProcEventKind::Fork { parent } => {
+                if self.tracked.contains_key(&event.pid) {
+                    return Ok(());
+                }
+                let process = ProcessInfo {
+                    pid: event.pid,
+                    name,
+                    user: String::new(),
+                    status: String::new(),
+                    cpu_usage: 0.0,
+                    memory: 0,
+                    is_game: false,
+                    is_protected: false,
+                    parent: Some(parent),
+                    // Unknown until a snapshot fills it in: sysinfo's
+                    // comes from boot time and ticks, so the event's own
+                    // clock can be a second or two off and would read as
+                    // a different process.
+                    start_time: 0,
+                    threads: 0,
+                    io_bytes: 0,
+                    leak: None,
+                    hung: None,
+                };
+                let mut tracked = Tracked::new(&process, event.time_ms);
+                tracked.started_ms = event.time_ms;
+                tracked.logged = false;
+                self.tracked.insert(event.pid, tracked);
+            }
+            ProcEventKind::Exec => {
+                if let Some(seen) = self.tracked.get_mut(&event.pid) {
+                    seen.process.name = name;
+                    if !seen.logged {
+                        new.push(seen.start());
+                    }
+                }
+            }
+            ProcEventKind::Exit { code, signal } => {
+                self.exited.insert(event.pid, event.time_ms);
+                let mut exit = match self.tracked.remove(&event.pid) {
+                    Some(mut seen) => {
+                        if !seen.logged {
+                            new.push(seen.start());
+                        }
+                        seen.exit(event.time_ms)
+                    }
+                    // Running since before we started watching.
+                    None => Event {
+                        time_ms: event.time_ms,
+                        kind: EventKind::Exit,
+                        pid: event.pid,
+                        name,
+                        parent: None,
+                        runtime_ms: None,
+                        peak_memory: None,
+                        exit_code: None,
+                        signal: None,
+                    },
+                };
+                exit.exit_code = code;
+                exit.signal = signal;
+                new.push(exit);
+            }
+        }
+        self.push(new)
+    }
+
+    /// Log what started and exited since the last snapshot. Time going
+    /// backwards (a replay jumping back) starts over without logging.
+    /// Fails only when writing to the file does; the file is dropped then
+    /// and the in-memory log carries on.
+    pub fn update(&mut self, processes: &[ProcessInfo], time: SystemTime) -> Result<()> {
+        // Forks first, so a process the snapshot caught isn't new to us.
+        let polled = self.poll();
+        let now = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let logging = self.last_ms.is_some_and(|last| now >= last);
+        self.last_ms = Some(now);
+        // What's left was scanned before the connector saw it exit.
+        self.exited.retain(|_, &mut t| t >= now);
+        let exited = self.exited.clone();
+        let dead = |p: &ProcessInfo| exited.contains_key(&p.pid);
+        if !logging {
+            self.tracked = processes
+                .iter()
+                .filter(|p| !dead(p))
+                .map(|p| (p.pid, Tracked::new(p, now)))
+                .collect();
+            return polled;
+        }
+
+        let mut earlier = std::mem::take(&mut self.tracked);
+        let mut new = Vec::new();
+        for p in processes {
+            if dead(p) {
+                // Whatever has the PID now (a fork since) is left as it was.
+                if let Some(seen) = earlier.remove(&p.pid) {
+                    self.tracked.insert(p.pid, seen);
+                }
+                continue;
+            }
+            match earlier.remove(&p.pid) {
+                Some(mut seen) if diff::same_process(&seen.process, p) => {
+                    seen.peak_memory = seen.peak_memory.max(p.memory);
+                    seen.process = p.clone();
+                    seen.missed = false;
+                    if !seen.logged {
+                        new.push(seen.start());
+                    }
+                    self.tracked.insert(p.pid, seen);
+                }
+                reused => {
+                    if let Some(old) = reused {
+                        new.push(old.exit(now));
+                    }
+                    let mut seen = Tracked::new(p, now);
+                    new.push(seen.start());
+                    self.tracked.insert(p.pid, seen);
+                }
+            }
+        }
+        for (pid, mut old) in earlier {
+            // The exit event may still be on its way (or the fork came in
+            // after the snapshot was taken).
+            if self.connector.is_some() && !old.missed {
+                old.missed = true;
+                self.tracked.insert(pid, old);
+            } else {
+                new.push(old.exit(now));
+            }
+        }
+        // Exits first, then by PID, so a reused PID reads in order.
+        new.sort_by_key(|e| (e.kind == EventKind::Start, e.pid));
+        polled.and(self.push(new))
+    }
+
+    fn push(&mut self, new: Vec<Event>) -> Result<()> {
+        let written = match &mut self.file {
+            Some((path, file)) if !new.is_empty() => {
+                let mut lines = Vec::new();
+                for event in &new {
+                    serde_json::to_writer(&mut lines, event)?;
+                    lines.push(b'\n');
+                }
+                file.write_all(&lines)
+                    .with_context(|| format!("couldn't write to {}", path.display()))
+            }
+            _ => Ok(()),
+        };
+        if written.is_err() {
+            self.file = None;
+        }
+
+        self.events.extend(new);
+        let extra = self.events.len().saturating_sub(KEEP);
+        self.events.drain(..extra);
+        written
+    }
+}
+
+impl Tracked {
+    /// First seen in a snapshot at `now`.
+    fn new(p: &ProcessInfo, now: u64) -> Self {
+        Self {
+            process: p.clone(),
+            // Start times are whole seconds; without one, count from when
+            // we first saw it.
+            started_ms: match p.start_time {
+                0 => now,
+                secs => secs * 1000,
+            },
+            peak_memory: p.memory,
+            logged: true,
+            missed: false,
+        }
+    }
+
+    /// Polling logs a start when it first sees the process; the connector
+    /// has the exact time.
+    fn start(&mut self) -> Event {
+        self.logged = true;
+        Event {
+            time_ms: self.started_ms,
+            kind: EventKind::Start,
+            pid: self.process.pid,
+            name: self.process.name.clone(),
+            parent: self.process.parent,
+            runtime_ms: None,
+            peak_memory: None,
+            exit_code: None,
+            signal: None,
+        }
+    }
+
+    fn exit(self, now: u64) -> Event {
+        Event {
+            time_ms: now,
+            kind: EventKind::Exit,
+            pid: self.process.pid,
+            name: self.process.name,
+            parent: self.process.parent,
+            runtime_ms: Some(now.saturating_sub(self.started_ms)),
+            // Nothing if it came and went between snapshots.
+            peak_memory: (self.peak_memory > 0).then_some(self.peak_memory),
+            exit_code: None,
+            signal: None,
+        }
+    }
+}
+
+/// `800ms`, `42s`, `5m12s`, `3h04m`.
+pub fn format_runtime(ms: u64) -> String {
+    let secs = ms / 1000;
+    match secs {
+        0 => format!("{}ms", ms),
+        1..60 => format!("{}s", secs),
+        60..3600 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,606 @@
+//! Small query language for filtering the process list.
+//!
+//! Queries look like `cpu>20 && mem>500MB && user!=root && name~"chrome"`.
+//! Supported pieces:
+//!
+//! - fields: `pid`, `name`, `user`, `status`, `cpu`, `mem`, `wchan` (what a
+//!   hung process is waiting in), and the flags `game`, `leak` and `hung`
+//!   (flagged by the leak and hung monitors)
+//! - comparisons: `=`/`==`, `!=`, `>`, `>=`, `<`, `<=`, `~` (contains), `!~`
+//! - combinators: `&&`/`and`, `||`/`or`, `!`/`not`, parentheses
+//! - bare words and quoted strings match against the process name, so a plain
+//!   `chrome` still works like the old substring search
+//!
+//! Adjacent terms without an operator are joined with `&&`. Memory values take
+//! `B`, `K`/`KB`, `M`/`MB`, `G`/`GB`, `T`/`TB` suffixes (powers of 1024) and are
+//! read as megabytes when bare, matching the table. CPU values may end in `%`.
+
+use crate::ProcessInfo;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Pid,
+    Name,
+    User,
+    Status,
+    Cpu,
+    Mem,
+    Wchan,
+    Game,
+    Leak,
+    Hung,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "pid" => Some(Field::Pid),
+            "name" => Some(Field::Name),
+            "user" => Some(Field::User),
+            "status" => Some(Field::Status),
+            "cpu" => Some(Field::Cpu),
+            "mem" | "memory" => Some(Field::Mem),
+            "game" => Some(Field::Game),
+            "wchan" => Some(Field::Wchan),
+            "leak" => Some(Field::Leak),
+            "hung" => Some(Field::Hung),
+            _ => None,
+        }
+    }
+
+    /// Fields compared as text, which are the ones `~` works on.
+    const TEXT: [Field; 4] = [Field::Name, Field::User, Field::Status, Field::Wchan];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Field::Pid => "pid",
+            Field::Name => "name",
+            Field::User => "user",
+            Field::Status => "status",
+            Field::Cpu => "cpu",
+            Field::Mem => "mem",
+            Field::Wchan => "wchan",
+            Field::Game => "game",
+            Field::Leak => "leak",
+            Field::Hung => "hung",
+        }
+    }
+
+    fn is_numeric(self) -> bool {
+        matches!(self, Field::Pid | Field::Cpu | Field::Mem)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+    NotContains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Num(f64),
+    Text(String),
+}
+
+/// Parsed filter expression, evaluated against each collected process.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare {
+        field: Field,
+        op: Op,
+        value: Value,
+    },
+    Flag(Field),
+    /// Bare word: case-insensitive substring match on the name.
+    Text(String),
+}
+
+impl Expr {
+    pub fn and(self, other: Expr) -> Expr {
+        Expr::And(Box::new(self), Box::new(other))
+    }
+
+    /// Lower-cased name terms the user is positively searching for, used to
+    /// rank matches. Terms under `!` are skipped.
+    pub fn name_terms(&self) -> Vec<&str> {
+        match self {
+            Expr::And(a, b) | Expr::Or(a, b) => {
+                let mut terms = a.name_terms();
+                terms.extend(b.name_terms());
+                terms
+            }
+            Expr::Text(t) => vec![t.as_str()],
+            Expr::Compare {
+                field: Field::Name,
+                op: Op::Eq | Op::Contains,
+                value: Value::Text(t),
+            } => vec![t.as_str()],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn matches(&self, p: &ProcessInfo) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(p) && b.matches(p),
+            Expr::Or(a, b) => a.matches(p) || b.matches(p),
+            Expr::Not(e) => !e.matches(p),
+            Expr::Flag(Field::Game) => p.is_game,
+            Expr::Flag(Field::Leak) => p.leak.is_some(),
+            Expr::Flag(Field::Hung) => p.hung.is_some(),
+            Expr::Flag(_) => false,
+            Expr::Text(t) => p.name.to_lowercase().contains(t),
+            Expr::Compare { field, op, value } => match value {
+                Value::Num(n) => {
+                    let lhs = match field {
+                        Field::Pid => p.pid as f64,
+                        Field::Cpu => p.cpu_usage as f64,
+                        Field::Mem => p.memory as f64,
+                        _ => return false,
+                    };
+                    match op {
+                        Op::Eq => lhs == *n,
+                        Op::Ne => lhs != *n,
+                        Op::Gt => lhs > *n,
+                        Op::Ge => lhs >= *n,
+                        Op::Lt => lhs < *n,
+                        Op::Le => lhs <= *n,
+                        Op::Contains | Op::NotContains => false,
+                    }
+                }
+                Value::Text(t) => {
+                    let lhs = match field {
+                        Field::Name => p.name.to_lowercase(),
+                        Field::User => p.user.to_lowercase(),
+                        Field::Status => p.status.to_lowercase(),
+                        Field::Wchan => p
+                            .hung
+                            .as_ref()
+                            .and_then(|hang| hang.wchan.as_deref())
+                            .unwrap_or_default()
+                            .to_lowercase(),
+                        _ => return false,
+                    };
+                    match op {
+                        Op::Eq => lhs == *t,
+                        Op::Ne => lhs != *t,
+                        Op::Contains => lhs.contains(t),
+                        Op::NotContains => !lhs.contains(t),
+                        _ => false,
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Parse failure with the character column it was detected at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "col {}: {}", self.pos + 1, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Quote a value so it can be dropped into a query as a single string.
+pub fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Parse a query. Empty input yields `Ok(None)`, meaning "match everything".
+pub fn parse(input: &str) -> Result<Option<Expr>, ParseError> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Ok(None);
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end: input.chars().count(),
+    };
+    let expr = parser.parse_or()?;
+    if let Some(tok) = parser.peek() {
+        return Err(ParseError {
+            pos: tok.pos,
+            message: format!("unexpected {}", tok.kind),
+        });
+    }
+    Ok(Some(expr))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Cmp(Op),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Word(w) => write!(f, "'{}'", w),
+            TokenKind::Quoted(s) => write!(f, "\"{}\"", s),
+            TokenKind::Cmp(_) => write!(f, "comparison"),
+            TokenKind::And => write!(f, "'&&'"),
+            TokenKind::Or => write!(f, "'||'"),
+            TokenKind::Not => write!(f, "'!'"),
+            TokenKind::LParen => write!(f, "'('"),
+            TokenKind::RParen => write!(f, "')'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    pos: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+
+        let kind = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => {
+                i += 1;
+                TokenKind::LParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RParen
+            }
+            '&' if next == Some('&') => {
+                i += 2;
+                TokenKind::And
+            }
+            '|' if next == Some('|') => {
+                i += 2;
+                TokenKind::Or
+            }
+            '!' if next == Some('=') => {
+                i += 2;
+                TokenKind::Cmp(Op::Ne)
+            }
+            '!' if next == Some('~') => {
+                i += 2;
+                TokenKind::Cmp(Op::NotContains)
+            }
+            '!' => {
+                i += 1;
+                TokenKind::Not
+            }
+            '=' => {
+                i += if next == Some('=') { 2 } else { 1 };
+                TokenKind::Cmp(Op::Eq)
+            }
+            '>' if next == Some('=') => {
+                i += 2;
+                TokenKind::Cmp(Op::Ge)
+            }
+            '>' => {
+                i += 1;
+                TokenKind::Cmp(Op::Gt)
+            }
+            '<' if next == Some('=') => {
+                i += 2;
+                TokenKind::Cmp(Op::Le)
+            }
+            '<' => {
+                i += 1;
+                TokenKind::Cmp(Op::Lt)
+            }
+            '~' => {
+                i += 1;
+                TokenKind::Cmp(Op::Contains)
+            }
+            '"' | '\'' => {
+                let quote = c;
+                i += 1;
+                let mut s = String::new();
+                loop {
+                    match chars.get(i) {
+                        Some(&ch) if ch == quote => {
+                            i += 1;
+                            break;
+                        }
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            s.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&ch) => {
+                            s.push(ch);
+                            i += 1;
+                        }
+                        None => {
+                            return Err(ParseError {
+                                pos: start,
+                                message: "unterminated string".to_string(),
+                            });
+                        }
+                    }
+                }
+                TokenKind::Quoted(s)
+            }
+            '&' | '|' => {
+                return Err(ParseError {
+                    pos: start,
+                    message: format!("expected '{}{}'", c, c),
+                });
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.get(i) {
+                    if ch.is_whitespace() || "()&|!=<>~\"'".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    i += 1;
+                }
+                match word.to_lowercase().as_str() {
+                    "and" => TokenKind::And,
+                    "or" => TokenKind::Or,
+                    "not" => TokenKind::Not,
+                    _ => TokenKind::Word(word),
+                }
+            }
+        };
+
+        tokens.push(Token { kind, pos: start });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn error_here(&self, message: &str) -> ParseError {
+        ParseError {
+            pos: self.peek().map(|t| t.pos).unwrap_or(self.end),
+            message: message.to_string(),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_and()?;
+        while matches!(self.peek().map(|t| &t.kind), Some(TokenKind::Or)) {
+            self.advance();
+            let rhs = self.parse_and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => {
+                    self.advance();
+                }
+                // Implicit `&&` between adjacent terms
+                Some(TokenKind::Word(_) | TokenKind::Quoted(_) | TokenKind::Not)
+                | Some(TokenKind::LParen) => {}
+                _ => break,
+            }
+            let rhs = self.parse_unary()?;
+            lhs = lhs.and(rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if matches!(self.peek().map(|t| &t.kind), Some(TokenKind::Not)) {
+            self.advance();
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let Some(tok) = self.advance() else {
+            return Err(self.error_here("expected a filter term"));
+        };
+
+        match tok.kind {
+            TokenKind::LParen => {
+                let inner = self.parse_or()?;
+                match self.advance() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        ..
+                    }) => Ok(inner),
+                    _ => Err(ParseError {
+                        pos: tok.pos,
+                        message: "unclosed '('".to_string(),
+                    }),
+                }
+            }
+            TokenKind::Quoted(s) => Ok(Expr::Text(s.to_lowercase())),
+            TokenKind::Word(word) => {
+                let op = match self.peek().map(|t| &t.kind) {
+                    Some(TokenKind::Cmp(op)) => Some(*op),
+                    _ => None,
+                };
+                let field = Field::from_name(&word);
+
+                let Some(op) = op else {
+                    return Ok(match field {
+                        Some(flag @ (Field::Game | Field::Leak | Field::Hung)) => Expr::Flag(flag),
+                        _ => Expr::Text(word.to_lowercase()),
+                    });
+                };
+
+                let Some(field) = field else {
+                    return Err(ParseError {
+                        pos: tok.pos,
+                        message: format!(
+                            "unknown field '{}' (try pid, name, user, status, cpu, mem, wchan, game, leak, hung)",
+                            word
+                        ),
+                    });
+                };
+                self.advance();
+                self.parse_comparison(field, op, tok.pos)
+            }
+            other => Err(ParseError {
+                pos: tok.pos,
+                message: format!("unexpected {}", other),
+            }),
+        }
+    }
+
+    fn parse_comparison(&mut self, field: Field, op: Op, pos: usize) -> Result<Expr, ParseError> {
+        if matches!(field, Field::Game | Field::Leak | Field::Hung) {
+            return Err(ParseError {
+                pos,
+                message: format!("'{0}' is a flag, use '{0}' or '!{0}'", field.as_str()),
+            });
+        }
+
+        let (raw, value_pos, quoted) = match self.advance() {
+            Some(Token {
+                kind: TokenKind::Word(w),
+                pos,
+            }) => (w, pos, false),
+            Some(Token {
+                kind: TokenKind::Quoted(s),
+                pos,
+            }) => (s, pos, true),
+            other => {
+                return Err(ParseError {
+                    pos: other.map(|t| t.pos).unwrap_or(self.end),
+                    message: "expected a value".to_string(),
+                });
+            }
+        };
+
+        if field.is_numeric() {
+            if matches!(op, Op::Contains | Op::NotContains) {
+                return Err(ParseError {
+                    pos,
+                    message: format!("'~' only works on {}", text_fields()),
+                });
+            }
+            if quoted {
+                return Err(ParseError {
+                    pos: value_pos,
+                    message: "expected a number".to_string(),
+                });
+            }
+            let n = parse_number(field, &raw).map_err(|message| ParseError {
+                pos: value_pos,
+                message,
+            })?;
+            Ok(Expr::Compare {
+                field,
+                op,
+                value: Value::Num(n),
+            })
+        } else {
+            if !matches!(op, Op::Eq | Op::Ne | Op::Contains | Op::NotContains) {
+                return Err(ParseError {
+                    pos,
+                    message: "text fields only support =, !=, ~ and !~".to_string(),
+                });
+            }
+            Ok(Expr::Compare {
+                field,
+                op,
+                value: Value::Text(raw.to_lowercase()),
+            })
+        }
+    }
+}
+
+/// `name, user, status and wchan`.
+fn text_fields() -> String {
+    let names: Vec<&str> = Field::TEXT.iter().map(|f| f.as_str()).collect();
+    let (last, rest) = names.split_last().expect("there are text fields");
+    format!("{} and {}", rest.join(", "), last)
+}
+
+/// Parse a number with an optional unit suffix, normalised to the field's
+/// native unit (bytes for memory, percent for CPU).
+fn parse_number(field: Field, raw: &str) -> Result<f64, String> {
+    let split = raw
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(raw.len());
+    let (digits, unit) = raw.split_at(split);
+    let n: f64 = digits
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", raw))?;
+    let unit = unit.to_lowercase();
+
+    match field {
+        Field::Mem => {
+            let mult: u64 = match unit.as_str() {
+                "b" => 1,
+                "k" | "kb" | "kib" => 1024,
+                "" | "m" | "mb" | "mib" => 1024 * 1024,
+                "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+                "t" | "tb" | "tib" => 1024 * 1024 * 1024 * 1024,
+                _ => return Err(format!("unknown memory unit '{}'", unit)),
+            };
+            Ok(n * mult as f64)
+        }
+        Field::Cpu => match unit.as_str() {
+            "" | "%" => Ok(n),
+            _ => Err(format!("unknown cpu unit '{}'", unit)),
+        },
+        _ => {
+            if unit.is_empty() {
+                Ok(n)
+            } else {
+                Err(format!("unexpected unit '{}'", unit))
+            }
+        }
+    }
+}
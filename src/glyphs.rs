@@ -0,0 +1,155 @@
+//! Symbols the UI draws with, in a Unicode and a plain-ASCII flavour.
+//!
+//! Emoji and box-drawing characters turn into garbage on the Linux console,
+//! some SSH sessions and non-UTF-8 locales. ASCII mode is chosen once at
+//! startup, from `--ascii` or [`detect_ascii`], and everything that prints
+//! a symbol goes through [`get`].
+
+use ratatui::symbols::border;
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub struct Glyphs {
+    pub logo: &'static str,
+    pub warning: &'static str,
+    // Only printed by tray mode
+    pub skull: &'static str,
+    pub active: &'static str,
+    pub cross: &'static str,
+    pub ellipsis: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+    pub left: &'static str,
+    pub right: &'static str,
+    pub play: &'static str,
+    pub pause: &'static str,
+    /// Replay timeline: already played, still to come.
+    pub played: &'static str,
+    pub unplayed: &'static str,
+    pub border: border::Set,
+}
+
+const UNICODE: Glyphs = Glyphs {
+    logo: "🎯",
+    warning: "⚠️ ",
+    skull: "💀",
+    active: "●",
+    cross: "✗",
+    ellipsis: "…",
+    up: "↑",
+    down: "↓",
+    left: "←",
+    right: "→",
+    play: "▶",
+    pause: "⏸",
+    played: "━",
+    unplayed: "─",
+    border: border::PLAIN,
+};
+
+const ASCII: Glyphs = Glyphs {
+    logo: "(+)",
+    warning: "!!",
+    skull: "x_x",
+    active: "*",
+    cross: "x",
+    ellipsis: "~",
+    up: "up",
+    down: "down",
+    left: "left",
+    right: "right",
+    play: ">",
+    pause: "||",
+    played: "=",
+    unplayed: "-",
+    border: border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    },
+};
+
+static ASCII_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ascii(ascii: bool) {
+    ASCII_MODE.store(ascii, Ordering::Relaxed);
+}
+
+pub fn is_ascii() -> bool {
+    ASCII_MODE.load(Ordering::Relaxed)
+}
+
+pub fn get() -> &'static Glyphs {
+    if is_ascii() { &ASCII } else { &UNICODE }
+}
+
+/// Guess whether the terminal can't render Unicode: the Linux virtual
+/// console and dumb terminals can't, and neither can a non-UTF-8 locale.
+/// Windows consoles are assumed fine.
+pub fn detect_ascii() -> bool {
+    if cfg!(target_os = "windows") {
+        return false;
+    }
+    let term = std::env::var("TERM").unwrap_or_default();
+    if matches!(
+        term.as_str(),
+        "linux" | "dumb" | "vt100" | "vt220" | "cons25"
+    ) {
+        return true;
+    }
+    // The first of LC_ALL, LC_CTYPE, LANG that's set decides, like libc does
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|v| !v.is_empty());
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            !(locale.contains("utf-8") || locale.contains("utf8"))
+        }
+        None => false,
+    }
+}
+
+/// Fit `s` into exactly `width` terminal columns: truncate with an ellipsis
+/// or pad with spaces. Counts display width, so double-width characters in
+/// process names don't push the columns after them out of line. In ASCII
+/// mode non-ASCII characters become `?`.
+pub fn fit(s: &str, width: usize) -> String {
+    let ascii;
+    let s = if is_ascii() && !s.is_ascii() {
+        ascii = s
+            .chars()
+            .map(|c| if c.is_ascii() { c } else { '?' })
+            .collect::<String>();
+        ascii.as_str()
+    } else {
+        s
+    };
+
+    if s.width() <= width {
+        return format!("{}{}", s, " ".repeat(width - s.width()));
+    }
+
+    let ellipsis = get().ellipsis;
+    let budget = width.saturating_sub(ellipsis.width());
+    let mut out = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > budget {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push_str(ellipsis);
+    used += ellipsis.width();
+    out.push_str(&" ".repeat(width.saturating_sub(used)));
+    out
+}
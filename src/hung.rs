@@ -0,0 +1,249 @@
+//! Hung processes: stuck in uninterruptible sleep (`D`), zombies their
+//! parent never reaps, and processes sitting traced or lock-blocked
+//! without using any CPU, each for longer than its `[hung]` limit.
+//!
+//! A plain stop isn't a hang: someone asked for it, with Ctrl-Z or a
+//! suspend (procsnipe's own included), and it lasts until they resume it.
+//!
+//! The clock starts when procsnipe first sees a process in the state, so
+//! one that was already stuck at startup takes the full limit to show up.
+
+use crate::ProcessInfo;
+use crate::events::format_runtime;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HungConfig {
+    pub enabled: bool,
+    /// Seconds in uninterruptible sleep.
+    pub blocked_secs: u64,
+    /// Seconds as an unreaped zombie.
+    pub zombie_secs: u64,
+    /// Seconds traced, lock-blocked or otherwise stuck at 0% CPU.
+    pub stuck_secs: u64,
+}
+
+impl Default for HungConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            blocked_secs: 30,
+            zombie_secs: 60,
+            stuck_secs: 300,
+        }
+    }
+}
+
+impl HungConfig {
+    pub fn limit(&self, kind: HangKind) -> Duration {
+        Duration::from_secs(match kind {
+            HangKind::Blocked => self.blocked_secs,
+            HangKind::Zombie => self.zombie_secs,
+            HangKind::Stuck => self.stuck_secs,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HangKind {
+    /// Uninterruptible sleep, usually a disk or network filesystem that
+    /// stopped answering.
+    Blocked,
+    /// Exited, but the parent hasn't collected it.
+    Zombie,
+    /// Traced or lock-blocked and not using any CPU.
+    Stuck,
+}
+
+impl HangKind {
+    /// What a process currently counts as, going by sysinfo's status text.
+    /// `Stopped` was asked for and `Dead` is on its way out, so neither
+    /// counts.
+    pub fn of(p: &ProcessInfo) -> Option<Self> {
+        match p.status.as_str() {
+            "UninterruptibleDiskSleep" => Some(HangKind::Blocked),
+            "Zombie" => Some(HangKind::Zombie),
+            "Tracing" | "LockBlocked" | "Wakekill" if p.cpu_usage == 0.0 => Some(HangKind::Stuck),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HangKind::Blocked => "blocked",
+            HangKind::Zombie => "zombie",
+            HangKind::Stuck => "stuck",
+        }
+    }
+}
+
+/// Set on a process that's been hung for longer than its limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hang {
+    pub kind: HangKind,
+    /// How long it's been like this.
+    pub duration: Duration,
+    /// The kernel function it's waiting in, where the source can tell.
+    pub wchan: Option<String>,
+}
+
+/// `blocked for 45s in nfs_wait_on_request`.
+impl fmt::Display for Hang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} for {}",
+            self.kind.as_str(),
+            format_runtime(self.duration.as_millis() as u64)
+        )?;
+        if let Some(wchan) = &self.wchan {
+            write!(f, " in {}", wchan)?;
+        }
+        Ok(())
+    }
+}
+
+/// A process that just went over its limit.
+#[derive(Debug, Clone)]
+pub struct Hung {
+    pub pid: u32,
+    pub name: String,
+    pub status: String,
+    pub hang: Hang,
+    /// PID and name, for zombies: it's the parent that's stuck.
+    pub parent: Option<(u32, String)>,
+}
+
+impl fmt::Display for Hung {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let runtime = format_runtime(self.hang.duration.as_millis() as u64);
+        write!(f, "hung: {} ({}) ", self.name, self.pid)?;
+        match self.hang.kind {
+            HangKind::Blocked => write!(f, "in uninterruptible sleep for {}", runtime)?,
+            HangKind::Zombie => write!(f, "zombie for {}", runtime)?,
+            HangKind::Stuck => write!(
+                f,
+                "{} with no cpu for {}",
+                self.status.to_lowercase(),
+                runtime
+            )?,
+        }
+        if let Some(wchan) = &self.hang.wchan {
+            write!(f, ", waiting in {}", wchan)?;
+        }
+        if let Some((pid, name)) = &self.parent {
+            write!(f, ", {} ({}) isn't reaping it", name, pid)?;
+        }
+        Ok(())
+    }
+}
+
+struct Watch {
+    kind: HangKind,
+    /// Unix ms it was first seen in this state.
+    since: u64,
+    flagged: bool,
+}
+
+/// A process, told apart from a later one with the same PID.
+type Key = (u32, u64);
+
+#[derive(Default)]
+pub struct HungMonitor {
+    config: HungConfig,
+    watches: HashMap<Key, Watch>,
+    last_ms: Option<u64>,
+}
+
+impl HungMonitor {
+    pub fn new(config: HungConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// New limits; the clocks keep running.
+    pub fn set_config(&mut self, config: HungConfig) {
+        self.config = config;
+    }
+
+    /// Add a snapshot taken at `time` and set `hung` on the processes over
+    /// their limit, asking `wchan` what the non-zombies are waiting in.
+    /// Returns the ones that weren't before. Time going backwards (a
+    /// replay jumping back) starts over.
+    pub fn update(
+        &mut self,
+        processes: &mut [ProcessInfo],
+        time: SystemTime,
+        mut wchan: impl FnMut(u32) -> Option<String>,
+    ) -> Vec<Hung> {
+        if !self.config.enabled {
+            self.watches.clear();
+            return Vec::new();
+        }
+        let now = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        if self.last_ms.is_some_and(|last| now < last) {
+            self.watches.clear();
+        }
+        self.last_ms = Some(now);
+
+        let mut earlier = std::mem::take(&mut self.watches);
+        let mut hung = Vec::new();
+        for p in processes.iter_mut() {
+            p.hung = None;
+            let Some(kind) = HangKind::of(p) else {
+                continue;
+            };
+            let key = (p.pid, p.start_time);
+            let mut watch = earlier
+                .remove(&key)
+                .filter(|w| w.kind == kind)
+                .unwrap_or(Watch {
+                    kind,
+                    since: now,
+                    flagged: false,
+                });
+            let duration = Duration::from_millis(now - watch.since);
+            if duration >= self.config.limit(kind) {
+                let hang = Hang {
+                    kind,
+                    duration,
+                    wchan: (kind != HangKind::Zombie).then(|| wchan(p.pid)).flatten(),
+                };
+                if !watch.flagged {
+                    hung.push(Hung {
+                        pid: p.pid,
+                        name: p.name.clone(),
+                        status: p.status.clone(),
+                        hang: hang.clone(),
+                        parent: p
+                            .parent
+                            .filter(|_| kind == HangKind::Zombie)
+                            .map(|pid| (pid, String::new())),
+                    });
+                }
+                p.hung = Some(hang);
+                watch.flagged = true;
+            }
+            self.watches.insert(key, watch);
+        }
+        // Parent names for the zombies, now the list isn't borrowed.
+        for report in &mut hung {
+            if let Some((pid, name)) = &mut report.parent {
+                match processes.iter().find(|p| p.pid == *pid) {
+                    Some(parent) => *name = parent.name.clone(),
+                    None => report.parent = None,
+                }
+            }
+        }
+        hung
+    }
+}
@@ -0,0 +1,738 @@
+//! Keymap for normal mode: key sequences bound to named actions.
+//!
+//! Bindings are written as strings. A binding is one or more chords separated
+//! by spaces; a run of plain characters like `gg` or `dd` is also read as a
+//! sequence. Chords take `ctrl-`, `alt-` and `shift-` prefixes and the named
+//! keys `esc`, `enter`, `tab`, `backspace`, `space`, `up`, `down`, `left`,
+//! `right`, `home`, `end`, `pageup`, `pagedown`, `insert`, `delete`, `f1`-`f12`.
+//! A run that looks like a misspelt key name (`pagdown`, `f13`) is an error
+//! rather than a sequence.
+//!
+//! When one binding is a prefix of another (say `g` and `gg`), the keymap
+//! waits [`SEQUENCE_TIMEOUT`] for the next key before firing the shorter one.
+//!
+//! Digits typed before a motion are a vim-style count (`10j`). A lone digit
+//! that is bound itself (views `1`-`9`) fires after the same timeout.
+
+use crate::glyphs;
+use anyhow::{Result, anyhow, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub const SEQUENCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Help,
+    Search,
+    Down,
+    Up,
+    Kill,
+    ToggleGames,
+    CycleSort,
+    ViewPicker,
+    Details,
+    Menu,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    Top,
+    Bottom,
+    NextMatch,
+    PrevMatch,
+    JumpToPid,
+    Command,
+    Faster,
+    Slower,
+    Pause,
+    StepForward,
+    StepBack,
+    ScrubForward,
+    ScrubBack,
+    Events,
+    /// Switch to view N (1-based, as shown in the picker).
+    View(u8),
+}
+
+impl Action {
+    /// Every action in help-screen order.
+    pub const ALL: [Action; 38] = [
+        Action::Down,
+        Action::Up,
+        Action::PageDown,
+        Action::PageUp,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::Top,
+        Action::Bottom,
+        Action::Search,
+        Action::NextMatch,
+        Action::PrevMatch,
+        Action::JumpToPid,
+        Action::Command,
+        Action::Details,
+        Action::Menu,
+        Action::Events,
+        Action::Kill,
+        Action::ToggleGames,
+        Action::CycleSort,
+        Action::Faster,
+        Action::Slower,
+        Action::Pause,
+        Action::StepForward,
+        Action::StepBack,
+        Action::ScrubForward,
+        Action::ScrubBack,
+        Action::ViewPicker,
+        Action::View(1),
+        Action::View(2),
+        Action::View(3),
+        Action::View(4),
+        Action::View(5),
+        Action::View(6),
+        Action::View(7),
+        Action::View(8),
+        Action::View(9),
+        Action::Help,
+        Action::Quit,
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            Action::Quit => "quit".to_string(),
+            Action::Help => "help".to_string(),
+            Action::Search => "search".to_string(),
+            Action::Down => "down".to_string(),
+            Action::Up => "up".to_string(),
+            Action::Kill => "kill".to_string(),
+            Action::ToggleGames => "toggle_games".to_string(),
+            Action::CycleSort => "cycle_sort".to_string(),
+            Action::ViewPicker => "view_picker".to_string(),
+            Action::Details => "details".to_string(),
+            Action::Menu => "menu".to_string(),
+            Action::PageDown => "page_down".to_string(),
+            Action::PageUp => "page_up".to_string(),
+            Action::HalfPageDown => "half_page_down".to_string(),
+            Action::HalfPageUp => "half_page_up".to_string(),
+            Action::Top => "top".to_string(),
+            Action::Bottom => "bottom".to_string(),
+            Action::NextMatch => "next_match".to_string(),
+            Action::PrevMatch => "prev_match".to_string(),
+            Action::JumpToPid => "jump_to_pid".to_string(),
+            Action::Command => "command".to_string(),
+            Action::Faster => "faster".to_string(),
+            Action::Slower => "slower".to_string(),
+            Action::Pause => "pause".to_string(),
+            Action::StepForward => "step_forward".to_string(),
+            Action::StepBack => "step_back".to_string(),
+            Action::ScrubForward => "scrub_forward".to_string(),
+            Action::ScrubBack => "scrub_back".to_string(),
+            Action::Events => "events".to_string(),
+            Action::View(n) => format!("view_{}", n),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(n) = name.strip_prefix("view_") {
+            return match n.parse::<u8>() {
+                Ok(n @ 1..=9) => Some(Action::View(n)),
+                _ => None,
+            };
+        }
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Action::Quit => "Quit".to_string(),
+            Action::Help => "Toggle this help".to_string(),
+            Action::Search => "Search/Filter processes".to_string(),
+            Action::Down => "Move down".to_string(),
+            Action::Up => "Move up".to_string(),
+            Action::Kill => "Kill selected process".to_string(),
+            Action::ToggleGames => "Toggle game-only view".to_string(),
+            Action::CycleSort => "Cycle sort (Name/CPU/Memory)".to_string(),
+            Action::ViewPicker => "Pick a saved view (n in the picker saves)".to_string(),
+            Action::Details => "Details for selected process".to_string(),
+            Action::Menu => "Actions menu for selected process".to_string(),
+            Action::PageDown => "Page down".to_string(),
+            Action::PageUp => "Page up".to_string(),
+            Action::HalfPageDown => "Half page down".to_string(),
+            Action::HalfPageUp => "Half page up".to_string(),
+            Action::Top => "Jump to top (or row N with a count)".to_string(),
+            Action::Bottom => "Jump to bottom (or row N with a count)".to_string(),
+            Action::NextMatch => "Next match of the last search".to_string(),
+            Action::PrevMatch => "Previous match of the last search".to_string(),
+            Action::JumpToPid => "Jump to PID".to_string(),
+            Action::Command => "Command line (:kill, :sort, :filter, :set ...)".to_string(),
+            Action::Faster => "Refresh faster (replay: play faster)".to_string(),
+            Action::Slower => "Refresh slower (replay: play slower)".to_string(),
+            Action::Pause => "Freeze/unfreeze the list (replay: play/pause)".to_string(),
+            Action::StepForward => "Replay: next snapshot".to_string(),
+            Action::StepBack => "Replay: previous snapshot".to_string(),
+            Action::ScrubForward => "Replay: skip ahead 10%".to_string(),
+            Action::ScrubBack => "Replay: skip back 10%".to_string(),
+            Action::Events => "Show/hide the process start/exit log".to_string(),
+            Action::View(n) => format!("Switch to view {}", n),
+        }
+    }
+
+    /// Navigation repeats quickly on purpose, so it skips the debounce.
+    pub fn is_navigation(self) -> bool {
+        self.takes_count()
+    }
+
+    /// Motions that accept a count prefix like `10j`.
+    pub fn takes_count(self) -> bool {
+        matches!(
+            self,
+            Action::Down
+                | Action::Up
+                | Action::PageDown
+                | Action::PageUp
+                | Action::HalfPageDown
+                | Action::HalfPageUp
+                | Action::Top
+                | Action::Bottom
+                | Action::NextMatch
+                | Action::PrevMatch
+                | Action::StepForward
+                | Action::StepBack
+                | Action::ScrubForward
+                | Action::ScrubBack
+        )
+    }
+
+    fn default_keys(self) -> Vec<&'static str> {
+        match self {
+            Action::Quit => vec!["q"],
+            Action::Help => vec!["?"],
+            Action::Search => vec!["/"],
+            Action::Down => vec!["j", "down"],
+            Action::Up => vec!["k", "up"],
+            Action::Kill => vec!["d"],
+            Action::ToggleGames => vec!["g"],
+            Action::CycleSort => vec!["s"],
+            Action::ViewPicker => vec!["v"],
+            Action::Details => vec!["enter"],
+            Action::Menu => vec!["m"],
+            Action::PageDown => vec!["pagedown"],
+            Action::PageUp => vec!["pageup"],
+            Action::HalfPageDown => vec!["ctrl-d"],
+            Action::HalfPageUp => vec!["ctrl-u"],
+            Action::Top => vec!["gg", "home"],
+            Action::Bottom => vec!["G", "end"],
+            Action::NextMatch => vec!["n"],
+            Action::PrevMatch => vec!["N"],
+            Action::JumpToPid => vec!["p"],
+            Action::Command => vec![":"],
+            Action::Faster => vec!["+", "="],
+            Action::Slower => vec!["-"],
+            Action::Pause => vec!["space", "f"],
+            Action::StepForward => vec!["l", "right"],
+            Action::StepBack => vec!["h", "left"],
+            Action::ScrubForward => vec!["]"],
+            Action::ScrubBack => vec!["["],
+            Action::Events => vec!["e"],
+            Action::View(n) => {
+                vec![["1", "2", "3", "4", "5", "6", "7", "8", "9"][(n as usize).clamp(1, 9) - 1]]
+            }
+        }
+    }
+}
+
+/// A single key press with modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Chord {
+    /// Normalise a terminal key event. Shift is dropped for characters since
+    /// it's already reflected in the character itself (`G`, `?`).
+    pub fn from_event(key: KeyEvent) -> Self {
+        let mut modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            let lower = rest.to_lowercase();
+            let (prefix_len, modifier) = if lower.starts_with("ctrl-") || lower.starts_with("ctrl+")
+            {
+                (5, KeyModifiers::CONTROL)
+            } else if lower.starts_with("alt-") || lower.starts_with("alt+") {
+                (4, KeyModifiers::ALT)
+            } else if lower.starts_with("shift-") || lower.starts_with("shift+") {
+                (6, KeyModifiers::SHIFT)
+            } else {
+                break;
+            };
+            if rest.len() == prefix_len {
+                break;
+            }
+            modifiers |= modifier;
+            rest = &rest[prefix_len..];
+        }
+
+        let code = named_key(rest)
+            .or_else(|| {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Some(KeyCode::Char(c)),
+                    _ => None,
+                }
+            })
+            .ok_or_else(|| {
+                if is_function_key(rest) {
+                    anyhow!("unknown key '{}' (function keys go from f1 to f12)", rest)
+                } else {
+                    anyhow!("unknown key '{}'", rest)
+                }
+            })?;
+
+        // `shift-g` means `G`
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            // Terminals report ctrl-d as lower-case 'd'
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            other => other,
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+fn named_key(s: &str) -> Option<KeyCode> {
+    let code = match s.to_lowercase().as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "backspace" | "bs" => KeyCode::Backspace,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "insert" | "ins" => KeyCode::Insert,
+        "delete" | "del" => KeyCode::Delete,
+        f if f.len() > 1 && f.starts_with('f') => match f[1..].parse::<u8>() {
+            Ok(n @ 1..=12) => KeyCode::F(n),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Named keys worth suggesting when a binding looks like a typo of one.
+/// The two- and three-letter aliases are left out: too many short runs of
+/// characters are one edit away from them.
+const KEY_NAMES: [&str; 16] = [
+    "escape",
+    "enter",
+    "return",
+    "backtab",
+    "backspace",
+    "space",
+    "down",
+    "left",
+    "right",
+    "home",
+    "pageup",
+    "pagedown",
+    "pgup",
+    "pgdn",
+    "insert",
+    "delete",
+];
+
+/// Looks like `f13`: an F key past the ones [`named_key`] knows.
+fn is_function_key(s: &str) -> bool {
+    s.strip_prefix(['f', 'F'])
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Plain printable characters, more than one, with no modifier syntax and
+/// nothing that looks like a function key.
+fn is_char_run(s: &str) -> bool {
+    s.chars().count() > 1
+        && !is_function_key(s)
+        && !s.contains(['-', '+'])
+        && s.chars().all(|c| !c.is_control() && !c.is_whitespace())
+}
+
+/// The named key `s` is probably a misspelling of, if any.
+fn near_miss(s: &str) -> Option<&'static str> {
+    let s = s.to_lowercase();
+    if s.chars().count() < 4 {
+        return None;
+    }
+    KEY_NAMES
+        .iter()
+        .map(|name| (edit_distance(&s, name), *name))
+        .filter(|(distance, name)| *distance <= if name.len() <= 4 { 1 } else { 2 })
+        .min()
+        .map(|(_, name)| name)
+}
+
+/// Levenshtein distance, by characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "backtab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Up => write!(f, "{}", glyphs::get().up),
+            KeyCode::Down => write!(f, "{}", glyphs::get().down),
+            KeyCode::Left => write!(f, "{}", glyphs::get().left),
+            KeyCode::Right => write!(f, "{}", glyphs::get().right),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pgup"),
+            KeyCode::PageDown => write!(f, "pgdn"),
+            KeyCode::Insert => write!(f, "ins"),
+            KeyCode::Delete => write!(f, "del"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// One or more chords pressed in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeySeq(pub Vec<Chord>);
+
+impl KeySeq {
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut chords = Vec::new();
+        for part in s.split_whitespace() {
+            match Chord::parse(part) {
+                Ok(chord) => chords.push(chord),
+                // `gg` / `dd`: a run of plain characters
+                Err(err) if is_char_run(part) => {
+                    if let Some(name) = near_miss(part) {
+                        bail!("{} (did you mean '{}'?)", err, name);
+                    }
+                    chords.extend(part.chars().map(|c| Chord {
+                        code: KeyCode::Char(c),
+                        modifiers: KeyModifiers::NONE,
+                    }));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if chords.is_empty() {
+            bail!("empty key binding");
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for KeySeq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plain = self
+            .0
+            .iter()
+            .all(|c| c.modifiers.is_empty() && matches!(c.code, KeyCode::Char(ch) if ch != ' '));
+        for (i, chord) in self.0.iter().enumerate() {
+            if i > 0 && !plain {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", chord)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of feeding a key press to the keymap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Action(Action),
+    /// The keys so far are the start of a longer binding.
+    Pending,
+    Unbound,
+}
+
+pub struct Keymap {
+    bindings: Vec<(KeySeq, Action)>,
+    pending: Vec<Chord>,
+    pending_since: Instant,
+    /// Count digits typed so far.
+    count: String,
+    /// Count that came with the last action, taken by [`Keymap::take_count`].
+    last_count: Option<usize>,
+}
+
+impl Keymap {
+    /// Defaults with the config's `[keys]` table applied. Each entry replaces
+    /// all default keys for that action; an empty list unbinds it.
+    pub fn from_config(keys: &BTreeMap<String, Vec<String>>) -> Result<Self> {
+        for name in keys.keys() {
+            if Action::from_name(name).is_none() {
+                let known: Vec<String> = Action::ALL.iter().map(|a| a.name()).collect();
+                bail!(
+                    "unknown action '{}' in [keys] (known: {})",
+                    name,
+                    known.join(", ")
+                );
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            match keys.get(&action.name()) {
+                Some(custom) => {
+                    for key in custom {
+                        let seq = KeySeq::parse(key)
+                            .map_err(|e| anyhow!("keys.{}: {}", action.name(), e))?;
+                        bindings.push((seq, action));
+                    }
+                }
+                None => {
+                    for key in action.default_keys() {
+                        bindings.push((KeySeq::parse(key)?, action));
+                    }
+                }
+            }
+        }
+
+        for (i, (a, action_a)) in bindings.iter().enumerate() {
+            for (b, action_b) in &bindings[i + 1..] {
+                if a == b && action_a != action_b {
+                    bail!(
+                        "'{}' is bound to both {} and {}",
+                        a,
+                        action_a.name(),
+                        action_b.name()
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            bindings,
+            pending: Vec::new(),
+            pending_since: Instant::now(),
+            count: String::new(),
+            last_count: None,
+        })
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty() || !self.count.is_empty()
+    }
+
+    /// Count typed before the action that was just returned, if any.
+    pub fn take_count(&mut self) -> Option<usize> {
+        self.last_count.take()
+    }
+
+    /// Feed one key press.
+    pub fn feed(&mut self, chord: Chord) -> Outcome {
+        if self.pending.is_empty()
+            && chord.modifiers.is_empty()
+            && let KeyCode::Char(c @ '0'..='9') = chord.code
+            && (c != '0' || !self.count.is_empty())
+        {
+            self.count.push(c);
+            self.pending_since = Instant::now();
+            return Outcome::Pending;
+        }
+
+        let outcome = self.feed_chord(chord);
+        match outcome {
+            Outcome::Action(action) => self.finish(action),
+            Outcome::Unbound => self.count.clear(),
+            Outcome::Pending => {}
+        }
+        outcome
+    }
+
+    /// Hand the typed count to `action` if it's a motion, otherwise drop it.
+    fn finish(&mut self, action: Action) {
+        let count = std::mem::take(&mut self.count);
+        self.last_count = if action.takes_count() {
+            count.parse().ok()
+        } else {
+            None
+        };
+    }
+
+    fn feed_chord(&mut self, chord: Chord) -> Outcome {
+        self.pending.push(chord);
+        self.pending_since = Instant::now();
+
+        let exact = self.exact_match(&self.pending);
+        let longer = self
+            .bindings
+            .iter()
+            .any(|(seq, _)| seq.0.len() > self.pending.len() && seq.0.starts_with(&self.pending));
+
+        match (exact, longer) {
+            (_, true) => Outcome::Pending,
+            (Some(action), false) => {
+                self.pending.clear();
+                Outcome::Action(action)
+            }
+            (None, false) => {
+                // A dead end: retry the last key on its own, so `gx` still runs `x`.
+                let retry = self.pending.len() > 1;
+                self.pending.clear();
+                if retry {
+                    self.feed_chord(chord)
+                } else {
+                    Outcome::Unbound
+                }
+            }
+        }
+    }
+
+    /// Called on every tick. Fires the shorter binding once a pending
+    /// sequence has waited long enough for its next key.
+    pub fn timeout(&mut self) -> Option<Action> {
+        if !self.is_pending() || self.pending_since.elapsed() < SEQUENCE_TIMEOUT {
+            return None;
+        }
+        let action = if self.pending.is_empty() {
+            // Digits with no motion after them: a single bound digit still
+            // runs its own action, anything longer is dropped.
+            let mut chars = self.count.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => self.exact_match(&[Chord {
+                    code: KeyCode::Char(c),
+                    modifiers: KeyModifiers::NONE,
+                }]),
+                _ => None,
+            }
+        } else {
+            self.exact_match(&self.pending)
+        };
+        self.pending.clear();
+        match action {
+            Some(action) => self.finish(action),
+            None => self.count.clear(),
+        }
+        action
+    }
+
+    /// Drop any half-typed sequence, e.g. when switching modes.
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.count.clear();
+    }
+
+    /// Keys typed so far in an unfinished sequence, for the footer.
+    pub fn pending_keys(&self) -> String {
+        format!("{}{}", self.count, KeySeq(self.pending.clone()))
+    }
+
+    fn exact_match(&self, chords: &[Chord]) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(seq, _)| seq.0 == chords)
+            .map(|(_, action)| *action)
+    }
+
+    /// Bound keys for `action`, e.g. `j/↓`.
+    pub fn keys_for(&self, action: Action) -> String {
+        let keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(seq, _)| seq.to_string())
+            .collect();
+        keys.join("/")
+    }
+
+    /// (keys, description) rows for the help screen, straight from the
+    /// active bindings. Unbound actions are left out, and views 1-9 are
+    /// folded into one row.
+    pub fn help_entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut view_keys = Vec::new();
+        for action in Action::ALL {
+            let keys = self.keys_for(action);
+            if keys.is_empty() {
+                continue;
+            }
+            if let Action::View(_) = action {
+                view_keys.push(keys);
+                continue;
+            }
+            if !view_keys.is_empty() {
+                entries.push(view_entry(&view_keys));
+                view_keys.clear();
+            }
+            entries.push((keys, action.description()));
+        }
+        if !view_keys.is_empty() {
+            entries.push(view_entry(&view_keys));
+        }
+        entries
+    }
+}
+
+fn view_entry(keys: &[String]) -> (String, String) {
+    let digits: Vec<String> = (1..=9).map(|n| n.to_string()).collect();
+    let keys = if keys == digits.as_slice() {
+        "1-9".to_string()
+    } else {
+        keys.join(" ")
+    };
+    (keys, "Switch to view by number".to_string())
+}
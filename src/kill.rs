@@ -0,0 +1,276 @@
+//! Choosing processes to kill by PID, name, regex or filter, and reporting
+//! how it went. Used by `procsnipe kill`; the TUI's `:kill` shares the
+//! target syntax.
+
+use crate::ProcessInfo;
+use crate::command::parse_duration;
+use crate::filter::Expr;
+use crate::source::{NoSuchProcess, PermissionDenied, ProcessSource, Signal};
+use anyhow::{Result, anyhow};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+/// Every process that was asked for got the signal.
+pub const EXIT_KILLED_ALL: i32 = 0;
+/// Something went wrong before anything was sent.
+pub const EXIT_ERROR: i32 = 1;
+/// Some processes got the signal, others were protected or failed.
+pub const EXIT_PARTIAL: i32 = 3;
+pub const EXIT_NOTHING_MATCHED: i32 = 4;
+/// Nothing was killed because of permissions or the protected list.
+pub const EXIT_PERMISSION_DENIED: i32 = 5;
+
+/// One thing to match processes against.
+#[derive(Debug, Clone)]
+pub enum Target {
+    Pid(u32),
+    /// Lower-cased name, may contain `*` and `?`.
+    Name(String),
+    Regex(Regex),
+}
+
+impl Target {
+    /// A PID when it's all digits, otherwise a name pattern.
+    pub fn parse(s: &str) -> Self {
+        match s.parse() {
+            Ok(pid) => Target::Pid(pid),
+            Err(_) => Target::Name(s.to_lowercase()),
+        }
+    }
+
+    pub fn regex(s: &str) -> Result<Self> {
+        Regex::new(s)
+            .map(Target::Regex)
+            .map_err(|e| anyhow!("bad regex '{}': {}", s, e))
+    }
+
+    /// Names match case-insensitively and without needing the `.exe`;
+    /// regexes match anywhere in the name.
+    pub fn matches(&self, p: &ProcessInfo) -> bool {
+        match self {
+            Target::Pid(pid) => p.pid == *pid,
+            Target::Name(pattern) => {
+                let name = p.name.to_lowercase();
+                glob(pattern, &name) || name.strip_suffix(".exe").is_some_and(|n| glob(pattern, n))
+            }
+            Target::Regex(re) => re.is_match(&p.name),
+        }
+    }
+}
+
+/// `*` matches any run of characters, `?` exactly one.
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was and how much text it had swallowed.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// What to kill: processes matching any of `targets` (or all processes
+/// when there are none), narrowed by the filter, user and age.
+#[derive(Debug, Clone, Default)]
+pub struct Selector {
+    pub targets: Vec<Target>,
+    pub filter: Option<Expr>,
+    pub user: Option<String>,
+    pub older_than: Option<Duration>,
+    /// Also take every descendant of a match, whatever it looks like.
+    pub tree: bool,
+    /// Our own PID. Like pkill, it and its ancestors (the shell that ran
+    /// us) are never selected, however broad the match.
+    pub own_pid: Option<u32>,
+}
+
+impl Selector {
+    fn matches(&self, p: &ProcessInfo, now: u64) -> bool {
+        (self.targets.is_empty() || self.targets.iter().any(|t| t.matches(p)))
+            && self.filter.as_ref().is_none_or(|f| f.matches(p))
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|u| p.user.eq_ignore_ascii_case(u))
+            && self
+                .older_than
+                .is_none_or(|min| p.age(now).is_some_and(|age| age > min))
+    }
+
+    /// Matching processes in the order to signal them: by PID, with
+    /// children ahead of their parents when `tree` is set so nothing gets
+    /// orphaned halfway. `now` is Unix seconds, for `older_than`.
+    pub fn select(&self, processes: &[ProcessInfo], now: u64) -> Vec<ProcessInfo> {
+        let by_pid: BTreeMap<u32, &ProcessInfo> = processes.iter().map(|p| (p.pid, p)).collect();
+        let spared = self
+            .own_pid
+            .map(|pid| ancestors(pid, &by_pid))
+            .unwrap_or_default();
+        let matched: Vec<u32> = by_pid
+            .values()
+            .filter(|p| !spared.contains(&p.pid) && self.matches(p, now))
+            .map(|p| p.pid)
+            .collect();
+        if !self.tree {
+            return matched.iter().map(|pid| by_pid[pid].clone()).collect();
+        }
+
+        let mut children: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for p in by_pid.values() {
+            if let Some(parent) = p.parent.filter(|&parent| parent != p.pid) {
+                children.entry(parent).or_default().push(p.pid);
+            }
+        }
+        let mut order = Vec::new();
+        let mut seen = BTreeSet::new();
+        for pid in matched {
+            children_first(pid, &children, &mut seen, &mut order);
+        }
+        order.iter().map(|pid| by_pid[pid].clone()).collect()
+    }
+}
+
+/// `pid` and every process above it.
+fn ancestors(pid: u32, by_pid: &BTreeMap<u32, &ProcessInfo>) -> BTreeSet<u32> {
+    let mut chain = BTreeSet::new();
+    let mut next = Some(pid);
+    while let Some(pid) = next.filter(|&pid| chain.insert(pid)) {
+        next = by_pid.get(&pid).and_then(|p| p.parent);
+    }
+    chain
+}
+
+fn children_first(
+    pid: u32,
+    children: &BTreeMap<u32, Vec<u32>>,
+    seen: &mut BTreeSet<u32>,
+    order: &mut Vec<u32>,
+) {
+    if !seen.insert(pid) {
+        return;
+    }
+    for &child in children.get(&pid).into_iter().flatten() {
+        children_first(child, children, seen, order);
+    }
+    order.push(pid);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Killed,
+    /// Gone before the signal got there; as good as killed for a
+    /// terminating signal.
+    Exited,
+    /// On the protected list; never signalled.
+    Protected,
+    Denied,
+    Failed(String),
+}
+
+/// What happened to each selected process.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub results: Vec<(ProcessInfo, Outcome)>,
+}
+
+impl Report {
+    pub fn exit_code(&self) -> i32 {
+        let killed = self.count(|o| matches!(o, Outcome::Killed | Outcome::Exited));
+        let denied = self.count(|o| matches!(o, Outcome::Denied | Outcome::Protected));
+        if self.results.is_empty() {
+            EXIT_NOTHING_MATCHED
+        } else if killed == self.results.len() {
+            EXIT_KILLED_ALL
+        } else if killed > 0 {
+            EXIT_PARTIAL
+        } else if denied > 0 {
+            EXIT_PERMISSION_DENIED
+        } else {
+            EXIT_ERROR
+        }
+    }
+
+    fn count(&self, f: impl Fn(&Outcome) -> bool) -> usize {
+        self.results.iter().filter(|(_, o)| f(o)).count()
+    }
+}
+
+/// What [`kill_all`] would do without sending anything: protected processes
+/// are skipped and the rest count as killed, so `--dry-run` exits with the
+/// same code a real run would when nothing fails along the way.
+pub fn plan(processes: &[ProcessInfo]) -> Report {
+    let results = processes
+        .iter()
+        .map(|p| {
+            let outcome = if p.is_protected {
+                Outcome::Protected
+            } else {
+                Outcome::Killed
+            };
+            (p.clone(), outcome)
+        })
+        .collect();
+    Report { results }
+}
+
+/// An age for `--older-than`: `30s`, `10m`, `2h` and so on, like
+/// [`parse_duration`], except a bare number is seconds rather than
+/// milliseconds so `--older-than 30` can't match nearly everything.
+pub fn parse_age(s: &str) -> Result<Duration> {
+    if s.trim().parse::<f64>().is_ok() {
+        return parse_duration(&format!("{}s", s.trim()));
+    }
+    parse_duration(s)
+}
+
+/// Send `signal` (or the platform's forceful kill when `None`) to each
+/// process in order, skipping protected ones.
+pub fn kill_all<S: ProcessSource + ?Sized>(
+    source: &mut S,
+    processes: &[ProcessInfo],
+    signal: Option<Signal>,
+) -> Report {
+    let results = processes
+        .iter()
+        .map(|p| {
+            let outcome = if p.is_protected {
+                Outcome::Protected
+            } else {
+                let sent = match signal {
+                    Some(signal) => source.signal(p.pid, signal),
+                    None => source.kill(p.pid),
+                };
+                match sent {
+                    Ok(()) => Outcome::Killed,
+                    Err(e) if e.is::<PermissionDenied>() => Outcome::Denied,
+                    Err(e) if e.is::<NoSuchProcess>() && signal.is_none_or(Signal::terminates) => {
+                        Outcome::Exited
+                    }
+                    Err(e) => Outcome::Failed(e.to_string()),
+                }
+            };
+            (p.clone(), outcome)
+        })
+        .collect();
+    Report { results }
+}
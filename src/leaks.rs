@@ -0,0 +1,201 @@
+//! Slow memory leaks: a least-squares trend over each process's resident
+//! memory, flagging the ones that have grown faster than `[leaks]
+//! mb_per_min` for a whole window, and mostly only ever gone up.
+//!
+//! Samples are thinned to about [`SAMPLES`] per window so a long-running
+//! TUI doesn't itself leak a point per process per refresh.
+
+use crate::ProcessInfo;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Points kept per process over one window.
+pub const SAMPLES: u64 = 30;
+/// Share of steps between samples that may not shrink for growth to count
+/// as steady; allows for the odd GC or trim.
+const STEADY: f64 = 0.8;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeakConfig {
+    pub enabled: bool,
+    /// Growth rate that counts as a leak, in MB per minute.
+    pub mb_per_min: f32,
+    /// How long the growth has to keep up, in minutes.
+    pub window_mins: u64,
+}
+
+impl Default for LeakConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            mb_per_min: 1.0,
+            window_mins: 10,
+        }
+    }
+}
+
+impl LeakConfig {
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.window_mins * 60)
+    }
+}
+
+/// A process that just started looking like it leaks.
+#[derive(Debug, Clone)]
+pub struct Leak {
+    pub pid: u32,
+    pub name: String,
+    /// MB per minute over the window.
+    pub rate: f32,
+    /// Bytes, now.
+    pub memory: u64,
+    /// How much history the trend is over.
+    pub window: Duration,
+}
+
+impl fmt::Display for Leak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "leak: {} ({}) growing {:.1} MB/min for {}m, now {} MB",
+            self.name,
+            self.pid,
+            self.rate,
+            self.window.as_secs() / 60,
+            self.memory / 1024 / 1024
+        )
+    }
+}
+
+#[derive(Default)]
+struct History {
+    /// (Unix ms, bytes), oldest first.
+    samples: VecDeque<(u64, u64)>,
+    flagged: bool,
+}
+
+impl History {
+    /// MB per minute when the history covers `window` and grows steadily
+    /// at `min_rate` or more.
+    fn leak_rate(&self, window: u64, min_rate: f32) -> Option<f32> {
+        let (&(first, _), &(last, _)) = (self.samples.front()?, self.samples.back()?);
+        if last - first < window || self.samples.len() < 3 {
+            return None;
+        }
+        let rate = slope(&self.samples) as f32;
+        let steps = self.samples.len() - 1;
+        let grew = self
+            .samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .filter(|((_, a), (_, b))| b >= a)
+            .count();
+        // Both halves grew, so a jump at the end of a flat stretch isn't
+        // mistaken for a trend.
+        let (start, middle, end) = (
+            self.samples[0].1,
+            self.samples[self.samples.len() / 2].1,
+            self.samples[steps].1,
+        );
+        let sustained = start < middle && middle < end;
+        (rate >= min_rate && sustained && grew as f64 >= steps as f64 * STEADY).then_some(rate)
+    }
+}
+
+/// Least-squares slope of memory over time, in MB per minute.
+fn slope(samples: &VecDeque<(u64, u64)>) -> f64 {
+    let n = samples.len() as f64;
+    let t0 = samples[0].0;
+    let points = samples
+        .iter()
+        .map(|&(t, m)| ((t - t0) as f64 / 60_000.0, m as f64 / 1024.0 / 1024.0));
+    let (sx, sy) = points
+        .clone()
+        .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let (mx, my) = (sx / n, sy / n);
+    let (num, den) = points.fold((0.0, 0.0), |(num, den), (x, y)| {
+        (num + (x - mx) * (y - my), den + (x - mx) * (x - mx))
+    });
+    if den == 0.0 { 0.0 } else { num / den }
+}
+
+/// A process, told apart from a later one with the same PID.
+type Key = (u32, u64);
+
+#[derive(Default)]
+pub struct LeakMonitor {
+    config: LeakConfig,
+    history: HashMap<Key, History>,
+    last_ms: Option<u64>,
+}
+
+impl LeakMonitor {
+    pub fn new(config: LeakConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// New settings; history carries over.
+    pub fn set_config(&mut self, config: LeakConfig) {
+        self.config = config;
+    }
+
+    /// Add a snapshot taken at `time` and set `leak` on the processes that
+    /// look like they leak. Returns the ones that didn't before. Time going
+    /// backwards (a replay jumping back) starts over.
+    pub fn update(&mut self, processes: &mut [ProcessInfo], time: SystemTime) -> Vec<Leak> {
+        if !self.config.enabled {
+            self.history.clear();
+            return Vec::new();
+        }
+        let now = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        if self.last_ms.is_some_and(|last| now < last) {
+            self.history.clear();
+        }
+        self.last_ms = Some(now);
+
+        let window = self.config.window().as_millis() as u64;
+        let spacing = window / SAMPLES;
+        let mut earlier = std::mem::take(&mut self.history);
+        let mut leaks = Vec::new();
+        for p in processes {
+            let key = (p.pid, p.start_time);
+            let mut history = earlier.remove(&key).unwrap_or_default();
+            if history
+                .samples
+                .back()
+                .is_none_or(|&(t, _)| now >= t + spacing)
+            {
+                history.samples.push_back((now, p.memory));
+            }
+            // Keep one point at or past the window so it's fully covered.
+            while history.samples.len() > 2 && history.samples[1].0 + window <= now {
+                history.samples.pop_front();
+            }
+
+            p.leak = history.leak_rate(window, self.config.mb_per_min);
+            if let Some(rate) = p.leak
+                && !history.flagged
+            {
+                leaks.push(Leak {
+                    pid: p.pid,
+                    name: p.name.clone(),
+                    rate,
+                    memory: p.memory,
+                    window: Duration::from_millis(now - history.samples[0].0),
+                });
+            }
+            history.flagged = p.leak.is_some();
+            self.history.insert(key, history);
+        }
+        leaks
+    }
+}
@@ -0,0 +1,35 @@
+//! procsnipe as a library: the process model, the [`ProcessSource`]
+//! abstraction with a live and a fake implementation, filters, views and
+//! the TUI itself. The `procsnipe` binary is a thin wrapper around this.
+
+pub mod alerts;
+pub mod app;
+pub mod batch;
+pub mod collector;
+pub mod command;
+pub mod config;
+pub mod connector;
+pub mod diff;
+pub mod events;
+pub mod filter;
+pub mod glyphs;
+pub mod hung;
+pub mod keymap;
+pub mod kill;
+pub mod leaks;
+mod menu;
+pub mod output;
+pub mod process;
+pub mod record;
+pub mod replay;
+pub mod source;
+pub mod theme;
+#[cfg(feature = "tray")]
+pub mod tray;
+pub mod ui;
+pub mod view;
+
+pub use process::{ProcessInfo, SortMode};
+pub use source::{
+    FakeSource, NoSuchProcess, PermissionDenied, ProcessSource, Signal, SysinfoSource, Totals,
+};
@@ -1,222 +1,261 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result, anyhow, bail};
+use clap::{ArgGroup, Parser, Subcommand};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
-use ratatui::{
-    Frame, Terminal,
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+use procsnipe::app::{App, run_app};
+use procsnipe::batch::{self, Batch};
+use procsnipe::command::Seek;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::connector::ProcConnector;
+use procsnipe::diff::{self, Diff};
+use procsnipe::filter;
+use procsnipe::keymap::Keymap;
+use procsnipe::kill::{self, Outcome, Selector, Target};
+use procsnipe::output::{self, Format};
+use procsnipe::record::{self, Recorder, Rotation};
+use procsnipe::replay::Player;
+use procsnipe::{
+    FakeSource, ProcessSource, Signal, SortMode, SysinfoSource, command, glyphs, process, view,
 };
-use std::time::{Duration, Instant};
-use sysinfo::{Pid, ProcessesToUpdate, System};
-
-#[cfg(feature = "tray")]
-mod tray;
+use ratatui::{Terminal, backend::CrosstermBackend};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// procsnipe - TUI Process Manager for Windows
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Cmd>,
+
     /// Run in system tray mode (background monitoring)
     #[arg(long)]
     tray: bool,
+
+    /// Plain ASCII output: no emoji or box-drawing characters (auto-detected
+    /// from TERM and the locale when not given)
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Config file to use instead of the default location
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    /// Refresh interval, e.g. 500ms or 2s (overrides refresh_ms from the config)
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_delay)]
+    delay: Option<Duration>,
+
+    /// Print a snapshot every refresh instead of starting the TUI (like top -b)
+    #[arg(short, long)]
+    batch: bool,
+
+    /// With --batch: stop after this many snapshots
+    #[arg(short = 'n', long, value_name = "N", requires = "batch")]
+    iterations: Option<u64>,
+
+    /// With --batch: table or ndjson
+    #[arg(short, long, default_value = "table", requires = "batch")]
+    format: Format,
+
+    /// With --batch: print at most this many rows per snapshot
+    #[arg(long, value_name = "N", requires = "batch")]
+    limit: Option<usize>,
+
+    #[command(flatten)]
+    record: RecordArgs,
+
+    /// Append every process start and exit the TUI sees to this file as NDJSON
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["tray", "batch"])]
+    events: Option<PathBuf>,
 }
 
-#[derive(PartialEq)]
-enum Mode {
-    Normal,
-    Search,
-    Help,
+#[derive(clap::Args, Debug)]
+struct RecordArgs {
+    /// Append every snapshot to this file as NDJSON (TUI and --batch)
+    #[arg(long, value_name = "PATH", conflicts_with = "tray")]
+    record: Option<PathBuf>,
+
+    /// Start a new recording file past this size, e.g. 100MB or 1GB
+    #[arg(long, value_name = "SIZE", default_value = "100MB", value_parser = parse_size, requires = "record")]
+    record_size: u64,
+
+    /// Also start a new recording file after this long, e.g. 1h
+    #[arg(long, value_name = "AGE", value_parser = parse_age, requires = "record")]
+    record_age: Option<Duration>,
+
+    /// Rotated recording files to keep (PATH.1, PATH.2, ...)
+    #[arg(long, value_name = "N", default_value_t = 5, requires = "record")]
+    record_keep: usize,
 }
 
-#[derive(PartialEq, Clone, Copy, Debug)]
-enum SortMode {
-    Name,
-    Cpu,
-    Memory,
+impl RecordArgs {
+    fn open(&self) -> Result<Option<Recorder>> {
+        let Some(path) = &self.record else {
+            return Ok(None);
+        };
+        let rotation = Rotation {
+            max_size: Some(self.record_size),
+            max_age: self.record_age,
+            keep: self.record_keep,
+        };
+        Recorder::open(path, rotation).map(Some)
+    }
 }
 
-struct ProcessInfo {
-    pid: u32,
-    name: String,
-    cpu_usage: f32,
-    memory: u64,
-    is_game: bool,
+#[derive(Subcommand, Debug)]
+enum Cmd {
+    /// Print one snapshot of the process list and exit
+    List(ListArgs),
+    /// Kill processes by PID, name, regex or filter query
+    ///
+    /// Exit codes: 0 all killed, 1 error, 3 partial, 4 nothing matched,
+    /// 5 permission denied (including protected processes).
+    Kill(KillArgs),
+    /// Play a --record file back in the TUI (read-only)
+    Replay(ReplayArgs),
+    /// What started, exited and grew between two snapshots
+    Diff(DiffArgs),
 }
 
-struct App {
-    mode: Mode,
-    processes: Vec<ProcessInfo>,
-    list_state: ListState,
-    search_query: String,
-    sort_mode: SortMode,
-    show_games_only: bool,
-    refresh_rate: Duration,
-    last_refresh: Instant,
-    sys: System,
+#[derive(clap::Args, Debug)]
+struct DiffArgs {
+    /// Earlier snapshot: a --record file (PATH@21:04, PATH@50%; the last
+    /// frame without @) or `list -f json`/`-f ndjson` output
+    before: String,
+
+    /// Later snapshot, same forms as BEFORE (default: the system now)
+    after: Option<String>,
+
+    /// Show this many of the biggest CPU and memory changes
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 10)]
+    top: usize,
+
+    /// Output format: table or json
+    #[arg(short, long, default_value = "table")]
+    format: Format,
+
+    /// How long to measure CPU usage when comparing with the system now
+    #[arg(long, value_name = "INTERVAL", default_value = "500ms", value_parser = parse_sample)]
+    sample: Duration,
 }
 
-impl App {
-    fn new() -> Self {
-        let mut list_state = ListState::default();
-        list_state.select(Some(0));
-
-        Self {
-            mode: Mode::Normal,
-            processes: Vec::new(),
-            list_state,
-            search_query: String::new(),
-            sort_mode: SortMode::Cpu,
-            show_games_only: false,
-            refresh_rate: Duration::from_millis(1000),
-            last_refresh: Instant::now(),
-            sys: System::new_all(),
-        }
-    }
+#[derive(clap::Args, Debug)]
+struct ReplayArgs {
+    /// File written by --record
+    file: PathBuf,
 
-    fn refresh_processes(&mut self) {
-        if self.last_refresh.elapsed() < self.refresh_rate {
-            return;
-        }
+    /// Start at this local time or position, e.g. 21:04 or 50%
+    #[arg(long, value_name = "TIME", value_parser = parse_seek)]
+    at: Option<Seek>,
+}
 
-        self.sys.refresh_processes(ProcessesToUpdate::All, true);
-
-        self.processes = self
-            .sys
-            .processes()
-            .iter()
-            .map(|(pid, process)| {
-                let name = process.name().to_string_lossy().to_string();
-                let is_game = Self::is_game_process(&name);
-
-                ProcessInfo {
-                    pid: pid.as_u32(),
-                    name,
-                    cpu_usage: process.cpu_usage(),
-                    memory: process.memory(),
-                    is_game,
-                }
-            })
-            .collect();
-
-        // Apply filters
-        if self.show_games_only {
-            self.processes.retain(|p| p.is_game);
-        }
+#[derive(clap::Args, Debug)]
+struct ListArgs {
+    /// Filter query, same language as / in the TUI (e.g. "cpu>10 && user=me")
+    query: Option<String>,
 
-        if !self.search_query.is_empty() {
-            let query = self.search_query.to_lowercase();
-            self.processes
-                .retain(|p| p.name.to_lowercase().contains(&query));
-        }
+    /// Start from a view (name or number); its filter, sort and columns apply
+    #[arg(long)]
+    view: Option<String>,
 
-        // Sort
-        match self.sort_mode {
-            SortMode::Name => self.processes.sort_by(|a, b| a.name.cmp(&b.name)),
-            SortMode::Cpu => self
-                .processes
-                .sort_by(|a, b| b.cpu_usage.partial_cmp(&a.cpu_usage).unwrap()),
-            SortMode::Memory => self.processes.sort_by(|a, b| b.memory.cmp(&a.memory)),
-        }
+    /// Sort column: pid, name, user, status, cpu, mem, leak or hung
+    #[arg(long, value_parser = parse_sort)]
+    sort: Option<SortMode>,
 
-        self.last_refresh = Instant::now();
-    }
+    /// Flip the sort order
+    #[arg(long)]
+    reverse: bool,
 
-    fn is_game_process(name: &str) -> bool {
-        let game_indicators = [
-            "game",
-            "steam",
-            "epic",
-            "uplay",
-            "origin",
-            "riot",
-            "valorant",
-            "league",
-            "csgo",
-            "cs2",
-            "dota",
-            "apex",
-            "fortnite",
-            "minecraft",
-            "roblox",
-            "gta",
-            "unity",
-            "unreal",
-            "dx11",
-            "dx12",
-            "vulkan",
-        ];
-
-        let name_lower = name.to_lowercase();
-        game_indicators
-            .iter()
-            .any(|&indicator| name_lower.contains(indicator))
-    }
+    /// Print at most this many rows
+    #[arg(short = 'n', long, value_name = "N")]
+    limit: Option<usize>,
 
-    fn kill_selected(&mut self) -> Result<()> {
-        if let Some(selected) = self.list_state.selected() {
-            if let Some(process) = self.processes.get(selected) {
-                let pid = Pid::from_u32(process.pid);
-                if let Some(proc) = self.sys.process(pid) {
-                    // Use kill_with for better Windows compatibility
-                    // ProcessSignal::Kill is more forceful and works better on Windows
-                    if !proc.kill() {
-                        // Process might require admin privileges or be protected
-                        // Silently fail rather than crashing
-                    }
-                }
-            }
-        }
-        Ok(())
-    }
+    /// Output format: table, json, ndjson or csv
+    #[arg(short, long, default_value = "table")]
+    format: Format,
 
-    fn next(&mut self) {
-        if self.processes.is_empty() {
-            return;
-        }
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i >= self.processes.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
-    }
+    /// How long to measure CPU usage before printing
+    #[arg(long, value_name = "INTERVAL", default_value = "500ms", value_parser = parse_sample)]
+    sample: Duration,
+}
 
-    fn previous(&mut self) {
-        if self.processes.is_empty() {
-            return;
-        }
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.processes.len() - 1
-                } else {
-                    i - 1
-                }
-            }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
+#[derive(clap::Args, Debug)]
+#[command(group = ArgGroup::new("what").required(true).multiple(true).args(["targets", "regex", "filter"]))]
+struct KillArgs {
+    /// PIDs or process names; names ignore case and `.exe` and may use * and ?
+    targets: Vec<String>,
+
+    /// Kill processes whose name matches this regex (repeatable)
+    #[arg(short = 'e', long, value_name = "REGEX")]
+    regex: Vec<String>,
+
+    /// Only processes matching this filter query, e.g. "mem>2GB && !game"
+    #[arg(short = 'q', long, value_name = "QUERY")]
+    filter: Option<String>,
+
+    /// Signal to send, e.g. TERM, HUP or 15 (default: forceful kill)
+    #[arg(short, long, value_parser = parse_signal)]
+    signal: Option<Signal>,
+
+    /// Also kill every descendant of a matched process
+    #[arg(long)]
+    tree: bool,
+
+    /// Only processes running longer than this, e.g. 30s, 10m or 2h (a bare number is seconds)
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    older_than: Option<Duration>,
+
+    /// Only processes owned by this user
+    #[arg(short, long)]
+    user: Option<String>,
+
+    /// List what would be killed without sending anything
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+}
+
+fn parse_signal(s: &str) -> Result<Signal, String> {
+    Signal::from_name(s).ok_or_else(|| {
+        let names: Vec<&str> = Signal::ALL.iter().map(|s| s.name()).collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+fn parse_age(s: &str) -> Result<Duration, String> {
+    kill::parse_age(s).map_err(|e| e.to_string())
+}
+
+fn parse_size(s: &str) -> Result<u64, String> {
+    record::parse_size(s).map_err(|e| e.to_string())
+}
+
+fn parse_seek(s: &str) -> Result<Seek, String> {
+    command::parse_seek(s).ok_or_else(|| "expected HH:MM[:SS] or N%".to_string())
+}
+
+fn parse_sort(s: &str) -> Result<SortMode, String> {
+    SortMode::from_name(s)
+        .ok_or_else(|| "expected pid, name, user, status, cpu, mem, leak or hung".to_string())
+}
+
+fn parse_sample(s: &str) -> Result<Duration, String> {
+    let sample = command::parse_duration(s).map_err(|e| e.to_string())?;
+    if sample > Duration::from_secs(60) {
+        return Err("must be at most 60s".to_string());
     }
+    Ok(sample)
+}
 
-    fn toggle_sort(&mut self) {
-        self.sort_mode = match self.sort_mode {
-            SortMode::Name => SortMode::Cpu,
-            SortMode::Cpu => SortMode::Memory,
-            SortMode::Memory => SortMode::Name,
-        };
+fn parse_delay(s: &str) -> Result<Duration, String> {
+    let delay = command::parse_duration(s).map_err(|e| e.to_string())?;
+    if !(Duration::from_millis(100)..=Duration::from_secs(60)).contains(&delay) {
+        return Err("must be between 100ms and 60s".to_string());
     }
+    Ok(delay)
 }
 
 #[cfg(target_os = "windows")]
@@ -235,29 +274,267 @@ fn is_elevated() -> bool {
     false
 }
 
+/// `procsnipe list`: one snapshot through the same view, filter and sort
+/// as the TUI, printed in the requested format.
+fn run_list(args: ListArgs, config: Option<&Path>) -> Result<()> {
+    let config = Config::load(config)?;
+    let views = view::load_views(&config.views)?;
+    let view = match &args.view {
+        Some(name) => {
+            let index =
+                view::find(&views, name).ok_or_else(|| anyhow!("no view named '{}'", name))?;
+            &views[index]
+        }
+        None => &views[0],
+    };
+    let query = match args.query.as_deref().map(filter::parse).transpose() {
+        Ok(query) => query.flatten(),
+        Err(e) => return Err(anyhow!("bad filter: {}", e)),
+    };
+    let filter = match (view.filter.clone(), query) {
+        (Some(v), Some(q)) => Some(v.and(q)),
+        (v, q) => v.or(q),
+    };
+
+    let mut source = SysinfoSource::new().with_sample_interval(args.sample);
+    let mut snapshot = source.snapshot()?;
+    config.mark(&mut snapshot);
+    let sort = args.sort.unwrap_or(view.sort);
+    let mut processes = process::select(&snapshot, filter.as_ref(), sort, args.reverse);
+    if let Some(limit) = args.limit {
+        processes.truncate(limit);
+    }
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written =
+        output::write(&mut out, args.format, &processes, &view.columns).and_then(|_| out.flush());
+    match written {
+        // `procsnipe list | head` closing the pipe early is fine.
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// `procsnipe kill`: returns the process exit code.
+fn run_kill(args: KillArgs, config: Option<&Path>) -> Result<i32> {
+    let config = Config::load(config)?;
+    let mut targets: Vec<Target> = args.targets.iter().map(|t| Target::parse(t)).collect();
+    for re in &args.regex {
+        targets.push(Target::regex(re)?);
+    }
+    let filter = match args.filter.as_deref().map(filter::parse).transpose() {
+        Ok(filter) => filter.flatten(),
+        Err(e) => return Err(anyhow!("bad filter: {}", e)),
+    };
+    let selector = Selector {
+        targets,
+        filter,
+        user: args.user,
+        older_than: args.older_than,
+        tree: args.tree,
+        own_pid: Some(std::process::id()),
+    };
+
+    let mut source = SysinfoSource::new();
+    let mut snapshot = source.snapshot()?;
+    config.mark(&mut snapshot);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let selected = selector.select(&snapshot, now);
+    if selected.is_empty() {
+        eprintln!("no processes matched");
+        return Ok(kill::EXIT_NOTHING_MATCHED);
+    }
+
+    if args.dry_run {
+        let plan = kill::plan(&selected);
+        for (p, outcome) in &plan.results {
+            if *outcome == Outcome::Protected {
+                println!("would skip {} {} (protected)", p.pid, p.name);
+            } else {
+                println!("would kill {} {}", p.pid, p.name);
+            }
+        }
+        return Ok(plan.exit_code());
+    }
+
+    let report = kill::kill_all(&mut source, &selected, args.signal);
+    for (p, outcome) in &report.results {
+        match outcome {
+            Outcome::Killed => println!("killed {} {}", p.pid, p.name),
+            Outcome::Exited => println!("exited {} {} (already gone)", p.pid, p.name),
+            Outcome::Protected => eprintln!("skipped {} {} (protected)", p.pid, p.name),
+            Outcome::Denied => eprintln!("denied {} {} (permissions)", p.pid, p.name),
+            Outcome::Failed(e) => eprintln!("failed {} {}: {}", p.pid, p.name, e),
+        }
+    }
+    Ok(report.exit_code())
+}
+
+fn run_replay(args: ReplayArgs, config: Option<&Path>, events: Option<&Path>) -> Result<()> {
+    let frames = record::read(&args.file)?;
+    let mut player =
+        Player::new(frames).with_context(|| format!("can't replay {}", args.file.display()))?;
+    if let Some(at) = args.at {
+        player.seek(at)?;
+    }
+
+    let watcher = ConfigWatcher::new(config);
+    let config = Config::load(config)?;
+    let views = view::load_views(&config.views)?;
+    let keymap = Keymap::from_config(&config.keys)?;
+    let theme = config.theme.resolve()?;
+    // Nothing is collected or signalled in a replay, so any source will do.
+    let mut app = App::replaying(
+        FakeSource::default(),
+        player,
+        config,
+        views,
+        keymap,
+        theme,
+        watcher,
+    );
+    if let Some(path) = events {
+        app.log_events_to(path)?;
+    }
+    run_tui(&mut app)
+}
+
+/// `procsnipe diff`: saved vs. live, or two saved snapshots.
+fn run_diff(args: DiffArgs, config: Option<&Path>) -> Result<()> {
+    let before = diff::load(&args.before)?;
+    let after = match &args.after {
+        Some(spec) => diff::load(spec)?.processes,
+        None => {
+            let config = Config::load(config)?;
+            let mut source = SysinfoSource::new().with_sample_interval(args.sample);
+            let mut snapshot = source.snapshot()?;
+            config.mark(&mut snapshot);
+            snapshot
+        }
+    };
+    let diff = Diff::new(&before.processes, &after);
+    let mut out = BufWriter::new(io::stdout().lock());
+    match diff::write(&mut out, args.format, &diff, args.top) {
+        Err(e) if is_broken_pipe(&e) => Ok(()),
+        result => result,
+    }
+}
+
+/// The reader went away, e.g. `procsnipe --batch | head`. Not an error.
+fn is_broken_pipe(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+}
+
 fn main() -> Result<()> {
     // Parse command line arguments
     let args = Args::parse();
+    glyphs::set_ascii(args.ascii || glyphs::detect_ascii());
+
+    match args.command {
+        Some(Cmd::List(list)) => return run_list(list, args.config.as_deref()),
+        Some(Cmd::Kill(kill)) => std::process::exit(run_kill(kill, args.config.as_deref())?),
+        Some(Cmd::Replay(_)) if args.record.record.is_some() => {
+            bail!("--record doesn't work with replay")
+        }
+        Some(Cmd::Replay(replay)) => {
+            return run_replay(replay, args.config.as_deref(), args.events.as_deref());
+        }
+        Some(Cmd::Diff(diff)) => return run_diff(diff, args.config.as_deref()),
+        None => {}
+    }
+
+    if args.batch {
+        let config = Config::load(args.config.as_deref())?;
+        let views = view::load_views(&config.views)?;
+        let interval = args.delay.unwrap_or(config.refresh_rate());
+        let mut batch = Batch {
+            format: args.format,
+            iterations: args.iterations,
+            limit: args.limit,
+            recorder: args.record.open()?,
+        };
+        let mut out = io::stdout().lock();
+        let result = batch::run(
+            SysinfoSource::new(),
+            &config,
+            &views[0],
+            interval,
+            &mut batch,
+            &mut out,
+        );
+        return match result {
+            Err(e) if is_broken_pipe(&e) => Ok(()),
+            result => result,
+        };
+    }
 
     // Launch in tray mode if --tray flag is set
     #[cfg(feature = "tray")]
     if args.tray {
-        let mut tray_app = tray::TrayApp::new();
+        let config = Config::load(args.config.as_deref())?;
+        let watcher = ConfigWatcher::new(args.config.as_deref());
+        let mut tray_app = procsnipe::tray::TrayApp::new(config, watcher);
         return tray_app.run();
     }
+    #[cfg(not(feature = "tray"))]
+    if args.tray {
+        bail!("this build has no tray support (it was built without the tray feature)");
+    }
 
     // Otherwise, launch normal TUI mode
+    let config = Config::load(args.config.as_deref())?;
+    let views = view::load_views(&config.views)?;
+    let keymap = Keymap::from_config(&config.keys)?;
+    let theme = config.theme.resolve()?;
+    let config_watcher = ConfigWatcher::new(args.config.as_deref());
+
     // Check if running on Windows and warn about admin privileges
     #[cfg(target_os = "windows")]
     {
         if !is_elevated() {
-            eprintln!("⚠️  Warning: Not running as administrator.");
+            eprintln!(
+                "{} Warning: Not running as administrator.",
+                glyphs::get().warning
+            );
             eprintln!("   Some processes might be protected and can't be killed.");
             eprintln!("   For full functionality, run as admin.\n");
             std::thread::sleep(Duration::from_secs(2));
         }
     }
 
+    let recorder = args.record.open()?;
+    let mut app = App::new(
+        SysinfoSource::new(),
+        config,
+        views,
+        keymap,
+        theme,
+        config_watcher,
+    );
+    if let Some(delay) = args.delay {
+        app.set_refresh_rate(delay);
+    }
+    if let Some(recorder) = recorder {
+        app.set_recorder(recorder);
+    }
+    if let Some(path) = &args.events {
+        app.log_events_to(path)?;
+    }
+    // Exact starts and exits when we're allowed to listen for them;
+    // otherwise the event log compares snapshots.
+    if let Ok(connector) = ProcConnector::open() {
+        app.attach_connector(connector);
+    }
+    run_tui(&mut app)
+}
+
+/// Take over the terminal, run `app` until it quits and restore the
+/// terminal.
+fn run_tui<S: ProcessSource + Clone + 'static>(app: &mut App<S>) -> Result<()> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -265,12 +542,8 @@ fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app
-    let mut app = App::new();
-    app.refresh_processes();
-
     // Main loop
-    let res = run_app(&mut terminal, &mut app);
+    let res = run_app(&mut terminal, app);
 
     // Restore terminal
     disable_raw_mode()?;
@@ -287,233 +560,3 @@ fn main() -> Result<()> {
 
     Ok(())
 }
-
-fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
-    let mut last_key_time = Instant::now();
-    let debounce_duration = Duration::from_millis(150); // Prevent double-clicks
-
-    loop {
-        app.refresh_processes();
-        terminal.draw(|f| ui(f, app))?;
-
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                // Only process Press events, ignore Release and Repeat to prevent double-clicks
-                if key.kind != event::KeyEventKind::Press {
-                    continue;
-                }
-
-                // Debounce to prevent accidental double-presses (except for navigation)
-                let is_navigation = matches!(
-                    key.code,
-                    KeyCode::Char('j') | KeyCode::Char('k') | KeyCode::Up | KeyCode::Down
-                );
-                if !is_navigation && last_key_time.elapsed() < debounce_duration {
-                    continue;
-                }
-                last_key_time = Instant::now();
-
-                match app.mode {
-                    Mode::Normal => match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('?') => app.mode = Mode::Help,
-                        KeyCode::Char('/') => {
-                            app.mode = Mode::Search;
-                            app.search_query.clear();
-                        }
-                        KeyCode::Char('j') | KeyCode::Down => app.next(),
-                        KeyCode::Char('k') | KeyCode::Up => app.previous(),
-                        KeyCode::Char('d') => {
-                            app.kill_selected()?;
-                        }
-                        KeyCode::Char('g') => app.show_games_only = !app.show_games_only,
-                        KeyCode::Char('s') => app.toggle_sort(),
-                        _ => {}
-                    },
-                    Mode::Search => match key.code {
-                        KeyCode::Esc => {
-                            app.mode = Mode::Normal;
-                            app.search_query.clear();
-                        }
-                        KeyCode::Char(c) => app.search_query.push(c),
-                        KeyCode::Backspace => {
-                            app.search_query.pop();
-                        }
-                        KeyCode::Enter => app.mode = Mode::Normal,
-                        _ => {}
-                    },
-                    Mode::Help => {
-                        if key.code == KeyCode::Esc || key.code == KeyCode::Char('?') {
-                            app.mode = Mode::Normal;
-                        }
-                    }
-                }
-            }
-        }
-    }
-}
-
-fn ui(f: &mut Frame, app: &mut App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(0),
-            Constraint::Length(3),
-        ])
-        .split(f.area());
-
-    // Header
-    let header = Paragraph::new(vec![Line::from(vec![
-        Span::styled(
-            "🎯 procsnipe ",
-            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-        ),
-        Span::styled("| ", Style::default().fg(Color::DarkGray)),
-        Span::styled(
-            format!("processes: {} ", app.processes.len()),
-            Style::default().fg(Color::Cyan),
-        ),
-        Span::styled("| ", Style::default().fg(Color::DarkGray)),
-        Span::styled(
-            format!("sort: {:?} ", app.sort_mode),
-            Style::default().fg(Color::Yellow),
-        ),
-        Span::styled("| ", Style::default().fg(Color::DarkGray)),
-        Span::styled(
-            if app.show_games_only {
-                "games only"
-            } else {
-                "all"
-            },
-            Style::default().fg(Color::Magenta),
-        ),
-    ])])
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Red)),
-    );
-    f.render_widget(header, chunks[0]);
-
-    // Process list or help
-    if app.mode == Mode::Help {
-        let help_text = vec![
-            Line::from(""),
-            Line::from(Span::styled(
-                "🎯 procsnipe controls",
-                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-            )),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled("  /  ", Style::default().fg(Color::Yellow)),
-                Span::raw("- Search/Filter processes"),
-            ]),
-            Line::from(vec![
-                Span::styled("  ESC", Style::default().fg(Color::Yellow)),
-                Span::raw("- Exit search/help"),
-            ]),
-            Line::from(vec![
-                Span::styled("  ?  ", Style::default().fg(Color::Yellow)),
-                Span::raw("- Toggle this help"),
-            ]),
-            Line::from(vec![
-                Span::styled("  q  ", Style::default().fg(Color::Yellow)),
-                Span::raw("- Quit"),
-            ]),
-            Line::from(vec![
-                Span::styled("  j/k", Style::default().fg(Color::Yellow)),
-                Span::raw("- Navigate (or ↑/↓)"),
-            ]),
-            Line::from(vec![
-                Span::styled("  d  ", Style::default().fg(Color::Yellow)),
-                Span::raw("- Kill selected process"),
-            ]),
-            Line::from(vec![
-                Span::styled("  g  ", Style::default().fg(Color::Yellow)),
-                Span::raw("- Toggle game-only view"),
-            ]),
-            Line::from(vec![
-                Span::styled("  s  ", Style::default().fg(Color::Yellow)),
-                Span::raw("- Cycle sort (Name/CPU/Memory)"),
-            ]),
-            Line::from(""),
-            Line::from(Span::styled(
-                "press ? or ESC to close",
-                Style::default().fg(Color::DarkGray),
-            )),
-        ];
-        let help = Paragraph::new(help_text).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Help")
-                .border_style(Style::default().fg(Color::Yellow)),
-        );
-        f.render_widget(help, chunks[1]);
-    } else {
-        let items: Vec<ListItem> = app
-            .processes
-            .iter()
-            .map(|p| {
-                let cpu_bar = format!("{:>5.1}%", p.cpu_usage);
-                let mem_mb = p.memory / 1024 / 1024;
-                let mem_str = format!("{:>6} MB", mem_mb);
-
-                let style = if p.is_game {
-                    Style::default().fg(Color::Green)
-                } else if p.cpu_usage > 50.0 {
-                    Style::default().fg(Color::Red)
-                } else if p.cpu_usage > 20.0 {
-                    Style::default().fg(Color::Yellow)
-                } else {
-                    Style::default().fg(Color::White)
-                };
-
-                let content = Line::from(vec![
-                    Span::styled(
-                        format!("{:<8} ", p.pid),
-                        Style::default().fg(Color::DarkGray),
-                    ),
-                    Span::styled(format!("{:<30} ", p.name), style),
-                    Span::styled(cpu_bar, Style::default().fg(Color::Cyan)),
-                    Span::raw("  "),
-                    Span::styled(mem_str, Style::default().fg(Color::Magenta)),
-                ]);
-
-                ListItem::new(content)
-            })
-            .collect();
-
-        let list = List::new(items)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Processes")
-                    .border_style(Style::default().fg(Color::Cyan)),
-            )
-            .highlight_style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">> ");
-
-        f.render_stateful_widget(list, chunks[1], &mut app.list_state);
-    }
-
-    // Footer
-    let footer_text = match app.mode {
-        Mode::Normal => "press ? for help | q to quit",
-        Mode::Search => &format!("search: {}_", app.search_query),
-        Mode::Help => "viewing help",
-    };
-
-    let footer = Paragraph::new(footer_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::DarkGray)),
-        );
-    f.render_widget(footer, chunks[2]);
-}
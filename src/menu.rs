@@ -0,0 +1,102 @@
+//! Right-click context menu for a single process.
+
+use ratatui::layout::Rect;
+use ratatui::widgets::ListState;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuItem {
+    Details,
+    Kill,
+    OnlyThisName,
+    HideThisName,
+}
+
+impl MenuItem {
+    pub const ALL: [MenuItem; 4] = [
+        MenuItem::Details,
+        MenuItem::Kill,
+        MenuItem::OnlyThisName,
+        MenuItem::HideThisName,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuItem::Details => "Details",
+            MenuItem::Kill => "Kill",
+            MenuItem::OnlyThisName => "Show only this name",
+            MenuItem::HideThisName => "Hide this name",
+        }
+    }
+}
+
+pub struct ContextMenu {
+    pub pid: u32,
+    pub name: String,
+    /// Where the menu was opened; the popup is placed next to it.
+    pub anchor: (u16, u16),
+    pub state: ListState,
+    /// Last drawn position, for mapping clicks back to items.
+    pub area: Rect,
+}
+
+impl ContextMenu {
+    pub fn new(pid: u32, name: String, anchor: (u16, u16)) -> Self {
+        let mut state = ListState::default();
+        state.select(Some(0));
+        Self {
+            pid,
+            name,
+            anchor,
+            state,
+            area: Rect::default(),
+        }
+    }
+
+    pub fn selected(&self) -> MenuItem {
+        MenuItem::ALL[self
+            .state
+            .selected()
+            .unwrap_or(0)
+            .min(MenuItem::ALL.len() - 1)]
+    }
+
+    pub fn next(&mut self) {
+        let i = self.state.selected().unwrap_or(0);
+        self.state.select(Some((i + 1) % MenuItem::ALL.len()));
+    }
+
+    pub fn previous(&mut self) {
+        let i = self.state.selected().unwrap_or(0);
+        let len = MenuItem::ALL.len();
+        self.state.select(Some((i + len - 1) % len));
+    }
+
+    /// Popup rectangle inside `bounds`, shifted left/up if it would run off
+    /// the screen.
+    pub fn popup_area(&self, bounds: Rect) -> Rect {
+        let width = MenuItem::ALL
+            .iter()
+            .map(|i| i.label().len() as u16)
+            .max()
+            .unwrap_or(0)
+            .max(self.name.chars().count().min(30) as u16)
+            + 6;
+        let height = MenuItem::ALL.len() as u16 + 2;
+        let width = width.min(bounds.width);
+        let height = height.min(bounds.height);
+        let x = self.anchor.0.min(bounds.right().saturating_sub(width));
+        let y = self.anchor.1.min(bounds.bottom().saturating_sub(height));
+        Rect::new(x.max(bounds.x), y.max(bounds.y), width, height)
+    }
+
+    /// Item under a click, if the click landed on one.
+    pub fn item_at(&self, x: u16, y: u16) -> Option<usize> {
+        let inner_top = self.area.y + 1;
+        let inside = x > self.area.x
+            && x < self.area.right().saturating_sub(1)
+            && y >= inner_top
+            && y < self.area.bottom().saturating_sub(1);
+        let index = (y.saturating_sub(inner_top)) as usize;
+        (inside && index < MenuItem::ALL.len()).then_some(index)
+    }
+}
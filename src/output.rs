@@ -0,0 +1,174 @@
+//! Printing process lists for scripts and people: an aligned table, JSON,
+//! NDJSON (one object per line) or CSV. JSON and CSV always carry every
+//! field; the table shows the view's columns.
+
+use crate::ProcessInfo;
+use crate::events::format_runtime;
+use crate::glyphs::fit;
+use crate::view::Column;
+use anyhow::bail;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl Format {
+    pub const NAMES: [&str; 4] = ["table", "json", "ndjson", "csv"];
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "table" => Format::Table,
+            "json" => Format::Json,
+            "ndjson" | "jsonl" => Format::Ndjson,
+            "csv" => Format::Csv,
+            _ => bail!("unknown format '{}' ({})", s, Format::NAMES.join(", ")),
+        })
+    }
+}
+
+pub fn write(
+    out: &mut dyn Write,
+    format: Format,
+    processes: &[ProcessInfo],
+    columns: &[Column],
+) -> io::Result<()> {
+    match format {
+        Format::Table => write_table(out, processes, columns),
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, processes)?;
+            writeln!(out)
+        }
+        Format::Ndjson => {
+            for p in processes {
+                serde_json::to_writer(&mut *out, p)?;
+                writeln!(out)?;
+            }
+            Ok(())
+        }
+        Format::Csv => write_csv(out, processes),
+    }
+}
+
+fn write_table(
+    out: &mut dyn Write,
+    processes: &[ProcessInfo],
+    columns: &[Column],
+) -> io::Result<()> {
+    let row = |cells: Vec<String>| {
+        let line: String = columns
+            .iter()
+            .zip(cells)
+            .map(|(col, text)| {
+                let width = col.width() as usize - 1;
+                if col.right_aligned() {
+                    format!("{:>width$} ", text)
+                } else {
+                    format!("{} ", fit(&text, width))
+                }
+            })
+            .collect();
+        line.trim_end().to_string()
+    };
+
+    writeln!(
+        out,
+        "{}",
+        row(columns.iter().map(|c| c.label().to_string()).collect())
+    )?;
+    for p in processes {
+        writeln!(
+            out,
+            "{}",
+            row(columns.iter().map(|c| cell(*c, p)).collect())
+        )?;
+    }
+    Ok(())
+}
+
+/// Plain text of one table cell, same units as the TUI.
+fn cell(column: Column, p: &ProcessInfo) -> String {
+    match column {
+        Column::Pid => p.pid.to_string(),
+        Column::Name => p.name.clone(),
+        Column::User => p.user.clone(),
+        Column::Status => p.status.clone(),
+        Column::Cpu => format!("{:.1}%", p.cpu_usage),
+        Column::Mem => format!("{} MB", p.memory / 1024 / 1024),
+        Column::Leak => leak_cell(p),
+        Column::Hung => hung_cell(p),
+        Column::Wchan => wchan_cell(p),
+    }
+}
+
+/// `+2.5/min`, or blank when it isn't leaking.
+pub fn leak_cell(p: &ProcessInfo) -> String {
+    p.leak
+        .map(|rate| format!("{:+.1}/min", rate))
+        .unwrap_or_default()
+}
+
+/// `blocked 45s`, or blank when it isn't hung.
+pub fn hung_cell(p: &ProcessInfo) -> String {
+    p.hung
+        .as_ref()
+        .map(|hang| {
+            format!(
+                "{} {}",
+                hang.kind.as_str(),
+                format_runtime(hang.duration.as_millis() as u64)
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// What a hung process is waiting in, when the source could tell.
+pub fn wchan_cell(p: &ProcessInfo) -> String {
+    p.hung
+        .as_ref()
+        .and_then(|hang| hang.wchan.clone())
+        .unwrap_or_default()
+}
+
+/// CSV with a header row.
+pub fn write_csv(out: &mut dyn Write, processes: &[ProcessInfo]) -> io::Result<()> {
+    let field = |s: &str| {
+        if s.contains([',', '"', '\n']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    writeln!(
+        out,
+        "pid,name,user,status,cpu,memory_bytes,game,protected,parent,started,threads,io_bytes"
+    )?;
+    for p in processes {
+        writeln!(
+            out,
+            "{},{},{},{},{:.1},{},{},{},{},{},{},{}",
+            p.pid,
+            field(&p.name),
+            field(&p.user),
+            field(&p.status),
+            p.cpu_usage,
+            p.memory,
+            p.is_game,
+            p.is_protected,
+            p.parent.map(|p| p.to_string()).unwrap_or_default(),
+            p.start_time,
+            p.threads,
+            p.io_bytes
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! The process model shared by every part of procsnipe.
+
+use crate::filter::Expr;
+use crate::hung::Hang;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use sysinfo::ProcessStatus;
+
+#[derive(PartialEq, Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    Pid,
+    Name,
+    User,
+    Status,
+    Cpu,
+    #[serde(alias = "mem")]
+    Memory,
+    /// Leak rate, leaking processes first.
+    Leak,
+    /// How long it's been hung, longest first.
+    Hung,
+}
+
+impl SortMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortMode::Pid => "pid",
+            SortMode::Name => "name",
+            SortMode::User => "user",
+            SortMode::Status => "status",
+            SortMode::Cpu => "cpu",
+            SortMode::Memory => "memory",
+            SortMode::Leak => "leak",
+            SortMode::Hung => "hung",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "pid" => Some(SortMode::Pid),
+            "name" => Some(SortMode::Name),
+            "user" => Some(SortMode::User),
+            "status" => Some(SortMode::Status),
+            "cpu" => Some(SortMode::Cpu),
+            "mem" | "memory" => Some(SortMode::Memory),
+            "leak" => Some(SortMode::Leak),
+            "hung" => Some(SortMode::Hung),
+            _ => None,
+        }
+    }
+
+    /// Numbers sort biggest first, text A to Z.
+    pub fn descending_by_default(self) -> bool {
+        matches!(
+            self,
+            SortMode::Cpu | SortMode::Memory | SortMode::Leak | SortMode::Hung
+        )
+    }
+
+    /// Sort in this mode's usual direction.
+    pub fn sort(self, processes: &mut [ProcessInfo]) {
+        match self {
+            SortMode::Pid => processes.sort_by_key(|p| p.pid),
+            SortMode::Name => processes.sort_by(|a, b| a.name.cmp(&b.name)),
+            SortMode::User => processes.sort_by(|a, b| a.user.cmp(&b.user)),
+            SortMode::Status => processes.sort_by(|a, b| a.status.cmp(&b.status)),
+            SortMode::Cpu => processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+            SortMode::Memory => processes.sort_by_key(|p| std::cmp::Reverse(p.memory)),
+            SortMode::Leak => processes.sort_by(|a, b| {
+                let rate = |p: &ProcessInfo| p.leak.unwrap_or(f32::NEG_INFINITY);
+                rate(b).total_cmp(&rate(a))
+            }),
+            SortMode::Hung => processes
+                .sort_by_key(|p| std::cmp::Reverse(p.hung.as_ref().map(|hang| hang.duration))),
+        }
+    }
+}
+
+/// One row of the process list. Serialized with the same field names as
+/// the CSV export.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub user: String,
+    /// sysinfo's status text, e.g. `Runnable`, `Sleeping`, `Zombie`.
+    pub status: String,
+    #[serde(rename = "cpu")]
+    pub cpu_usage: f32,
+    /// Resident memory in bytes.
+    #[serde(rename = "memory_bytes")]
+    pub memory: u64,
+    #[serde(rename = "game")]
+    pub is_game: bool,
+    /// Matches the config's protected list; the kill key refuses it.
+    #[serde(rename = "protected")]
+    pub is_protected: bool,
+    pub parent: Option<u32>,
+    /// Unix time the process started, in seconds. 0 when unknown.
+    #[serde(rename = "started")]
+    pub start_time: u64,
+    /// 0 when the platform doesn't say.
+    #[serde(default)]
+    pub threads: u32,
+    /// Bytes read from and written to disk since it started.
+    #[serde(default)]
+    pub io_bytes: u64,
+    /// MB per minute when the leak monitor thinks it leaks. Worked out from
+    /// history, so never saved: a replay works it out again.
+    #[serde(skip)]
+    pub leak: Option<f32>,
+    /// Set by the hung monitor once it's been stuck for too long; never
+    /// saved either.
+    #[serde(skip)]
+    pub hung: Option<Hang>,
+}
+
+impl ProcessInfo {
+    pub fn is_suspended(&self) -> bool {
+        self.status == ProcessStatus::Stop.to_string()
+    }
+
+    /// How long the process has been running as of `now` (Unix seconds).
+    pub fn age(&self, now: u64) -> Option<Duration> {
+        (self.start_time > 0).then(|| Duration::from_secs(now.saturating_sub(self.start_time)))
+    }
+}
+
+/// The rows a list shows: `processes` narrowed by `filter` and sorted by
+/// `sort`, flipped when `reversed`.
+pub fn select(
+    processes: &[ProcessInfo],
+    filter: Option<&Expr>,
+    sort: SortMode,
+    reversed: bool,
+) -> Vec<ProcessInfo> {
+    let mut selected: Vec<ProcessInfo> = match filter {
+        Some(filter) => processes
+            .iter()
+            .filter(|p| filter.matches(p))
+            .cloned()
+            .collect(),
+        None => processes.to_vec(),
+    };
+    sort.sort(&mut selected);
+    if reversed {
+        selected.reverse();
+    }
+    selected
+}
@@ -0,0 +1,210 @@
+//! Recording every snapshot to disk (`--record`), so "the game hitched at
+//! 21:04" can be looked up afterwards.
+//!
+//! A recording is NDJSON: one [`Frame`] per line, appended as soon as it's
+//! collected, so a crash loses at most the line being written. When the
+//! file gets too big or too old it's renamed to `path.1` (older ones shift
+//! to `path.2`, ...) and a fresh one is started.
+
+use crate::ProcessInfo;
+use crate::source::Totals;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// One whole snapshot as a single NDJSON line. Used for recordings and
+/// `--batch --format ndjson`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    /// Unix time in milliseconds.
+    pub time_ms: u64,
+    /// How long the scan took.
+    pub took_ms: u64,
+    pub totals: Totals,
+    pub processes: Vec<ProcessInfo>,
+}
+
+impl Frame {
+    pub fn new(
+        time: SystemTime,
+        took: Duration,
+        totals: Totals,
+        processes: Vec<ProcessInfo>,
+    ) -> Self {
+        Self {
+            time_ms: time
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            took_ms: took.as_millis() as u64,
+            totals,
+            processes,
+        }
+    }
+}
+
+/// When to start a new file.
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    /// Rotate before a line would take the file past this many bytes.
+    pub max_size: Option<u64>,
+    /// Rotate once we've been writing to the file this long.
+    pub max_age: Option<Duration>,
+    /// Rotated files to keep around; older ones are deleted.
+    pub keep: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self {
+            max_size: Some(100 * 1024 * 1024),
+            max_age: None,
+            keep: 5,
+        }
+    }
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    rotation: Rotation,
+    file: File,
+    size: u64,
+    opened: Instant,
+}
+
+impl Recorder {
+    /// Append to `path`, creating it if needed.
+    pub fn open(path: &Path, rotation: Rotation) -> Result<Self> {
+        let file = append(path).with_context(|| format!("couldn't open {}", path.display()))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotation,
+            file,
+            size,
+            opened: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, frame: &Frame) -> Result<()> {
+        let mut line = serde_json::to_vec(frame)?;
+        line.push(b'\n');
+        let len = line.len() as u64;
+
+        let too_big = self
+            .rotation
+            .max_size
+            .is_some_and(|max| self.size + len > max);
+        let too_old = self
+            .rotation
+            .max_age
+            .is_some_and(|max| self.opened.elapsed() >= max);
+        if self.size > 0 && (too_big || too_old) {
+            self.rotate()
+                .with_context(|| format!("couldn't rotate {}", self.path.display()))?;
+        }
+
+        // One write per line, so readers never see half a frame.
+        self.file
+            .write_all(&line)
+            .with_context(|| format!("couldn't write {}", self.path.display()))?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// `path` becomes `path.1`, `path.1` becomes `path.2`, and so on up to
+    /// `keep`; then start over with an empty `path`.
+    fn rotate(&mut self) -> io::Result<()> {
+        let keep = self.rotation.keep;
+        if keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            ignore_missing(fs::remove_file(rotated(&self.path, keep)))?;
+            for n in (1..keep).rev() {
+                ignore_missing(fs::rename(
+                    rotated(&self.path, n),
+                    rotated(&self.path, n + 1),
+                ))?;
+            }
+            fs::rename(&self.path, rotated(&self.path, 1))?;
+        }
+        self.file = append(&self.path)?;
+        self.size = 0;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+/// Every frame in a recording, oldest first. A cut-off last line (the
+/// recorder was killed mid-write) is skipped; anything else that doesn't
+/// parse is an error.
+pub fn read(path: &Path) -> Result<Vec<Frame>> {
+    let file = File::open(path).with_context(|| format!("couldn't open {}", path.display()))?;
+    let lines: Vec<String> = BufReader::new(file)
+        .lines()
+        .collect::<io::Result<_>>()
+        .with_context(|| format!("couldn't read {}", path.display()))?;
+    let mut frames = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(frame) => frames.push(frame),
+            Err(e) if e.is_eof() && i + 1 == lines.len() => {}
+            Err(e) => bail!(
+                "{}:{}: not a recorded snapshot: {}",
+                path.display(),
+                i + 1,
+                e
+            ),
+        }
+    }
+    Ok(frames)
+}
+
+/// `path.n`
+pub fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    name.into()
+}
+
+fn append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn ignore_missing(result: io::Result<()>) -> io::Result<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// `100MB`, `1.5G`, `512k`, `1024b`. A bare number is megabytes, like in
+/// filters.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let lower = s.trim().to_lowercase();
+    let split = lower
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+    let mult: u64 = match unit.trim() {
+        "b" => 1,
+        "k" | "kb" | "kib" => 1024,
+        "" | "m" | "mb" | "mib" => 1024 * 1024,
+        "g" | "gb" | "gib" => 1024 * 1024 * 1024,
+        _ => bail!("'{}' is not a size (like 100MB or 1GB)", s),
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() && n > 0.0 => Ok((n * mult as f64) as u64),
+        _ => bail!("'{}' is not a size (like 100MB or 1GB)", s),
+    }
+}
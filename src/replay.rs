@@ -0,0 +1,211 @@
+//! Playing a `--record` file back through the TUI (`procsnipe replay`).
+//!
+//! A [`Player`] stands in for the live collector: the app asks it for the
+//! next snapshot the same way, and it hands out recorded frames at the pace
+//! they were recorded (times the speed), or wherever it was stepped or
+//! scrubbed to.
+
+use crate::collector::Snapshot;
+use crate::command::Seek;
+use crate::record::Frame;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Duration as Days, Local, NaiveTime, TimeZone};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Playback speeds `+`/`-` step through.
+const SPEEDS: [f64; 9] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
+
+pub struct Player {
+    frames: Vec<Frame>,
+    position: usize,
+    playing: bool,
+    speed: f64,
+    /// When playback (re)started, and the recording time it started from.
+    clock: (Instant, u64),
+    /// The position moved and the app hasn't seen the new frame yet.
+    changed: bool,
+}
+
+impl Player {
+    /// Starts paused on the first frame.
+    pub fn new(frames: Vec<Frame>) -> Result<Self> {
+        if frames.is_empty() {
+            bail!("the recording is empty");
+        }
+        let start = frames[0].time_ms;
+        Ok(Self {
+            frames,
+            position: 0,
+            playing: false,
+            speed: 1.0,
+            clock: (Instant::now(), start),
+            changed: true,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Index of the frame on screen.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frames[self.position]
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// How far through the recording, 0.0 to 1.0, by time.
+    pub fn progress(&self) -> f64 {
+        let (start, end) = self.span();
+        if end == start {
+            return 1.0;
+        }
+        (self.frame().time_ms - start) as f64 / (end - start) as f64
+    }
+
+    /// Play or pause. Playing from the last frame starts over.
+    pub fn toggle(&mut self) {
+        if !self.playing && self.position + 1 == self.frames.len() {
+            self.jump(0);
+        }
+        self.playing = !self.playing;
+        self.sync_clock();
+    }
+
+    /// Next speed up or down; returns the new one.
+    pub fn change_speed(&mut self, faster: bool) -> f64 {
+        let next = if faster {
+            SPEEDS.iter().find(|&&s| s > self.speed)
+        } else {
+            SPEEDS.iter().rev().find(|&&s| s < self.speed)
+        };
+        if let Some(&speed) = next {
+            self.advance();
+            self.speed = speed;
+            self.sync_clock();
+        }
+        self.speed
+    }
+
+    /// Move `n` frames (back when negative) and pause there.
+    pub fn step(&mut self, n: isize) {
+        self.playing = false;
+        let target = self.position.saturating_add_signed(n);
+        self.jump(target.min(self.frames.len() - 1));
+    }
+
+    /// Jump to a fraction (0.0 to 1.0) of the recording's time span.
+    pub fn scrub(&mut self, fraction: f64) {
+        let (start, end) = self.span();
+        let at = start + ((end - start) as f64 * fraction.clamp(0.0, 1.0)) as u64;
+        self.jump(self.index_at(at));
+    }
+
+    pub fn seek(&mut self, seek: Seek) -> Result<()> {
+        match seek {
+            Seek::Percent(percent) => self.scrub(percent / 100.0),
+            Seek::Time(secs) => {
+                let Some(at) = self.next_time_of_day(secs) else {
+                    bail!("the recording ends before then");
+                };
+                self.jump(self.index_at(at));
+            }
+        }
+        Ok(())
+    }
+
+    /// The frame to show if it changed since the last call, moving along
+    /// first when playing. Reaching the end pauses.
+    pub fn latest(&mut self) -> Option<Snapshot> {
+        self.advance();
+        if !std::mem::take(&mut self.changed) {
+            return None;
+        }
+        let frame = self.frame();
+        Some(Snapshot {
+            processes: frame.processes.clone(),
+            totals: frame.totals,
+            collected_at: Instant::now(),
+            time: UNIX_EPOCH + Duration::from_millis(frame.time_ms),
+            took: Duration::from_millis(frame.took_ms),
+        })
+    }
+
+    fn advance(&mut self) {
+        if !self.playing {
+            return;
+        }
+        let (started, from) = self.clock;
+        let now = from + (started.elapsed().as_millis() as f64 * self.speed) as u64;
+        let target = self.index_at(now);
+        if target > self.position {
+            self.position = target;
+            self.changed = true;
+        }
+        if self.position + 1 == self.frames.len() {
+            self.playing = false;
+        }
+    }
+
+    fn jump(&mut self, position: usize) {
+        if position != self.position {
+            self.position = position;
+            self.changed = true;
+        }
+        self.sync_clock();
+    }
+
+    fn sync_clock(&mut self) {
+        self.clock = (Instant::now(), self.frame().time_ms);
+    }
+
+    fn span(&self) -> (u64, u64) {
+        let end = self.frames[self.frames.len() - 1].time_ms;
+        (self.frames[0].time_ms, end.max(self.frames[0].time_ms))
+    }
+
+    /// Last frame recorded at or before `time_ms`.
+    fn index_at(&self, time_ms: u64) -> usize {
+        self.frames
+            .partition_point(|f| f.time_ms <= time_ms)
+            .saturating_sub(1)
+    }
+
+    /// The first time the local clock shows `secs` past midnight during
+    /// the recording. A minute's slack at the start, so `21:04` still finds
+    /// a recording that began at 21:04:30.
+    fn next_time_of_day(&self, secs: u32) -> Option<u64> {
+        let (start, end) = self.span();
+        let start_local: DateTime<Local> = (UNIX_EPOCH + Duration::from_millis(start)).into();
+        let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, 0)?;
+        let mut day = start_local.date_naive();
+        loop {
+            let at = Local.from_local_datetime(&day.and_time(time)).earliest()?;
+            let ms = at.timestamp_millis().max(0) as u64;
+            if ms + 60_000 > start {
+                return (ms <= end).then_some(ms.max(start));
+            }
+            day = day.checked_add_signed(Days::days(1))?;
+        }
+    }
+}
+
+/// `21:04:05` in local time.
+pub fn clock(time_ms: u64) -> String {
+    let time: DateTime<Local> = (UNIX_EPOCH + Duration::from_millis(time_ms)).into();
+    time.format("%H:%M:%S").to_string()
+}
@@ -0,0 +1,511 @@
+//! Where process data comes from. Everything above this layer (the TUI,
+//! filters, views) talks to a [`ProcessSource`]: [`SysinfoSource`] reads the
+//! real system, [`FakeSource`] serves an in-memory list for tests and for
+//! tools that embed procsnipe.
+
+use crate::ProcessInfo;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, System, Users};
+
+/// Signals that can be sent to a process. Not every platform supports all
+/// of them; sending an unsupported one is an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Hup,
+    Int,
+    Quit,
+    Kill,
+    Term,
+    Stop,
+    Cont,
+    Usr1,
+    Usr2,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 9] = [
+        Signal::Hup,
+        Signal::Int,
+        Signal::Quit,
+        Signal::Kill,
+        Signal::Term,
+        Signal::Stop,
+        Signal::Cont,
+        Signal::Usr1,
+        Signal::Usr2,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Signal::Hup => "HUP",
+            Signal::Int => "INT",
+            Signal::Quit => "QUIT",
+            Signal::Kill => "KILL",
+            Signal::Term => "TERM",
+            Signal::Stop => "STOP",
+            Signal::Cont => "CONT",
+            Signal::Usr1 => "USR1",
+            Signal::Usr2 => "USR2",
+        }
+    }
+
+    /// Linux signal number.
+    pub fn number(self) -> u8 {
+        match self {
+            Signal::Hup => 1,
+            Signal::Int => 2,
+            Signal::Quit => 3,
+            Signal::Kill => 9,
+            Signal::Usr1 => 10,
+            Signal::Usr2 => 12,
+            Signal::Term => 15,
+            Signal::Cont => 18,
+            Signal::Stop => 19,
+        }
+    }
+
+    /// `TERM`, `sigterm`, `SIGTERM` or `15`.
+    pub fn from_name(s: &str) -> Option<Self> {
+        let upper = s.trim().to_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        Self::ALL
+            .into_iter()
+            .find(|sig| sig.name() == name || sig.number().to_string() == name)
+    }
+
+    /// Signals that end the process unless it handles them.
+    pub fn terminates(self) -> bool {
+        !matches!(
+            self,
+            Signal::Stop | Signal::Cont | Signal::Usr1 | Signal::Usr2
+        )
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SIG{}", self.name())
+    }
+}
+
+/// The process is there but we aren't allowed to signal it. Returned
+/// (inside the `anyhow::Error`) by `signal` and `kill`, so callers can tell
+/// it apart from a process that vanished ([`NoSuchProcess`]).
+#[derive(Debug)]
+pub struct PermissionDenied(pub u32);
+
+impl fmt::Display for PermissionDenied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "permission denied for PID {}", self.0)
+    }
+}
+
+impl std::error::Error for PermissionDenied {}
+
+/// No process with this PID (any more).
+#[derive(Debug)]
+pub struct NoSuchProcess(pub u32);
+
+impl fmt::Display for NoSuchProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no process with PID {}", self.0)
+    }
+}
+
+impl std::error::Error for NoSuchProcess {}
+
+/// Whole-machine numbers that go with a snapshot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Totals {
+    /// Average over all cores, 0-100.
+    pub cpu: f32,
+    /// Bytes.
+    pub memory_used: u64,
+    /// Bytes. 0 when the source doesn't know.
+    pub memory_total: u64,
+}
+
+/// A place to list and act on processes.
+pub trait ProcessSource: Send {
+    /// Every process right now. `is_game` and `is_protected` are left false;
+    /// they depend on the config and are marked by the caller.
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>>;
+
+    /// System-wide CPU and memory as of the last snapshot.
+    fn totals(&mut self) -> Totals {
+        Totals::default()
+    }
+
+    fn signal(&mut self, pid: u32, signal: Signal) -> Result<()>;
+
+    /// Change the nice value (-20 to 19).
+    fn renice(&mut self, pid: u32, nice: i32) -> Result<()>;
+
+    /// Forceful kill.
+    fn kill(&mut self, pid: u32) -> Result<()> {
+        self.signal(pid, Signal::Kill)
+    }
+
+    /// Extra label/value rows for the details view (command line, paths and
+    /// so on). Empty when the source has nothing beyond the snapshot.
+    fn details(&mut self, _pid: u32) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// The kernel function the process is sleeping in, where the platform
+    /// says (`/proc/<pid>/wchan` on Linux).
+    fn wchan(&mut self, _pid: u32) -> Option<String> {
+        None
+    }
+}
+
+/// The live system, read through sysinfo.
+pub struct SysinfoSource {
+    sys: System,
+    users: Users,
+    /// CPU usage needs two refreshes to mean anything; this is the gap
+    /// between them on the first snapshot.
+    sample: Duration,
+    primed: bool,
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        Self {
+            sys: System::new(),
+            users: Users::new_with_refreshed_list(),
+            sample: sysinfo::MINIMUM_CPU_UPDATE_INTERVAL,
+            primed: false,
+        }
+    }
+
+    /// Measure CPU over `interval` for the first snapshot instead of the
+    /// shortest interval sysinfo allows. Longer is steadier.
+    pub fn with_sample_interval(mut self, interval: Duration) -> Self {
+        self.sample = interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        self
+    }
+
+    /// Refresh just these PIDs and return the first.
+    fn refresh(&mut self, pids: &[u32]) -> Option<&sysinfo::Process> {
+        let pids: Vec<Pid> = pids.iter().map(|&p| Pid::from_u32(p)).collect();
+        self.sys
+            .refresh_processes(ProcessesToUpdate::Some(&pids), true);
+        self.sys.process(*pids.first()?)
+    }
+
+    fn find(&mut self, pid: u32) -> Result<&sysinfo::Process> {
+        self.refresh(&[pid])
+            .ok_or_else(|| NoSuchProcess(pid).into())
+    }
+
+    /// Work out why sending a signal failed: the process exited in the
+    /// meantime, or it isn't ours.
+    fn send_failed(&mut self, pid: u32) -> Result<()> {
+        match self.refresh(&[pid]).map(|p| p.status()) {
+            None | Some(ProcessStatus::Zombie) => Err(NoSuchProcess(pid).into()),
+            Some(_) => Err(PermissionDenied(pid).into()),
+        }
+    }
+}
+
+impl Default for SysinfoSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cloning opens a second, independent view of the same system, so a
+/// background thread can scan while the UI keeps one for kills.
+impl Clone for SysinfoSource {
+    fn clone(&self) -> Self {
+        Self::new().with_sample_interval(self.sample)
+    }
+}
+
+impl ProcessSource for SysinfoSource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>> {
+        if !self.primed {
+            self.sys.refresh_processes(ProcessesToUpdate::All, true);
+            self.sys.refresh_cpu_usage();
+            std::thread::sleep(self.sample);
+            self.primed = true;
+        }
+        self.sys.refresh_processes(ProcessesToUpdate::All, true);
+        self.sys.refresh_cpu_usage();
+        self.sys.refresh_memory();
+
+        let users = &self.users;
+        Ok(self
+            .sys
+            .processes()
+            .iter()
+            .map(|(pid, process)| ProcessInfo {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+                user: process
+                    .user_id()
+                    .and_then(|uid| users.get_user_by_id(uid))
+                    .map(|u| u.name().to_string())
+                    .unwrap_or_default(),
+                status: process.status().to_string(),
+                cpu_usage: process.cpu_usage(),
+                memory: process.memory(),
+                is_game: false,
+                is_protected: false,
+                parent: process.parent().map(|p| p.as_u32()),
+                start_time: process.start_time(),
+                threads: process.tasks().map_or(0, |t| t.len() as u32),
+                io_bytes: {
+                    let disk = process.disk_usage();
+                    disk.total_read_bytes + disk.total_written_bytes
+                },
+                leak: None,
+                hung: None,
+            })
+            .collect())
+    }
+
+    fn totals(&mut self) -> Totals {
+        Totals {
+            cpu: self.sys.global_cpu_usage(),
+            memory_used: self.sys.used_memory(),
+            memory_total: self.sys.total_memory(),
+        }
+    }
+
+    fn kill(&mut self, pid: u32) -> Result<()> {
+        // Plain kill() is TerminateProcess on Windows, which works where
+        // signals don't.
+        if !self.find(pid)?.kill() {
+            return self.send_failed(pid);
+        }
+        Ok(())
+    }
+
+    fn signal(&mut self, pid: u32, signal: Signal) -> Result<()> {
+        let sig = match signal {
+            Signal::Hup => sysinfo::Signal::Hangup,
+            Signal::Int => sysinfo::Signal::Interrupt,
+            Signal::Quit => sysinfo::Signal::Quit,
+            Signal::Kill => sysinfo::Signal::Kill,
+            Signal::Term => sysinfo::Signal::Term,
+            Signal::Stop => sysinfo::Signal::Stop,
+            Signal::Cont => sysinfo::Signal::Continue,
+            Signal::Usr1 => sysinfo::Signal::User1,
+            Signal::Usr2 => sysinfo::Signal::User2,
+        };
+        match self.find(pid)?.kill_with(sig) {
+            Some(true) => Ok(()),
+            Some(false) => self.send_failed(pid),
+            None => bail!("{} isn't supported on this platform", signal),
+        }
+    }
+
+    #[cfg(unix)]
+    fn renice(&mut self, pid: u32, nice: i32) -> Result<()> {
+        let output = std::process::Command::new("renice")
+            .args(["-n", &nice.to_string(), "-p", &pid.to_string()])
+            .output()
+            .context("couldn't run renice")?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn renice(&mut self, _pid: u32, _nice: i32) -> Result<()> {
+        bail!("renice isn't supported on this platform")
+    }
+
+    fn details(&mut self, pid: u32) -> Vec<(&'static str, String)> {
+        let Some(ppid) = self.refresh(&[pid]).map(|p| p.parent()) else {
+            return Vec::new();
+        };
+        let parent = match ppid {
+            Some(ppid) => {
+                let name = self
+                    .refresh(&[ppid.as_u32()])
+                    .map(|p| p.name().to_string_lossy().to_string())
+                    .unwrap_or_default();
+                format!("{} {}", ppid.as_u32(), name)
+            }
+            None => "-".to_string(),
+        };
+        let waiting = self.wchan(pid);
+        let Some(process) = self.sys.process(Pid::from_u32(pid)) else {
+            return Vec::new();
+        };
+
+        let run = process.run_time();
+        let disk = process.disk_usage();
+        let path = |p: Option<&std::path::Path>| {
+            p.map(|p| p.display().to_string())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let cmd: Vec<String> = process
+            .cmd()
+            .iter()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect();
+
+        let mut rows = vec![
+            ("Parent", parent),
+            (
+                "Running for",
+                format!("{}h {:02}m {:02}s", run / 3600, run / 60 % 60, run % 60),
+            ),
+            (
+                "Virtual mem",
+                format!("{} MB", process.virtual_memory() / 1024 / 1024),
+            ),
+            (
+                "Disk read",
+                format!("{} MB total", disk.total_read_bytes / 1024 / 1024),
+            ),
+            (
+                "Disk written",
+                format!("{} MB total", disk.total_written_bytes / 1024 / 1024),
+            ),
+            ("Executable", path(process.exe())),
+            ("Working dir", path(process.cwd())),
+            (
+                "Command",
+                if cmd.is_empty() {
+                    "-".to_string()
+                } else {
+                    cmd.join(" ")
+                },
+            ),
+        ];
+        if let Some(wchan) = waiting {
+            rows.push(("Waiting in", wchan));
+        }
+        rows
+    }
+
+    #[cfg(target_os = "linux")]
+    fn wchan(&mut self, pid: u32) -> Option<String> {
+        let wchan = std::fs::read_to_string(format!("/proc/{}/wchan", pid)).ok()?;
+        // "0" when it's running rather than waiting.
+        let wchan = wchan.trim();
+        (!wchan.is_empty() && wchan != "0").then(|| wchan.to_string())
+    }
+}
+
+#[derive(Default)]
+struct FakeState {
+    processes: Vec<ProcessInfo>,
+    signals: Vec<(u32, Signal)>,
+    nice: BTreeMap<u32, i32>,
+    denied: Vec<u32>,
+    wchan: BTreeMap<u32, String>,
+}
+
+/// A fixed, in-memory process list. Clones share the same list, so a kill
+/// through one clone disappears from the next snapshot of another.
+///
+/// Terminating signals remove the process, `STOP`/`CONT` flip its status,
+/// and everything sent is recorded for inspection.
+#[derive(Clone, Default)]
+pub struct FakeSource {
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeSource {
+    pub fn new(processes: Vec<ProcessInfo>) -> Self {
+        let source = Self::default();
+        source.set_processes(processes);
+        source
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Replace the list returned by the next snapshot.
+    pub fn set_processes(&self, processes: Vec<ProcessInfo>) {
+        self.state().processes = processes;
+    }
+
+    pub fn processes(&self) -> Vec<ProcessInfo> {
+        self.state().processes.clone()
+    }
+
+    /// Every signal delivered so far, in order.
+    pub fn signals(&self) -> Vec<(u32, Signal)> {
+        self.state().signals.clone()
+    }
+
+    /// Make signals to `pid` fail with [`PermissionDenied`].
+    pub fn deny(&self, pid: u32) {
+        self.state().denied.push(pid);
+    }
+
+    /// Nice value last set for `pid`.
+    pub fn nice(&self, pid: u32) -> Option<i32> {
+        self.state().nice.get(&pid).copied()
+    }
+
+    /// What `wchan` says `pid` is waiting in.
+    pub fn set_wchan(&self, pid: u32, wchan: &str) {
+        self.state().wchan.insert(pid, wchan.to_string());
+    }
+}
+
+impl ProcessSource for FakeSource {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>> {
+        Ok(self.processes())
+    }
+
+    /// Sums over the list; there's no machine behind it to know the total.
+    fn totals(&mut self) -> Totals {
+        let state = self.state();
+        Totals {
+            cpu: state.processes.iter().map(|p| p.cpu_usage).sum(),
+            memory_used: state.processes.iter().map(|p| p.memory).sum(),
+            memory_total: 0,
+        }
+    }
+
+    fn signal(&mut self, pid: u32, signal: Signal) -> Result<()> {
+        let mut state = self.state();
+        let index = state
+            .processes
+            .iter()
+            .position(|p| p.pid == pid)
+            .ok_or(NoSuchProcess(pid))?;
+        if state.denied.contains(&pid) {
+            return Err(PermissionDenied(pid).into());
+        }
+        state.signals.push((pid, signal));
+        match signal {
+            Signal::Stop => state.processes[index].status = "Stopped".to_string(),
+            Signal::Cont => state.processes[index].status = "Runnable".to_string(),
+            s if s.terminates() => {
+                state.processes.remove(index);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn renice(&mut self, pid: u32, nice: i32) -> Result<()> {
+        let mut state = self.state();
+        if !state.processes.iter().any(|p| p.pid == pid) {
+            return Err(NoSuchProcess(pid).into());
+        }
+        state.nice.insert(pid, nice);
+        Ok(())
+    }
+
+    fn wchan(&mut self, pid: u32) -> Option<String> {
+        self.state().wchan.get(&pid).cloned()
+    }
+}
@@ -0,0 +1,420 @@
+//! Color themes. Every color the UI draws with comes from a [`Theme`], picked
+//! by name in the config's `[theme]` table with optional per-slot overrides.
+//! Truecolor themes are mapped down to the 256 or 16 color palette on
+//! terminals that can't show them.
+
+use anyhow::{Result, anyhow, bail};
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// App title and header border.
+    pub accent: Color,
+    /// Separators, PID/user/status columns, footer.
+    pub muted: Color,
+    /// Process count, CPU column, list border.
+    pub info: Color,
+    /// Sort label, help keys, help border.
+    pub highlight: Color,
+    /// View label, memory column, view picker border.
+    pub secondary: Color,
+    /// Row states.
+    pub normal: Color,
+    pub game: Color,
+    pub cpu_high: Color,
+    pub cpu_medium: Color,
+    pub protected: Color,
+    pub suspended: Color,
+    pub selected_bg: Color,
+    pub selected_fg: Color,
+    /// Inline errors, e.g. a bad filter query.
+    pub error: Color,
+}
+
+/// Color slots that can be overridden from `[theme]`.
+const SLOTS: [&str; 14] = [
+    "accent",
+    "muted",
+    "info",
+    "highlight",
+    "secondary",
+    "normal",
+    "game",
+    "cpu_high",
+    "cpu_medium",
+    "protected",
+    "suspended",
+    "selected_bg",
+    "selected_fg",
+    "error",
+];
+
+pub const BUILTIN_THEMES: [&str; 5] = ["dark", "light", "solarized", "high-contrast", "colorblind"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// The original procsnipe look.
+    pub fn dark() -> Self {
+        Self {
+            accent: Color::Red,
+            muted: Color::DarkGray,
+            info: Color::Cyan,
+            highlight: Color::Yellow,
+            secondary: Color::Magenta,
+            normal: Color::White,
+            game: Color::Green,
+            cpu_high: Color::Red,
+            cpu_medium: Color::Yellow,
+            protected: Color::Blue,
+            suspended: Color::Gray,
+            selected_bg: Color::DarkGray,
+            selected_fg: Color::Reset,
+            error: Color::Red,
+        }
+    }
+
+    /// For terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            accent: Color::Red,
+            muted: Color::Gray,
+            info: Color::Blue,
+            highlight: Color::Rgb(0xb5, 0x89, 0x00),
+            secondary: Color::Magenta,
+            normal: Color::Black,
+            game: Color::Rgb(0x00, 0x80, 0x00),
+            cpu_high: Color::Red,
+            cpu_medium: Color::Rgb(0xc0, 0x60, 0x00),
+            protected: Color::Blue,
+            suspended: Color::Gray,
+            selected_bg: Color::Rgb(0xd0, 0xd8, 0xe8),
+            selected_fg: Color::Black,
+            error: Color::Red,
+        }
+    }
+
+    /// Ethan Schoonover's Solarized (dark).
+    pub fn solarized() -> Self {
+        Self {
+            accent: Color::Rgb(0xdc, 0x32, 0x2f),
+            muted: Color::Rgb(0x58, 0x6e, 0x75),
+            info: Color::Rgb(0x2a, 0xa1, 0x98),
+            highlight: Color::Rgb(0xb5, 0x89, 0x00),
+            secondary: Color::Rgb(0xd3, 0x36, 0x82),
+            normal: Color::Rgb(0x93, 0xa1, 0xa1),
+            game: Color::Rgb(0x85, 0x99, 0x00),
+            cpu_high: Color::Rgb(0xdc, 0x32, 0x2f),
+            cpu_medium: Color::Rgb(0xcb, 0x4b, 0x16),
+            protected: Color::Rgb(0x26, 0x8b, 0xd2),
+            suspended: Color::Rgb(0x65, 0x7b, 0x83),
+            selected_bg: Color::Rgb(0x07, 0x36, 0x42),
+            selected_fg: Color::Rgb(0xee, 0xe8, 0xd5),
+            error: Color::Rgb(0xdc, 0x32, 0x2f),
+        }
+    }
+
+    /// Bright colors only, selection shown as inverted text.
+    pub fn high_contrast() -> Self {
+        Self {
+            accent: Color::LightRed,
+            muted: Color::White,
+            info: Color::LightCyan,
+            highlight: Color::LightYellow,
+            secondary: Color::LightMagenta,
+            normal: Color::White,
+            game: Color::LightGreen,
+            cpu_high: Color::LightRed,
+            cpu_medium: Color::LightYellow,
+            protected: Color::LightBlue,
+            suspended: Color::Gray,
+            selected_bg: Color::White,
+            selected_fg: Color::Black,
+            error: Color::LightRed,
+        }
+    }
+
+    /// Okabe-Ito palette: distinguishable with the common forms of color
+    /// blindness, and no red/green pairs carrying meaning.
+    pub fn colorblind() -> Self {
+        Self {
+            accent: Color::Rgb(0xe6, 0x9f, 0x00),
+            muted: Color::DarkGray,
+            info: Color::Rgb(0x56, 0xb4, 0xe9),
+            highlight: Color::Rgb(0xf0, 0xe4, 0x42),
+            secondary: Color::Rgb(0xcc, 0x79, 0xa7),
+            normal: Color::White,
+            game: Color::Rgb(0x56, 0xb4, 0xe9),
+            cpu_high: Color::Rgb(0xd5, 0x5e, 0x00),
+            cpu_medium: Color::Rgb(0xf0, 0xe4, 0x42),
+            protected: Color::Rgb(0x00, 0x72, 0xb2),
+            suspended: Color::Gray,
+            selected_bg: Color::DarkGray,
+            selected_fg: Color::Reset,
+            error: Color::Rgb(0xd5, 0x5e, 0x00),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "solarized" => Some(Self::solarized()),
+            "high-contrast" | "high_contrast" => Some(Self::high_contrast()),
+            "colorblind" | "colorblind-safe" => Some(Self::colorblind()),
+            _ => None,
+        }
+    }
+
+    fn slot_mut(&mut self, slot: &str) -> Option<&mut Color> {
+        Some(match slot {
+            "accent" => &mut self.accent,
+            "muted" => &mut self.muted,
+            "info" => &mut self.info,
+            "highlight" => &mut self.highlight,
+            "secondary" => &mut self.secondary,
+            "normal" => &mut self.normal,
+            "game" => &mut self.game,
+            "cpu_high" => &mut self.cpu_high,
+            "cpu_medium" => &mut self.cpu_medium,
+            "protected" => &mut self.protected,
+            "suspended" => &mut self.suspended,
+            "selected_bg" => &mut self.selected_bg,
+            "selected_fg" => &mut self.selected_fg,
+            "error" => &mut self.error,
+            _ => return None,
+        })
+    }
+
+    fn colors_mut(&mut self) -> [&mut Color; 14] {
+        [
+            &mut self.accent,
+            &mut self.muted,
+            &mut self.info,
+            &mut self.highlight,
+            &mut self.secondary,
+            &mut self.normal,
+            &mut self.game,
+            &mut self.cpu_high,
+            &mut self.cpu_medium,
+            &mut self.protected,
+            &mut self.suspended,
+            &mut self.selected_bg,
+            &mut self.selected_fg,
+            &mut self.error,
+        ]
+    }
+
+    /// Map every color down to what the terminal can show.
+    pub fn downgrade(mut self, depth: ColorDepth) -> Self {
+        for color in self.colors_mut() {
+            *color = depth.adapt(*color);
+        }
+        self
+    }
+}
+
+/// The `[theme]` config table. Unknown keys are treated as color overrides
+/// and rejected in [`ThemeConfig::resolve`] if they don't name a slot.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// One of [`BUILTIN_THEMES`].
+    pub name: String,
+    /// `auto`, `truecolor`, `256` or `16`.
+    pub colors: String,
+    /// Per-slot overrides, e.g. `game = "#00ff00"`.
+    #[serde(flatten)]
+    pub overrides: BTreeMap<String, String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "dark".to_string(),
+            colors: "auto".to_string(),
+            overrides: BTreeMap::new(),
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// Build the theme with overrides applied and colors fitted to the
+    /// terminal.
+    pub fn resolve(&self) -> Result<Theme> {
+        let mut theme = Theme::builtin(&self.name).ok_or_else(|| {
+            anyhow!(
+                "theme.name = \"{}\" is not a built-in theme (try {})",
+                self.name,
+                BUILTIN_THEMES.join(", ")
+            )
+        })?;
+
+        for (slot, value) in &self.overrides {
+            let color = Color::from_str(value).map_err(|_| {
+                anyhow!(
+                    "theme.{} = \"{}\" is not a color (use a name like \"lightblue\", \"#rrggbb\" or a 0-255 index)",
+                    slot,
+                    value
+                )
+            })?;
+            match theme.slot_mut(slot) {
+                Some(c) => *c = color,
+                None => bail!(
+                    "unknown theme color '{}' (slots: {})",
+                    slot,
+                    SLOTS.join(", ")
+                ),
+            }
+        }
+
+        let depth = match self.colors.as_str() {
+            "auto" => ColorDepth::detect(),
+            other => ColorDepth::from_name(other).ok_or_else(|| {
+                anyhow!(
+                    "theme.colors = \"{}\" must be auto, truecolor, 256 or 16",
+                    other
+                )
+            })?,
+        };
+        Ok(theme.downgrade(depth))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "truecolor" | "24bit" => Some(ColorDepth::TrueColor),
+            "256" => Some(ColorDepth::Ansi256),
+            "16" => Some(ColorDepth::Ansi16),
+            _ => None,
+        }
+    }
+
+    /// Guess from `COLORTERM` and `TERM`. Windows Terminal and modern
+    /// conhost both do truecolor.
+    pub fn detect() -> Self {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        if colorterm.contains("truecolor") || colorterm.contains("24bit") {
+            return ColorDepth::TrueColor;
+        }
+        if cfg!(target_os = "windows") {
+            return ColorDepth::TrueColor;
+        }
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    fn adapt(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorDepth::TrueColor, c) => c,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(rgb_to_256(r, g, b)),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => rgb_to_16(r, g, b),
+            (ColorDepth::Ansi16, Color::Indexed(i)) => {
+                let (r, g, b) = indexed_to_rgb(i);
+                rgb_to_16(r, g, b)
+            }
+            (_, c) => c,
+        }
+    }
+}
+
+/// Nearest entry in the xterm 6x6x6 cube or grayscale ramp.
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let nearest = |v: u8| {
+        LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, l)| (v as i32 - **l as i32).abs())
+            .map(|(i, _)| i as u8)
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (nearest(r), nearest(g), nearest(b));
+    let cube = 16 + 36 * ri + 6 * gi + bi;
+    let cube_rgb = (
+        LEVELS[ri as usize],
+        LEVELS[gi as usize],
+        LEVELS[bi as usize],
+    );
+
+    let avg = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_index = ((avg.saturating_sub(8)) / 10).min(23) as u8;
+    let gray_level = 8 + 10 * gray_index;
+    let gray = 232 + gray_index;
+
+    if distance((r, g, b), (gray_level, gray_level, gray_level)) < distance((r, g, b), cube_rgb) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn indexed_to_rgb(i: u8) -> (u8, u8, u8) {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match i {
+        0..=15 => ANSI16[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                LEVELS[(i / 36) as usize],
+                LEVELS[((i / 6) % 6) as usize],
+                LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        }
+    }
+}
+
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn rgb_to_16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(c, _)| *c)
+        .unwrap_or(Color::White)
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
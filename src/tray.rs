@@ -1,30 +1,74 @@
+use crate::alerts::{self, Alerts, Rule};
+use crate::config::{Config, ConfigWatcher};
+use crate::glyphs;
+use crate::hung::HungMonitor;
+use crate::leaks::LeakMonitor;
+use crate::source::{ProcessSource, SysinfoSource};
 use anyhow::Result;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
-use sysinfo::{ProcessesToUpdate, System};
+use std::time::{Duration, SystemTime};
 use tray_icon::{
     Icon, TrayIconBuilder,
     menu::{Menu, MenuEvent, MenuItem},
 };
 
 pub struct TrayApp {
-    sys: Arc<Mutex<System>>,
-    last_notification: Instant,
-    notification_cooldown: Duration,
+    source: SysinfoSource,
+    alerts: Alerts,
+    leaks: LeakMonitor,
+    hung: HungMonitor,
+    config: Config,
+    config_watcher: ConfigWatcher,
+}
+
+/// The config's `[[alerts]]`, or without any the old CPU check from
+/// `[tray]`.
+fn rules(config: &Config) -> Vec<Rule> {
+    if config.alerts.is_empty() {
+        let cooldown = Duration::from_secs(config.tray.cooldown_secs);
+        return vec![Rule::cpu_above(config.tray.cpu_alert, cooldown)];
+    }
+    // The config was validated when it was loaded.
+    alerts::load_rules(&config.alerts).unwrap_or_default()
 }
 
 impl TrayApp {
-    pub fn new() -> Self {
+    pub fn new(config: Config, config_watcher: ConfigWatcher) -> Self {
         Self {
-            sys: Arc::new(Mutex::new(System::new_all())),
-            last_notification: Instant::now(),
-            notification_cooldown: Duration::from_secs(60), // Don't spam notifications
+            source: SysinfoSource::new(),
+            alerts: Alerts::new(rules(&config)),
+            leaks: LeakMonitor::new(config.leaks.clone()),
+            hung: HungMonitor::new(config.hung.clone()),
+            config,
+            config_watcher,
+        }
+    }
+
+    /// Pick up config file edits; a broken file keeps the old settings.
+    fn reload_config(&mut self) {
+        match self.config_watcher.poll() {
+            Some(Ok(config)) => {
+                self.alerts.set_rules(rules(&config));
+                self.leaks.set_config(config.leaks.clone());
+                self.hung.set_config(config.hung.clone());
+                self.config = config;
+                println!("config reloaded");
+            }
+            Some(Err(err)) => println!(
+                "{} config error, keeping old settings: {:#}",
+                glyphs::get().warning,
+                err
+            ),
+            None => {}
         }
     }
 
     pub fn run(&mut self) -> Result<()> {
-        println!("🎯 procsnipe running in system tray");
-        println!("⚠️  DISCLAIMER: procsnipe monitors system processes in the background.");
+        let g = glyphs::get();
+        println!("{} procsnipe running in system tray", g.logo);
+        println!(
+            "{} DISCLAIMER: procsnipe monitors system processes in the background.",
+            g.warning
+        );
         println!("   This is for performance monitoring only.");
         println!("   Killing critical processes can crash your system.");
         println!("   Use at your own risk.\n");
@@ -92,57 +136,39 @@ impl TrayApp {
             }
 
             // Monitor processes
+            self.reload_config();
             self.monitor_processes()?;
 
             // Sleep to avoid high CPU usage
-            std::thread::sleep(Duration::from_secs(5));
+            std::thread::sleep(Duration::from_secs(self.config.tray.poll_secs));
         }
 
         Ok(())
     }
 
     fn monitor_processes(&mut self) -> Result<()> {
-        let mut sys = self.sys.lock().unwrap();
-        sys.refresh_processes(ProcessesToUpdate::All, true);
-
-        // Check for high CPU usage
-        let mut high_cpu_processes = Vec::new();
-        let mut game_detected = false;
-
-        for (_, process) in sys.processes() {
-            let cpu = process.cpu_usage();
-
-            if cpu > 80.0 {
-                high_cpu_processes.push((process.name().to_string_lossy().to_string(), cpu));
-            }
-
-            // Check for games
-            let name = process.name().to_string_lossy().to_lowercase();
-            if self.is_game_process(&name) && !game_detected {
-                game_detected = true;
-            }
+        let mut processes = self.source.snapshot()?;
+        self.config.mark(&mut processes);
+        let now = SystemTime::now();
+        for leak in self.leaks.update(&mut processes, now) {
+            println!("{} {}", glyphs::get().warning, leak);
         }
-
-        // Send notifications (with cooldown)
-        if self.last_notification.elapsed() > self.notification_cooldown {
-            if !high_cpu_processes.is_empty() {
-                let top_process = &high_cpu_processes[0];
-                println!("⚠️  High CPU: {} ({:.1}%)", top_process.0, top_process.1);
-                self.last_notification = Instant::now();
+        let source = &mut self.source;
+        for hung in self
+            .hung
+            .update(&mut processes, now, |pid| source.wchan(pid))
+        {
+            println!("{} {}", glyphs::get().warning, hung);
+        }
+        let fired = self.alerts.check(&processes, now, &mut self.source);
+        for alert in fired {
+            if alert.notify || !alert.outcomes.is_empty() {
+                println!("{} {}", glyphs::get().warning, alert);
             }
         }
-
         Ok(())
     }
 
-    fn is_game_process(&self, name: &str) -> bool {
-        let indicators = [
-            "game", "steam", "epic", "uplay", "origin", "riot", "valorant", "league", "csgo",
-            "cs2", "dota",
-        ];
-        indicators.iter().any(|&i| name.contains(i))
-    }
-
     fn launch_tui(&self) -> Result<()> {
         use std::process::Command;
 
@@ -165,18 +191,42 @@ impl TrayApp {
     }
 
     fn show_about(&self) {
-        println!("\n╔════════════════════════════════════╗");
-        println!("║        procsnipe v1.0              ║");
-        println!("║   TUI Process Manager for Windows  ║");
-        println!("╟────────────────────────────────────╢");
-        println!("║  ⚠️  DISCLAIMER:                   ║");
-        println!("║  - Monitors system processes       ║");
-        println!("║  - Can terminate processes         ║");
-        println!("║  - Use at your own risk            ║");
-        println!("║  - Killing system processes can    ║");
-        println!("║    cause system instability        ║");
-        println!("╟────────────────────────────────────╢");
-        println!("║  Made with 💀 by berochitiri       ║");
-        println!("╚════════════════════════════════════╝\n");
+        let g = glyphs::get();
+        let lines = [
+            "       procsnipe v1.0".to_string(),
+            "  TUI Process Manager for Windows".to_string(),
+            String::new(),
+            format!(" {} DISCLAIMER:", g.warning),
+            " - Monitors system processes".to_string(),
+            " - Can terminate processes".to_string(),
+            " - Use at your own risk".to_string(),
+            " - Killing system processes can".to_string(),
+            "   cause system instability".to_string(),
+            String::new(),
+            format!(" Made with {} by berochitiri", g.skull),
+        ];
+
+        // Box characters and padding are computed so emoji widths and ASCII
+        // mode both line up.
+        let (tl, tr, bl, br, h, v) = if glyphs::is_ascii() {
+            ("+", "+", "+", "+", "-", "|")
+        } else {
+            ("╔", "╗", "╚", "╝", "═", "║")
+        };
+        let width = 36;
+        println!("\n{}{}{}", tl, h.repeat(width), tr);
+        for line in &lines {
+            if line.is_empty() {
+                let (sl, sr, sh) = if glyphs::is_ascii() {
+                    ("+", "+", "-")
+                } else {
+                    ("╟", "╢", "─")
+                };
+                println!("{}{}{}", sl, sh.repeat(width), sr);
+            } else {
+                println!("{}{}{}", v, glyphs::fit(line, width), v);
+            }
+        }
+        println!("{}{}{}\n", bl, h.repeat(width), br);
     }
 }
@@ -0,0 +1,659 @@
+//! Drawing. Reads the [`App`] state and renders one frame.
+
+use crate::app::{App, HIGHLIGHT_WIDTH, Mode};
+use crate::command;
+use crate::diff::{self, Diff};
+use crate::events::{self, EventKind};
+use crate::glyphs::{self, fit};
+use crate::keymap::Action;
+use crate::menu::MenuItem;
+use crate::output;
+use crate::replay;
+use crate::source::ProcessSource;
+use crate::theme::Theme;
+use crate::view::Column;
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+};
+
+/// Draw the whole screen for the current mode.
+pub fn ui<S: ProcessSource + Clone + 'static>(f: &mut Frame, app: &mut App<S>) {
+    // Replays get a second header line for the timeline.
+    let header_height = if app.player().is_some() { 4 } else { 3 };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(header_height),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+    let theme = app.theme.clone();
+
+    // Header
+    let header = Paragraph::new(vec![Line::from(vec![
+        Span::styled(
+            format!("{} procsnipe ", glyphs::get().logo),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled("| ", Style::default().fg(theme.muted)),
+        Span::styled(
+            format!("processes: {} ", app.processes.len()),
+            Style::default().fg(theme.info),
+        ),
+        Span::styled("| ", Style::default().fg(theme.muted)),
+        Span::styled(
+            format!(
+                "sort: {:?} {} ",
+                app.sort_mode,
+                if app.sort_mode.descending_by_default() != app.sort_reversed {
+                    glyphs::get().down
+                } else {
+                    glyphs::get().up
+                }
+            ),
+            Style::default().fg(theme.highlight),
+        ),
+        Span::styled("| ", Style::default().fg(theme.muted)),
+        Span::styled(
+            format!(
+                "view: {} ",
+                app.views
+                    .get(app.active_view)
+                    .map(|v| v.name.as_str())
+                    .unwrap_or("all")
+            ),
+            Style::default().fg(theme.secondary),
+        ),
+        Span::styled("| ", Style::default().fg(theme.muted)),
+        if let Some(player) = app.player() {
+            Span::styled(
+                format!(
+                    "REPLAY {} x{}",
+                    if player.is_playing() {
+                        glyphs::get().play
+                    } else {
+                        glyphs::get().pause
+                    },
+                    player.speed()
+                ),
+                Style::default()
+                    .fg(theme.secondary)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else if app.paused {
+            Span::styled(
+                "PAUSED",
+                Style::default()
+                    .fg(theme.cpu_medium)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::styled(
+                format!("every {}", command::format_duration(app.refresh_rate)),
+                Style::default().fg(theme.info),
+            )
+        },
+        if app.is_stale() {
+            Span::styled(
+                format!(
+                    " {} stale {}s (last scan took {})",
+                    glyphs::get().warning,
+                    app.last_snapshot.elapsed().as_secs(),
+                    command::format_duration(app.collect_took)
+                ),
+                Style::default()
+                    .fg(theme.error)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else {
+            Span::raw("")
+        },
+    ])])
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(glyphs::get().border)
+            .border_style(Style::default().fg(theme.accent)),
+    );
+    f.render_widget(header, chunks[0]);
+
+    if let Some(player) = app.player() {
+        let row = Rect {
+            x: chunks[0].x + 1,
+            y: chunks[0].y + 2,
+            width: chunks[0].width.saturating_sub(2),
+            height: 1,
+        };
+        let time = format!(" {} ", replay::clock(player.frame().time_ms));
+        let count = format!(" {}/{} ", player.position() + 1, player.len());
+        let width = row.width.saturating_sub((time.len() + count.len()) as u16);
+        let head = ((width.saturating_sub(1)) as f64 * player.progress()).round() as usize;
+        let rest = (width as usize).saturating_sub(head + 1);
+        let timeline = Line::from(vec![
+            Span::styled(time.clone(), Style::default().fg(theme.highlight)),
+            Span::styled(
+                glyphs::get().played.repeat(head),
+                Style::default().fg(theme.secondary),
+            ),
+            Span::styled(glyphs::get().active, Style::default().fg(theme.accent)),
+            Span::styled(
+                glyphs::get().unplayed.repeat(rest),
+                Style::default().fg(theme.muted),
+            ),
+            Span::styled(count, Style::default().fg(theme.info)),
+        ]);
+        f.render_widget(Paragraph::new(timeline), row);
+        app.timeline_area = Rect {
+            x: row.x + time.len() as u16,
+            width,
+            ..row
+        };
+    }
+
+    // Process list or help
+    if app.mode == Mode::Help {
+        let entries = app.keymap.help_entries();
+        let key_width = entries
+            .iter()
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        let key_line = |keys: &str, desc: &str| {
+            Line::from(vec![
+                Span::styled(
+                    format!("  {:<width$} ", keys, width = key_width),
+                    Style::default().fg(theme.highlight),
+                ),
+                Span::raw(format!("- {}", desc)),
+            ])
+        };
+
+        let mut help_text = vec![
+            Line::from(""),
+            Line::from(Span::styled(
+                format!("{} procsnipe controls", glyphs::get().logo),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+        ];
+        for (keys, desc) in &entries {
+            help_text.push(key_line(keys, desc));
+        }
+        help_text.push(key_line("ESC", "Exit search/help"));
+        help_text.push(Line::from(vec![
+            Span::raw(" ".repeat(key_width + 5)),
+            Span::styled(
+                "search takes queries, e.g. cpu>20 && mem>500MB && name~\"chrome\"",
+                Style::default().fg(theme.muted),
+            ),
+        ]));
+        help_text.push(Line::from(""));
+        help_text.push(Line::from(Span::styled(
+            format!(
+                "press {} or ESC to close | rebind keys under [keys] in the config",
+                app.keymap.keys_for(Action::Help)
+            ),
+            Style::default().fg(theme.muted),
+        )));
+        let help = Paragraph::new(help_text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(glyphs::get().border)
+                .title("Help")
+                .border_style(Style::default().fg(theme.highlight)),
+        );
+        f.render_widget(help, chunks[1]);
+    } else if matches!(app.mode, Mode::Views | Mode::SaveView) {
+        let items: Vec<ListItem> = app
+            .views
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let key = if i < 9 {
+                    format!("{} ", i + 1)
+                } else {
+                    "  ".to_string()
+                };
+                let marker = if i == app.active_view {
+                    format!("{} ", glyphs::get().active)
+                } else {
+                    "  ".to_string()
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(key, Style::default().fg(theme.highlight)),
+                    Span::styled(marker, Style::default().fg(theme.info)),
+                    Span::styled(
+                        format!("{} ", fit(&v.name, 16)),
+                        Style::default().fg(theme.normal),
+                    ),
+                    Span::styled(
+                        format!("sort: {:<7} ", v.sort.as_str()),
+                        Style::default().fg(theme.info),
+                    ),
+                    Span::styled(
+                        if v.query.is_empty() {
+                            "(everything)".to_string()
+                        } else {
+                            v.query.clone()
+                        },
+                        Style::default().fg(theme.muted),
+                    ),
+                ]))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(glyphs::get().border)
+                    .title("Views")
+                    .border_style(Style::default().fg(theme.secondary)),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(theme.selected_bg)
+                    .fg(theme.selected_fg)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        f.render_stateful_widget(list, chunks[1], &mut app.view_state);
+    } else if app.mode == Mode::Details {
+        let pid = app.details_pid.unwrap_or(0);
+        let text = match app.process_details(pid) {
+            Some(rows) => {
+                let width = rows.iter().map(|(l, _)| l.len()).max().unwrap_or(0);
+                rows.into_iter()
+                    .map(|(label, value)| {
+                        Line::from(vec![
+                            Span::styled(
+                                format!("  {:<width$}  ", label, width = width),
+                                Style::default().fg(theme.highlight),
+                            ),
+                            Span::styled(value, Style::default().fg(theme.normal)),
+                        ])
+                    })
+                    .collect()
+            }
+            None => vec![Line::from(Span::styled(
+                format!("  process {} has exited", pid),
+                Style::default().fg(theme.muted),
+            ))],
+        };
+        let details = Paragraph::new(text).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(glyphs::get().border)
+                .title(format!("Details: {}", pid))
+                .border_style(Style::default().fg(theme.highlight)),
+        );
+        f.render_widget(details, chunks[1]);
+    } else if app.mode == Mode::Diff {
+        let (title, text) = match app.diff() {
+            Some((label, diff)) => (format!("Diff: {} -> now", label), diff_lines(&diff, &theme)),
+            None => ("Diff".to_string(), Vec::new()),
+        };
+        let diff = Paragraph::new(text).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(glyphs::get().border)
+                .title(title)
+                .border_style(Style::default().fg(theme.highlight)),
+        );
+        f.render_widget(diff, chunks[1]);
+    } else {
+        let columns = app.columns().to_vec();
+        let thresholds = &app.config.colors;
+        let items: Vec<ListItem> = app
+            .processes
+            .iter()
+            .map(|p| {
+                let cpu_bar = format!("{:>5.1}%", p.cpu_usage);
+                let mem_mb = p.memory / 1024 / 1024;
+                let mem_str = format!("{:>6} MB", mem_mb);
+
+                let style = Style::default().fg(if p.is_suspended() {
+                    theme.suspended
+                } else if p.is_protected {
+                    theme.protected
+                } else if p.is_game {
+                    theme.game
+                } else if p.cpu_usage > thresholds.cpu_high {
+                    theme.cpu_high
+                } else if p.cpu_usage > thresholds.cpu_medium {
+                    theme.cpu_medium
+                } else {
+                    theme.normal
+                });
+
+                let spans: Vec<Span> = columns
+                    .iter()
+                    .map(|col| match col {
+                        Column::Pid => {
+                            Span::styled(format!("{:<8} ", p.pid), Style::default().fg(theme.muted))
+                        }
+                        Column::Name => Span::styled(format!("{} ", fit(&p.name, 30)), style),
+                        Column::User => Span::styled(
+                            format!("{} ", fit(&p.user, 12)),
+                            Style::default().fg(theme.muted),
+                        ),
+                        Column::Status => Span::styled(
+                            format!("{} ", fit(&p.status, 10)),
+                            Style::default().fg(theme.muted),
+                        ),
+                        Column::Cpu => {
+                            Span::styled(format!("{}  ", cpu_bar), Style::default().fg(theme.info))
+                        }
+                        Column::Mem => Span::styled(
+                            format!("{}  ", mem_str),
+                            Style::default().fg(theme.secondary),
+                        ),
+                        Column::Leak => Span::styled(
+                            format!("{:>9} ", output::leak_cell(p)),
+                            Style::default().fg(theme.cpu_high),
+                        ),
+                        Column::Hung => Span::styled(
+                            format!("{:<15} ", output::hung_cell(p)),
+                            Style::default().fg(theme.cpu_high),
+                        ),
+                        Column::Wchan => Span::styled(
+                            format!("{} ", fit(&output::wchan_cell(p), 24)),
+                            Style::default().fg(theme.muted),
+                        ),
+                    })
+                    .collect();
+                let content = Line::from(spans);
+
+                ListItem::new(content)
+            })
+            .collect();
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_set(glyphs::get().border)
+            .title("Processes")
+            .border_style(Style::default().fg(theme.info));
+        // The event pane takes the bottom third when it's open.
+        let (list_area, events_area) = if app.show_events {
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Min(5),
+                    Constraint::Length((chunks[1].height / 3).max(5)),
+                ])
+                .split(chunks[1]);
+            (split[0], Some(split[1]))
+        } else {
+            (chunks[1], None)
+        };
+        let inner = block.inner(list_area);
+        f.render_widget(block, list_area);
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+        app.header_area = parts[0];
+        app.list_area = parts[1];
+
+        // Column headers; click one to sort by it.
+        let mut labels = vec![Span::raw(" ".repeat(HIGHLIGHT_WIDTH as usize))];
+        for col in &columns {
+            let width = col.width() as usize - 1;
+            let label = if col.right_aligned() {
+                format!("{:>width$} ", col.label(), width = width)
+            } else {
+                format!("{} ", fit(col.label(), width))
+            };
+            let mut style = Style::default()
+                .fg(theme.muted)
+                .add_modifier(Modifier::BOLD);
+            if col.sort_mode() == app.sort_mode {
+                style = style.fg(theme.highlight).add_modifier(Modifier::UNDERLINED);
+            }
+            labels.push(Span::styled(label, style));
+        }
+        f.render_widget(Paragraph::new(Line::from(labels)), parts[0]);
+
+        let list = List::new(items)
+            .highlight_style(
+                Style::default()
+                    .bg(theme.selected_bg)
+                    .fg(theme.selected_fg)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        f.render_stateful_widget(list, parts[1], &mut app.list_state);
+
+        if let Some(area) = events_area {
+            render_events(f, app, area, &theme);
+        }
+    }
+
+    // Footer
+    let footer_text = match app.mode {
+        Mode::Normal => match &app.status_message {
+            _ if app.keymap.is_pending() => Line::from(format!(
+                "keys: {}{}",
+                app.keymap.pending_keys(),
+                glyphs::get().ellipsis
+            )),
+            Some(msg) => Line::from(msg.as_str()),
+            None => Line::from(format!(
+                "press {} for help | {} to quit",
+                app.keymap.keys_for(Action::Help),
+                app.keymap.keys_for(Action::Quit)
+            )),
+        },
+        Mode::Search => {
+            let mut spans = vec![Span::raw(format!("search: {}_", app.search_query))];
+            if let Some(err) = &app.filter_error {
+                spans.push(Span::styled(
+                    format!("  {} {}", glyphs::get().cross, err),
+                    Style::default().fg(theme.error),
+                ));
+            }
+            Line::from(spans)
+        }
+        Mode::Help => Line::from("viewing help"),
+        Mode::Views => Line::from("enter/1-9 switch | n save current as view | esc close"),
+        Mode::SaveView => Line::from(format!("view name: {}_", app.view_name)),
+        Mode::Details | Mode::Diff => Line::from("esc/enter/click to close"),
+        Mode::Menu => Line::from("enter/click pick | esc close"),
+        Mode::Pid => Line::from(format!("jump to pid: {}_", app.pid_input)),
+        Mode::Command => {
+            let mut spans = vec![Span::raw(format!(":{}_", app.command_line.input))];
+            if let Some((candidates, index)) = app.command_line.candidates() {
+                spans.push(Span::raw("  "));
+                for (i, c) in candidates.iter().enumerate().take(12) {
+                    let style = if i == index {
+                        Style::default().fg(theme.highlight)
+                    } else {
+                        Style::default().fg(theme.muted)
+                    };
+                    spans.push(Span::styled(format!("{} ", c), style));
+                }
+                if candidates.len() > 12 {
+                    spans.push(Span::raw(glyphs::get().ellipsis));
+                }
+            }
+            Line::from(spans)
+        }
+    };
+
+    let footer = Paragraph::new(footer_text)
+        .style(Style::default().fg(theme.muted))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_set(glyphs::get().border)
+                .border_style(Style::default().fg(theme.muted)),
+        );
+    f.render_widget(footer, chunks[2]);
+
+    // Last, so it stays on top of the footer when opened near the bottom
+    if let Some(menu) = &mut app.menu {
+        let area = menu.popup_area(f.area());
+        menu.area = area;
+        let items: Vec<ListItem> = MenuItem::ALL
+            .iter()
+            .map(|i| ListItem::new(format!(" {}", i.label())))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(glyphs::get().border)
+                    .title(fit(&menu.name, area.width.saturating_sub(2) as usize))
+                    .border_style(Style::default().fg(theme.accent)),
+            )
+            .style(Style::default().fg(theme.normal))
+            .highlight_style(
+                Style::default()
+                    .bg(theme.selected_bg)
+                    .fg(theme.selected_fg)
+                    .add_modifier(Modifier::BOLD),
+            );
+        f.render_widget(Clear, area);
+        f.render_stateful_widget(list, area, &mut menu.state);
+    }
+}
+
+/// The newest start/exit events that fit, oldest at the top.
+fn render_events<S: ProcessSource + Clone + 'static>(
+    f: &mut Frame,
+    app: &App<S>,
+    area: Rect,
+    theme: &Theme,
+) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let events = app.events().events();
+    let lines: Vec<Line> = events
+        .iter()
+        .skip(events.len().saturating_sub(rows))
+        .map(|e| {
+            let (kind, color) = match e.kind {
+                EventKind::Start => ("start", theme.info),
+                EventKind::Exit => ("exit", theme.cpu_medium),
+            };
+            let mut spans = vec![
+                Span::styled(
+                    format!(" {} ", replay::clock(e.time_ms)),
+                    Style::default().fg(theme.muted),
+                ),
+                Span::styled(format!("{:<6}", kind), Style::default().fg(color)),
+                Span::styled(format!("{:<8} ", e.pid), Style::default().fg(theme.muted)),
+                Span::styled(fit(&e.name, 30), Style::default().fg(theme.normal)),
+            ];
+            if let Some(parent) = e.parent {
+                spans.push(Span::styled(
+                    format!(" parent {:<8}", parent),
+                    Style::default().fg(theme.muted),
+                ));
+            }
+            if let Some(runtime) = e.runtime_ms {
+                spans.push(Span::styled(
+                    format!(" ran {}", events::format_runtime(runtime)),
+                    Style::default().fg(theme.info),
+                ));
+            }
+            if let Some(peak) = e.peak_memory {
+                spans.push(Span::styled(
+                    format!(" peak {} MB", peak / 1024 / 1024),
+                    Style::default().fg(theme.secondary),
+                ));
+            }
+            match (e.exit_code, e.signal) {
+                (Some(0), _) => {}
+                (Some(code), _) => spans.push(Span::styled(
+                    format!(" code {}", code),
+                    Style::default().fg(theme.error),
+                )),
+                (_, Some(signal)) => spans.push(Span::styled(
+                    format!(" signal {}", signal),
+                    Style::default().fg(theme.error),
+                )),
+                _ => {}
+            }
+            Line::from(spans)
+        })
+        .collect();
+    let pane = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_set(glyphs::get().border)
+            .title(if app.events().is_exact() {
+                format!("Events ({}, exact)", events.len())
+            } else {
+                format!("Events ({})", events.len())
+            })
+            .border_style(Style::default().fg(theme.secondary)),
+    );
+    f.render_widget(pane, area);
+}
+
+/// Rows per section of the diff view.
+const DIFF_ROWS: usize = 10;
+
+/// Started, exited, then the biggest CPU and memory changes.
+fn diff_lines(diff: &Diff, theme: &Theme) -> Vec<Line<'static>> {
+    let heading = |text: String| {
+        Line::from(Span::styled(
+            text,
+            Style::default()
+                .fg(theme.highlight)
+                .add_modifier(Modifier::BOLD),
+        ))
+    };
+    let row = |text: String| {
+        Line::from(Span::styled(
+            format!("  {}", text),
+            Style::default().fg(theme.normal),
+        ))
+    };
+    let mut lines = Vec::new();
+    for (title, processes) in [("Started", &diff.started), ("Exited", &diff.exited)] {
+        lines.push(heading(format!("{} ({})", title, processes.len())));
+        for p in processes.iter().take(DIFF_ROWS) {
+            lines.push(row(format!(
+                "{:<8} {} {:>5.1}% {:>6} MB",
+                p.pid,
+                fit(&p.name, 30),
+                p.cpu_usage,
+                p.memory / 1024 / 1024
+            )));
+        }
+        if processes.len() > DIFF_ROWS {
+            lines.push(Line::from(Span::styled(
+                format!("  ... and {} more", processes.len() - DIFF_ROWS),
+                Style::default().fg(theme.muted),
+            )));
+        }
+        lines.push(Line::default());
+    }
+    lines.push(heading("CPU".to_string()));
+    lines.extend(
+        diff.top_cpu(DIFF_ROWS)
+            .into_iter()
+            .map(|d| row(diff::cpu_row(d))),
+    );
+    lines.push(Line::default());
+    lines.push(heading("Memory".to_string()));
+    lines.extend(
+        diff.top_memory(DIFF_ROWS)
+            .into_iter()
+            .map(|d| row(diff::memory_row(d))),
+    );
+    lines
+}
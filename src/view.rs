@@ -0,0 +1,248 @@
+//! Named views: a filter query, a sort mode and a column layout bundled
+//! together so they can be switched with a single key.
+
+use crate::SortMode;
+use crate::filter::{self, Expr};
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Column {
+    Pid,
+    Name,
+    User,
+    Status,
+    Cpu,
+    #[serde(alias = "memory")]
+    Mem,
+    /// MB per minute for processes the leak monitor flagged, blank for the
+    /// rest.
+    Leak,
+    /// What a hung process is stuck as and for how long.
+    Hung,
+    /// The kernel function a hung process is waiting in (Linux only).
+    Wchan,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 5] = [
+        Column::Pid,
+        Column::Name,
+        Column::User,
+        Column::Cpu,
+        Column::Mem,
+    ];
+
+    /// Header text shown above the column.
+    pub fn label(self) -> &'static str {
+        match self {
+            Column::Pid => "PID",
+            Column::Name => "NAME",
+            Column::User => "USER",
+            Column::Status => "STATUS",
+            Column::Cpu => "CPU%",
+            Column::Mem => "MEM",
+            Column::Leak => "LEAK",
+            Column::Hung => "HUNG",
+            Column::Wchan => "WCHAN",
+        }
+    }
+
+    /// Display width including the gap after the column.
+    pub fn width(self) -> u16 {
+        match self {
+            Column::Pid => 9,
+            Column::Name => 31,
+            Column::User => 13,
+            Column::Status => 11,
+            Column::Cpu => 8,
+            Column::Mem => 11,
+            Column::Leak => 10,
+            Column::Hung => 16,
+            Column::Wchan => 25,
+        }
+    }
+
+    /// Numeric columns are right-aligned.
+    pub fn right_aligned(self) -> bool {
+        matches!(self, Column::Cpu | Column::Mem | Column::Leak)
+    }
+
+    pub fn sort_mode(self) -> SortMode {
+        match self {
+            Column::Pid => SortMode::Pid,
+            Column::Name => SortMode::Name,
+            Column::User => SortMode::User,
+            Column::Status => SortMode::Status,
+            Column::Cpu => SortMode::Cpu,
+            Column::Mem => SortMode::Memory,
+            Column::Leak => SortMode::Leak,
+            Column::Hung | Column::Wchan => SortMode::Hung,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Column::Pid => "pid",
+            Column::Name => "name",
+            Column::User => "user",
+            Column::Status => "status",
+            Column::Cpu => "cpu",
+            Column::Mem => "mem",
+            Column::Leak => "leak",
+            Column::Hung => "hung",
+            Column::Wchan => "wchan",
+        }
+    }
+}
+
+/// View as written in the config file. `filter` uses the query language from
+/// `filter.rs`; `sort` and `columns` fall back to the defaults when omitted.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewConfig {
+    pub name: String,
+    #[serde(default)]
+    pub filter: String,
+    pub sort: Option<SortMode>,
+    pub columns: Option<Vec<Column>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct View {
+    pub name: String,
+    pub query: String,
+    pub filter: Option<Expr>,
+    pub sort: SortMode,
+    pub columns: Vec<Column>,
+}
+
+impl View {
+    pub fn new(name: &str, query: &str, sort: SortMode, columns: &[Column]) -> Result<Self> {
+        let filter = filter::parse(query)
+            .map_err(|e| anyhow!("view '{}': bad filter \"{}\": {}", name, query, e))?;
+        if columns.is_empty() {
+            return Err(anyhow!("view '{}': needs at least one column", name));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            query: query.to_string(),
+            filter,
+            sort,
+            columns: columns.to_vec(),
+        })
+    }
+
+    pub fn from_config(cfg: &ViewConfig) -> Result<Self> {
+        if cfg.name.trim().is_empty() {
+            return Err(anyhow!("view with filter \"{}\" has no name", cfg.filter));
+        }
+        Self::new(
+            &cfg.name,
+            &cfg.filter,
+            cfg.sort.unwrap_or(SortMode::Cpu),
+            cfg.columns.as_deref().unwrap_or(&Column::DEFAULT),
+        )
+    }
+
+    /// TOML snippet that recreates this view, used when saving from the picker.
+    pub fn to_toml(&self) -> String {
+        let columns: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| format!("\"{}\"", c.as_str()))
+            .collect();
+        format!(
+            "\n[[views]]\nname = {}\nfilter = {}\nsort = \"{}\"\ncolumns = [{}]\n",
+            toml_string(&self.name),
+            toml_string(&self.query),
+            self.sort.as_str(),
+            columns.join(", ")
+        )
+    }
+}
+
+fn toml_string(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+/// Views that ship with procsnipe. "all" is always first so `1` resets.
+pub fn builtin_views() -> Vec<View> {
+    let mut views = vec![
+        View::new("all", "", SortMode::Cpu, &Column::DEFAULT),
+        View::new("games", "game", SortMode::Cpu, &Column::DEFAULT),
+        View::new("hogs", "cpu>20 || mem>1GB", SortMode::Cpu, &Column::DEFAULT),
+    ];
+
+    let me = std::env::var("USER").or_else(|_| std::env::var("USERNAME"));
+    if let Ok(me) = me {
+        views.push(View::new(
+            "my user",
+            &format!("user={}", toml_string(&me)),
+            SortMode::Cpu,
+            &[Column::Pid, Column::Name, Column::Cpu, Column::Mem],
+        ));
+    }
+
+    views.push(View::new(
+        "zombies",
+        "status=zombie",
+        SortMode::Name,
+        &[Column::Pid, Column::Name, Column::User, Column::Status],
+    ));
+
+    views.push(View::new(
+        "leaks",
+        "leak",
+        SortMode::Leak,
+        &[
+            Column::Pid,
+            Column::Name,
+            Column::User,
+            Column::Mem,
+            Column::Leak,
+        ],
+    ));
+
+    views.push(View::new(
+        "hung",
+        "hung",
+        SortMode::Hung,
+        &[
+            Column::Pid,
+            Column::Name,
+            Column::User,
+            Column::Status,
+            Column::Hung,
+            Column::Wchan,
+        ],
+    ));
+
+    views
+        .into_iter()
+        .map(|v| v.expect("built-in views are valid"))
+        .collect()
+}
+
+/// Built-ins followed by the user's views. A user view with the same name as
+/// a built-in replaces it in place.
+pub fn load_views(configured: &[ViewConfig]) -> Result<Vec<View>> {
+    let mut views = builtin_views();
+    for cfg in configured {
+        let view = View::from_config(cfg)?;
+        match views.iter_mut().find(|v| v.name == view.name) {
+            Some(existing) => *existing = view,
+            None => views.push(view),
+        }
+    }
+    Ok(views)
+}
+
+/// Index of a view given by name (case-insensitive) or 1-based number.
+pub fn find(views: &[View], name: &str) -> Option<usize> {
+    match name.parse::<usize>() {
+        Ok(n) => n.checked_sub(1).filter(|&i| i < views.len()),
+        Err(_) => views.iter().position(|v| v.name.eq_ignore_ascii_case(name)),
+    }
+}
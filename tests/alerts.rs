@@ -0,0 +1,353 @@
+//! Alert rules: parsing them from the config, when they fire, what they do,
+//! and the TUI running them.
+
+mod common;
+
+use common::{fixture, process, scratch};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::alerts::{Action, AlertConfig, Alerts, Rule, load_rules};
+use procsnipe::app::App;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::Keymap;
+use procsnipe::{FakeSource, ProcessInfo, Signal, glyphs, view};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MB: u64 = 1024 * 1024;
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn rules(toml: &str) -> anyhow::Result<Vec<Rule>> {
+    let config: Config = toml::from_str(toml)?;
+    config.validate()?;
+    load_rules(&config.alerts)
+}
+
+fn rule(toml: &str) -> Rule {
+    rules(toml).unwrap().remove(0)
+}
+
+#[test]
+fn rules_from_the_config() {
+    let parsed = rules(
+        r#"
+        [[alerts]]
+        name = "runaway"
+        process = "chrome*"
+        cpu = 80
+        memory = "2GB"
+        io = "50MB"
+        threads = 500
+        older_than = "1h"
+        for = "30s"
+        cooldown = "5m"
+        actions = ["notify", "log /tmp/alerts.log", "renice 10", "suspend", "kill", "run echo hi"]
+
+        [[alerts]]
+        name = "fresh"
+        younger_than = "10s"
+        "#,
+    )
+    .unwrap();
+    let runaway = &parsed[0];
+    assert_eq!(runaway.condition.cpu, Some(80.0));
+    assert_eq!(runaway.condition.memory, Some(2048 * MB));
+    assert_eq!(runaway.condition.io, Some(50 * MB));
+    assert_eq!(runaway.condition.threads, Some(500));
+    assert_eq!(runaway.sustained, Duration::from_secs(30));
+    assert_eq!(runaway.cooldown, Duration::from_secs(300));
+    assert_eq!(
+        runaway.actions,
+        [
+            Action::Notify,
+            Action::Log(Some("/tmp/alerts.log".into())),
+            Action::Renice(10),
+            Action::Suspend,
+            Action::Kill,
+            Action::Run("echo hi".to_string()),
+        ]
+    );
+    let fresh = &parsed[1];
+    assert_eq!(fresh.actions, [Action::Notify]);
+    assert_eq!(fresh.sustained, Duration::ZERO);
+    assert_eq!(fresh.cooldown, Duration::from_secs(60));
+
+    for (toml, error) in [
+        ("[[alerts]]\nname = \"x\"", "needs a condition"),
+        ("[[alerts]]\ncpu = 5", "without a name"),
+        (
+            "[[alerts]]\nname = \"x\"\ncpu = 5\nactions = [\"panic\"]",
+            "unknown action",
+        ),
+        (
+            "[[alerts]]\nname = \"x\"\ncpu = 5\nactions = [\"renice 40\"]",
+            "-20 to 19",
+        ),
+        (
+            "[[alerts]]\nname = \"x\"\ncpu = 5\nactions = [\"run\"]",
+            "needs a command",
+        ),
+        ("[[alerts]]\nname = \"x\"\nmemory = \"lots\"", "not a size"),
+        (
+            "[[alerts]]\nname = \"x\"\ncpu = 5\nfor = \"a while\"",
+            "not a duration",
+        ),
+        (
+            "[[alerts]]\nname = \"x\"\ncpu = 5\nwhen = \"now\"",
+            "unknown field",
+        ),
+    ] {
+        let err = rules(toml).unwrap_err();
+        assert!(format!("{:#}", err).contains(error), "{}: {:#}", toml, err);
+    }
+}
+
+/// Check `processes` at `secs` and say which PIDs the rules fired for.
+fn fired(alerts: &mut Alerts, processes: &[ProcessInfo], secs: u64) -> Vec<u32> {
+    let mut source = FakeSource::new(processes.to_vec());
+    alerts
+        .check(processes, at(secs), &mut source)
+        .iter()
+        .map(|a| a.pid)
+        .collect()
+}
+
+#[test]
+fn sustained_and_cooldown() {
+    let mut alerts = Alerts::new(vec![rule(
+        "[[alerts]]\nname = \"hot\"\ncpu = 50\nfor = \"10s\"\ncooldown = \"1m\"",
+    )]);
+    let hot = fixture();
+    let mut cooled = fixture();
+    cooled.iter_mut().for_each(|p| p.cpu_usage = 1.0);
+
+    assert!(fired(&mut alerts, &hot, 1000).is_empty());
+    assert!(fired(&mut alerts, &hot, 1005).is_empty());
+    // A dip starts the clock over.
+    assert!(fired(&mut alerts, &cooled, 1008).is_empty());
+    assert!(fired(&mut alerts, &hot, 1010).is_empty());
+    assert!(fired(&mut alerts, &hot, 1015).is_empty());
+    assert_eq!(fired(&mut alerts, &hot, 1020), [2077]);
+    // Still hot, but the rule is cooling down.
+    assert!(fired(&mut alerts, &hot, 1030).is_empty());
+    assert!(fired(&mut alerts, &hot, 1079).is_empty());
+    assert_eq!(fired(&mut alerts, &hot, 1080), [2077]);
+}
+
+#[test]
+fn every_kind_of_condition() {
+    let now = 100_000;
+    let mut busy = process(10, "Chrome.exe", "alice", "Running", 1.0, 3000);
+    busy.threads = 600;
+    busy.start_time = now - 7200;
+    busy.io_bytes = 0;
+    let mut young = process(11, "chrome", "alice", "Running", 1.0, 10);
+    young.start_time = now - 5;
+    let unknown_age = process(12, "chrome", "alice", "Running", 1.0, 10);
+
+    // What fires on the second look; I/O rates need two.
+    let check = |toml: &str| {
+        let mut alerts = Alerts::new(vec![Rule {
+            cooldown: Duration::ZERO,
+            ..rule(toml)
+        }]);
+        let mut processes = vec![busy.clone(), young.clone(), unknown_age.clone()];
+        fired(&mut alerts, &processes, now);
+        // Ten seconds later, with 200 MB more disk traffic from the busy one.
+        processes[0].io_bytes = 200 * MB;
+        fired(&mut alerts, &processes, now + 10)
+    };
+    assert_eq!(
+        check("[[alerts]]\nname = \"a\"\nprocess = \"chrome\""),
+        [10, 11, 12]
+    );
+    assert_eq!(check("[[alerts]]\nname = \"a\"\nmemory = \"2GB\""), [10]);
+    assert_eq!(check("[[alerts]]\nname = \"a\"\nthreads = 500"), [10]);
+    assert_eq!(check("[[alerts]]\nname = \"a\"\nolder_than = \"1h\""), [10]);
+    assert_eq!(
+        check("[[alerts]]\nname = \"a\"\nyounger_than = \"1m\""),
+        [11]
+    );
+    assert_eq!(check("[[alerts]]\nname = \"a\"\nio = \"10MB\""), [10]);
+    assert!(check("[[alerts]]\nname = \"a\"\nio = \"30MB\"").is_empty());
+    assert!(check("[[alerts]]\nname = \"a\"\nprocess = \"chrome\"\ncpu = 50").is_empty());
+
+    let mut alerts = Alerts::new(vec![rule(
+        "[[alerts]]\nname = \"disk\"\nio = \"10MB\"\nthreads = 100",
+    )]);
+    let mut source = FakeSource::default();
+    alerts.check(&[busy.clone()], at(now), &mut source);
+    busy.io_bytes = 200 * MB;
+    let alert = alerts.check(&[busy], at(now + 10), &mut source).remove(0);
+    assert_eq!(
+        alert.to_string(),
+        "disk: Chrome.exe (10) io 20 MB/s, 600 threads"
+    );
+}
+
+#[test]
+fn actions_go_through_the_source() {
+    let processes = vec![
+        process(1, "systemd", "root", "Sleeping", 99.0, 10),
+        process(20, "miner", "bob", "Running", 99.0, 10),
+        process(21, "renderer", "bob", "Running", 99.0, 10),
+    ];
+    let mut marked = processes.clone();
+    Config::default().mark(&mut marked);
+    let source = FakeSource::new(processes);
+    let mut alerts = Alerts::new(
+        rules(
+            r#"
+            [[alerts]]
+            name = "miners"
+            process = "miner"
+            actions = ["suspend", "notify"]
+
+            [[alerts]]
+            name = "busy"
+            cpu = 90
+            actions = ["renice 10", "kill"]
+            "#,
+        )
+        .unwrap(),
+    );
+    let alerts = alerts.check(&marked, at(1000), &mut source.clone());
+    let text: Vec<String> = alerts.iter().map(|a| a.to_string()).collect();
+    assert_eq!(
+        text,
+        [
+            "miners: miner (20), suspended",
+            "busy: systemd (1) cpu 99.0%, protected, left alone",
+            "busy: miner (20) cpu 99.0%, reniced to 10, killed",
+            "busy: renderer (21) cpu 99.0%, reniced to 10, killed",
+        ]
+    );
+    assert!(alerts[0].notify);
+    assert!(!alerts[1].notify);
+    assert_eq!(
+        source.signals(),
+        [(20, Signal::Stop), (20, Signal::Kill), (21, Signal::Kill)]
+    );
+    assert_eq!(source.nice(21), Some(10));
+    assert_eq!(source.nice(1), None);
+}
+
+#[test]
+fn log_and_run() {
+    let dir = scratch("alerts-log");
+    // The directory doesn't have to exist yet.
+    let log = dir.join("logs").join("alerts.log");
+    let out = dir.join("ran.txt");
+    let mut alerts = Alerts::new(vec![Rule {
+        actions: vec![
+            Action::Log(Some(log.clone())),
+            Action::Run(format!(
+                "echo \"$PROCSNIPE_RULE $PROCSNIPE_PID $PROCSNIPE_NAME\" > '{}'",
+                out.display()
+            )),
+        ],
+        ..rule("[[alerts]]\nname = \"hot\"\ncpu = 50")
+    }]);
+    let fired = alerts.check(&fixture(), at(1000), &mut FakeSource::default());
+    assert_eq!(fired.len(), 1);
+    assert!(!fired[0].notify);
+
+    let logged = fs::read_to_string(&log).unwrap();
+    assert_eq!(logged.lines().count(), 1);
+    assert!(
+        logged.contains("hot: cs2 (2077) cpu 72.0%, ran echo"),
+        "{}",
+        logged
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while fs::read_to_string(&out).is_err() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(fs::read_to_string(&out).unwrap(), "hot 2077 cs2\n");
+}
+
+#[test]
+fn the_tui_runs_the_rules() {
+    glyphs::set_ascii(true);
+    let config = Config {
+        refresh_ms: 60_000,
+        alerts: vec![AlertConfig {
+            name: "hog".to_string(),
+            cpu: Some(50.0),
+            actions: vec!["notify".to_string(), "kill".to_string()],
+            ..AlertConfig::default()
+        }],
+        ..Config::default()
+    };
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let source = FakeSource::new(fixture());
+    let mut app = App::new(source.clone(), config, views, keymap, theme, watcher);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while app.processes().is_empty() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for a snapshot"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+    assert_eq!(
+        app.status_message().unwrap(),
+        format!(
+            "{} hog: cs2 (2077) cpu 72.0%, killed",
+            glyphs::get().warning
+        )
+    );
+    assert_eq!(source.signals(), [(2077, Signal::Kill)]);
+}
+
+#[test]
+fn rules_keep_running_while_paused() {
+    let config = Config {
+        refresh_ms: 100,
+        alerts: vec![AlertConfig {
+            name: "hog".to_string(),
+            cpu: Some(90.0),
+            actions: vec!["kill".to_string()],
+            ..AlertConfig::default()
+        }],
+        ..Config::default()
+    };
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let source = FakeSource::new(fixture());
+    let mut app = App::new(source.clone(), config, views, keymap, theme, watcher);
+    app.set_debounce(Duration::ZERO);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while app.processes().is_empty() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for a snapshot"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+
+    let space = Event::Key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+    assert!(!app.on_event(space).unwrap());
+    let mut processes = fixture();
+    processes[5].cpu_usage = 99.0;
+    source.set_processes(processes);
+    while source.signals().is_empty() {
+        assert!(Instant::now() < deadline, "the rule never ran while paused");
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+    assert_eq!(source.signals(), [(3001, Signal::Kill)]);
+}
@@ -0,0 +1,74 @@
+//! `--batch` output against a fake process list.
+
+mod common;
+
+use common::fixture;
+use procsnipe::batch::{self, Batch};
+use procsnipe::config::Config;
+use procsnipe::output::Format;
+use procsnipe::{FakeSource, glyphs, view};
+use serde_json::Value;
+use std::time::Duration;
+
+fn run(format: Format, iterations: u64, limit: Option<usize>) -> anyhow::Result<String> {
+    glyphs::set_ascii(true);
+    let config = Config::default();
+    let views = view::load_views(&config.views).unwrap();
+    let mut batch = Batch {
+        format,
+        iterations: Some(iterations),
+        limit,
+        recorder: None,
+    };
+    let mut out = Vec::new();
+    batch::run(
+        FakeSource::new(fixture()),
+        &config,
+        &views[0],
+        Duration::from_millis(10),
+        &mut batch,
+        &mut out,
+    )?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn table_prints_a_header_and_table_per_iteration() {
+    let out = run(Format::Table, 3, Some(2)).unwrap();
+    let blocks: Vec<&str> = out.split_terminator("\n\n").collect();
+    assert_eq!(blocks.len(), 3, "{}", out);
+    for (i, block) in blocks.iter().enumerate() {
+        let lines: Vec<&str> = block.lines().collect();
+        assert!(
+            lines[0].starts_with(&format!("--- #{} +", i + 1)),
+            "{}",
+            block
+        );
+        assert!(lines[0].contains("| 8 processes |"), "{}", block);
+        assert!(lines[1].starts_with("PID"));
+        // Sorted by CPU, cut to the limit.
+        assert!(lines[2].contains("cs2") && lines[3].contains("firefox"));
+        assert_eq!(lines.len(), 4);
+    }
+}
+
+#[test]
+fn ndjson_prints_one_snapshot_per_line() {
+    let out = run(Format::Ndjson, 2, None).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2);
+    for line in lines {
+        let frame: Value = serde_json::from_str(line).unwrap();
+        assert!(frame["time_ms"].as_u64().unwrap() > 0);
+        let processes = frame["processes"].as_array().unwrap();
+        assert_eq!(processes.len(), fixture().len());
+        assert_eq!(processes[0]["name"], "cs2");
+        assert_eq!(processes[0]["game"], true);
+    }
+}
+
+#[test]
+fn other_formats_are_rejected() {
+    let err = run(Format::Csv, 1, None).unwrap_err();
+    assert_eq!(err.to_string(), "batch mode prints table or ndjson");
+}
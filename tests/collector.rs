@@ -0,0 +1,165 @@
+//! The background collector: the interval, pausing, refreshing on demand,
+//! skipping to the newest snapshot, and the stale marker when scans fail.
+
+mod common;
+
+use anyhow::{Result, bail};
+use common::{fixture, process};
+use procsnipe::app::App;
+use procsnipe::collector::Collector;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::Keymap;
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, ProcessInfo, ProcessSource, Signal, glyphs, view};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// Wait out anything already on its way, then drop it.
+fn drain(collector: &Collector) {
+    std::thread::sleep(Duration::from_millis(50));
+    collector.latest();
+}
+
+#[test]
+fn collects_on_the_interval() {
+    let source = FakeSource::new(fixture());
+    let collector = Collector::spawn(source.clone(), Duration::from_millis(20));
+    let first = collector.next().unwrap();
+    assert_eq!(first.processes.len(), fixture().len());
+    assert_eq!(
+        first.totals.cpu,
+        fixture().iter().map(|p| p.cpu_usage).sum::<f32>()
+    );
+
+    // Changes show up in a later snapshot without being asked for.
+    source.set_processes(vec![process(9, "late", "root", "Running", 1.0, 1)]);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut last = first.collected_at;
+    loop {
+        let snapshot = collector.next().unwrap();
+        assert!(snapshot.collected_at - last >= Duration::from_millis(15));
+        last = snapshot.collected_at;
+        if snapshot.processes[0].name == "late" {
+            break;
+        }
+        assert!(Instant::now() < deadline, "never saw the new list");
+    }
+}
+
+#[test]
+fn latest_skips_to_the_newest() {
+    let source = FakeSource::new(fixture());
+    let collector = Collector::spawn(source.clone(), Duration::from_millis(10));
+    std::thread::sleep(Duration::from_millis(50));
+    source.set_processes(vec![process(9, "newest", "root", "Running", 1.0, 1)]);
+    std::thread::sleep(Duration::from_millis(50));
+    let latest = collector.latest().unwrap();
+    assert_eq!(latest.processes[0].name, "newest");
+
+    // Slowed right down, nothing else comes.
+    collector.set_interval(HOUR);
+    drain(&collector);
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(collector.latest().is_none());
+
+    // Until it's asked for.
+    collector.refresh_now();
+    let started = Instant::now();
+    collector.next().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn paused_until_resumed() {
+    let collector = Collector::spawn(FakeSource::new(fixture()), Duration::from_millis(10));
+    collector.next().unwrap();
+    collector.set_paused(true);
+    drain(&collector);
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(collector.latest().is_none(), "scanned while paused");
+
+    // A new interval while paused doesn't wake it up.
+    collector.set_interval(Duration::from_millis(5));
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(collector.latest().is_none());
+
+    collector.set_paused(false);
+    collector.next().unwrap();
+    collector.next().unwrap();
+}
+
+/// Scans fail while `failing` is set.
+#[derive(Clone)]
+struct Flaky {
+    inner: FakeSource,
+    failing: Arc<AtomicBool>,
+}
+
+impl ProcessSource for Flaky {
+    fn snapshot(&mut self) -> Result<Vec<ProcessInfo>> {
+        if self.failing.load(Ordering::SeqCst) {
+            bail!("no /proc today");
+        }
+        self.inner.snapshot()
+    }
+
+    fn signal(&mut self, pid: u32, signal: Signal) -> Result<()> {
+        self.inner.signal(pid, signal)
+    }
+
+    fn renice(&mut self, pid: u32, nice: i32) -> Result<()> {
+        self.inner.renice(pid, nice)
+    }
+}
+
+#[test]
+fn failed_scans_mark_the_list_stale() {
+    glyphs::set_ascii(true);
+    let config = Config {
+        refresh_ms: 100,
+        ..Config::default()
+    };
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let source = Flaky {
+        inner: FakeSource::new(fixture()),
+        failing: Arc::new(AtomicBool::new(false)),
+    };
+    let mut app = App::new(source.clone(), config, views, keymap, theme, watcher);
+    let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+    let mut screen = || {
+        app.receive_snapshot();
+        terminal.draw(|f| ui(f, &mut app)).unwrap();
+        terminal.backend().to_string()
+    };
+    let wait_for = |screen: &mut dyn FnMut() -> String, what: &str, shown: bool| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let text = screen();
+            if text.contains(what) == shown {
+                return text;
+            }
+            assert!(Instant::now() < deadline, "{}", text);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    };
+
+    let fresh = wait_for(&mut screen, "firefox", true);
+    assert!(!fresh.contains("stale"), "{}", fresh);
+
+    // The last good list stays up, marked as old.
+    source.failing.store(true, Ordering::SeqCst);
+    let stale = wait_for(&mut screen, "stale", true);
+    assert!(stale.contains("firefox"), "{}", stale);
+
+    source.failing.store(false, Ordering::SeqCst);
+    wait_for(&mut screen, "stale", false);
+}
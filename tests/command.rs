@@ -0,0 +1,177 @@
+//! The `:` command line: shortened names, argument errors, tab completion
+//! and history.
+
+mod common;
+
+use common::fixture;
+use procsnipe::SortMode;
+use procsnipe::command::{self, Command, CommandLine, Setting};
+use procsnipe::view;
+use std::time::Duration;
+
+fn error(line: &str) -> String {
+    command::parse(line).unwrap_err().to_string()
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(command::parse("q").unwrap(), Command::Quit);
+    assert_eq!(command::parse(":  help").unwrap(), Command::Help);
+    assert_eq!(
+        command::parse("so MEM desc").unwrap(),
+        Command::Sort {
+            mode: SortMode::Memory,
+            descending: Some(true)
+        }
+    );
+    assert_eq!(
+        command::parse("k 12 firefox").unwrap(),
+        Command::Kill(vec!["12".to_string(), "firefox".to_string()])
+    );
+    assert_eq!(
+        command::parse("fi user=bob  cpu>5").unwrap(),
+        Command::Filter("user=bob  cpu>5".to_string())
+    );
+    assert_eq!(
+        command::parse("renice +5 3001").unwrap(),
+        Command::Renice {
+            nice: 5,
+            pid: Some(3001)
+        }
+    );
+    assert_eq!(
+        command::parse("set refresh 1.5s").unwrap(),
+        Command::Set(Setting::Refresh(Duration::from_millis(1500)))
+    );
+    assert_eq!(
+        command::parse("set ascii off").unwrap(),
+        Command::Set(Setting::Ascii(false))
+    );
+
+    assert_eq!(error(""), "empty command");
+    assert_eq!(error("s cpu"), "'s' is ambiguous: seek, set, sort");
+    assert!(error("frobnicate").starts_with("unknown command 'frobnicate' (try diff, "));
+    assert_eq!(error("pid abc"), "usage: pid PID");
+    assert_eq!(
+        error("sort gpu"),
+        "unknown column 'gpu' (sort pid|name|user|status|cpu|mem|leak|hung [asc|desc])"
+    );
+    assert!(error("sort cpu sideways").starts_with("usage: sort"));
+    assert_eq!(
+        error("renice 20"),
+        "nice value 20 is out of range (-20 to 19)"
+    );
+    assert_eq!(
+        error("export json out.json"),
+        "unknown export format 'json' (csv)"
+    );
+    assert_eq!(
+        error("set refresh 50ms"),
+        "refresh 50ms is out of range (100ms to 60s)"
+    );
+    assert_eq!(
+        error("set refresh soon"),
+        "'soon' is not a duration (like 500ms or 2s)"
+    );
+    assert!(error("set ascii maybe").starts_with("usage: set"));
+    assert_eq!(error("view"), "usage: view NAME|NUMBER");
+}
+
+/// Type `text`, then press tab `tabs` times.
+fn complete(line: &mut CommandLine, text: &str, tabs: usize) -> String {
+    let processes = fixture();
+    let views = view::load_views(&[]).unwrap();
+    line.open();
+    for c in text.chars() {
+        line.push(c);
+    }
+    for _ in 0..tabs {
+        line.complete(&processes, &views);
+    }
+    line.input.clone()
+}
+
+#[test]
+fn completion() {
+    let mut line = CommandLine::default();
+    // One candidate goes in with a space after it, ready for the next word.
+    assert_eq!(complete(&mut line, "ki", 1), "kill ");
+    assert_eq!(line.candidates(), None);
+    assert_eq!(complete(&mut line, "kill FI", 1), "kill firefox ");
+    assert_eq!(complete(&mut line, "so", 1), "sort ");
+    assert_eq!(complete(&mut line, "sort cpu ", 1), "sort cpu asc");
+    assert_eq!(
+        complete(&mut line, "set theme h", 1),
+        "set theme high-contrast "
+    );
+    assert_eq!(complete(&mut line, "set ascii o", 2), "set ascii off");
+    assert_eq!(complete(&mut line, "view ga", 1), "view games ");
+
+    // Several are cycled through, wrapping around.
+    assert_eq!(complete(&mut line, "s", 1), "seek");
+    let (candidates, index) = line.candidates().unwrap();
+    assert_eq!(
+        (candidates, index),
+        (&["seek", "set", "sort"].map(String::from)[..], 0)
+    );
+    assert_eq!(complete(&mut line, "s", 3), "sort");
+    assert_eq!(complete(&mut line, "s", 4), "seek");
+    assert_eq!(complete(&mut line, "pid 10", 2), "pid 1013");
+    assert_eq!(complete(&mut line, "renice 5 30", 1), "renice 5 3001");
+    // Typing ends the cycle and starts over from what's there.
+    line.push(' ');
+    assert_eq!(line.candidates(), None);
+    line.complete(&fixture(), &[]);
+    assert_eq!(line.input, "renice 5 3001 ");
+
+    // Nothing to offer leaves the line alone.
+    assert_eq!(complete(&mut line, "quit ", 1), "quit ");
+    assert_eq!(complete(&mut line, "kill zzz", 1), "kill zzz");
+    assert_eq!(complete(&mut line, "bogus x", 1), "bogus x");
+}
+
+#[test]
+fn history() {
+    let mut line = CommandLine::default();
+    // Nothing to go back to yet.
+    line.history_prev();
+    assert_eq!(line.input, "");
+
+    for text in ["sort cpu", "  kill 12 ", "kill 12", ""] {
+        line.open();
+        for c in text.chars() {
+            line.push(c);
+        }
+        line.submit();
+    }
+
+    // Up from a half-typed line, and back down to it.
+    line.open();
+    line.push('f');
+    line.history_prev();
+    assert_eq!(line.input, "kill 12");
+    line.history_prev();
+    assert_eq!(line.input, "sort cpu");
+    line.history_prev();
+    assert_eq!(line.input, "sort cpu");
+    line.history_next();
+    assert_eq!(line.input, "kill 12");
+    line.history_next();
+    assert_eq!(line.input, "f");
+    line.history_next();
+    assert_eq!(line.input, "f");
+
+    // Only the newest hundred are kept.
+    for pid in 0..150 {
+        line.open();
+        for c in format!("pid {}", pid).chars() {
+            line.push(c);
+        }
+        line.submit();
+    }
+    line.open();
+    for _ in 0..200 {
+        line.history_prev();
+    }
+    assert_eq!(line.input, "pid 50");
+}
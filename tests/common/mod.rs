@@ -0,0 +1,108 @@
+//! Shared fixtures and helpers for the integration tests.
+// Each test binary uses a different part of this.
+#![allow(dead_code)]
+
+use procsnipe::app::App;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::Keymap;
+use procsnipe::record::Frame;
+use procsnipe::replay::Player;
+use procsnipe::{FakeSource, ProcessInfo, Totals, glyphs, view};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+const MB: u64 = 1024 * 1024;
+
+/// When [`recording`] starts, in Unix milliseconds.
+pub const START_MS: u64 = 1_700_000_000_000;
+
+pub fn process(
+    pid: u32,
+    name: &str,
+    user: &str,
+    status: &str,
+    cpu: f32,
+    mem_mb: u64,
+) -> ProcessInfo {
+    ProcessInfo {
+        pid,
+        name: name.to_string(),
+        user: user.to_string(),
+        status: status.to_string(),
+        cpu_usage: cpu,
+        memory: mem_mb * MB,
+        is_game: false,
+        is_protected: false,
+        parent: None,
+        start_time: 0,
+        threads: 0,
+        io_bytes: 0,
+        leak: None,
+        hung: None,
+    }
+}
+
+/// Every value distinct per column, so each sort order is unambiguous.
+pub fn fixture() -> Vec<ProcessInfo> {
+    vec![
+        process(1, "systemd", "root", "Sleeping", 0.1, 12),
+        process(240, "sshd", "root", "Idle", 0.0, 8),
+        process(1012, "firefox", "alice", "Running", 35.5, 850),
+        process(1013, "steam", "alice", "Sleeping", 4.2, 300),
+        process(2077, "cs2", "carol", "Running", 72.0, 2100),
+        process(3001, "cargo", "bob", "Running", 18.3, 400),
+        process(3002, "rust-analyzer", "bob", "Sleeping", 2.5, 1200),
+        process(4100, "vim", "dave", "Stopped", 0.4, 25),
+    ]
+}
+
+/// Four frames of the fixture a second apart; firefox exits after the
+/// second one.
+pub fn recording() -> Vec<Frame> {
+    (0..4)
+        .map(|i| {
+            let mut processes = fixture();
+            if i >= 2 {
+                processes.retain(|p| p.name != "firefox");
+            }
+            Frame::new(
+                UNIX_EPOCH + Duration::from_millis(START_MS + i * 1000),
+                Duration::from_millis(5),
+                Totals::default(),
+                processes,
+            )
+        })
+        .collect()
+}
+
+/// The TUI with the default config replaying `frames`, on the first one.
+/// Keys aren't debounced and symbols are ASCII.
+pub fn replaying(source: FakeSource, frames: Vec<Frame>) -> App<FakeSource> {
+    glyphs::set_ascii(true);
+    let config = Config::default();
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let mut app = App::replaying(
+        source,
+        Player::new(frames).unwrap(),
+        config,
+        views,
+        keymap,
+        theme,
+        watcher,
+    );
+    app.set_debounce(Duration::ZERO);
+    app.receive_snapshot();
+    app
+}
+
+/// A fresh, empty directory for one test under the system temp dir.
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("procsnipe-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,231 @@
+//! The config file: defaults, what validation rejects and how it says so,
+//! protection and game marking, and picking up edits while running.
+
+mod common;
+
+use common::{process, scratch};
+use procsnipe::config::{Config, ConfigWatcher};
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// The whole error chain for `toml`, or `None` if it's valid.
+fn error(toml: &str) -> Option<String> {
+    let config: Config = toml::from_str(toml).unwrap_or_else(|e| panic!("{}: {}", toml, e));
+    config.validate().err().map(|e| format!("{:#}", e))
+}
+
+/// Write `text` to `path` with a modification time `secs` in, so the
+/// watcher sees a change however coarse the filesystem's clock is.
+fn write(path: &Path, text: &str, secs: u64) {
+    fs::write(path, text).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000 + secs))
+        .unwrap();
+}
+
+#[test]
+fn example_is_the_defaults() {
+    Config::default().validate().unwrap();
+    let example = Config::load_file(Path::new("config.example.toml")).unwrap();
+    let defaults = Config::default();
+    assert_eq!(example.refresh_ms, defaults.refresh_ms);
+    assert_eq!(example.protected, defaults.protected);
+    assert_eq!(example.game_indicators(), defaults.game_indicators());
+    assert_eq!(example.colors.cpu_high, defaults.colors.cpu_high);
+    assert_eq!(example.tray.poll_secs, defaults.tray.poll_secs);
+    assert_eq!(example.hung.stuck_secs, defaults.hung.stuck_secs);
+    assert_eq!(example.theme.name, defaults.theme.name);
+}
+
+#[test]
+fn validation_errors() {
+    for (toml, expected) in [
+        (
+            "refresh_ms = 50",
+            "refresh_ms = 50 is out of range (100 to 60000 milliseconds)",
+        ),
+        ("refresh_ms = 60001", "refresh_ms = 60001 is out of range"),
+        (
+            "[colors]\ncpu_medium = -1.0",
+            "colors.cpu_medium and colors.cpu_high can't be negative",
+        ),
+        (
+            "[colors]\ncpu_medium = 60.0\ncpu_high = 50.0",
+            "colors.cpu_medium (60) must be below colors.cpu_high (50)",
+        ),
+        (
+            "[tray]\ncpu_alert = 0.0",
+            "tray.cpu_alert = 0 must be above 0 (it's a CPU percentage)",
+        ),
+        ("[tray]\npoll_secs = 0", "tray.poll_secs must be at least 1"),
+        (
+            "[games]\nextra = [\" \"]",
+            "games.indicators and games.extra can't contain empty strings",
+        ),
+        (
+            "[leaks]\nmb_per_min = 0.0",
+            "leaks.mb_per_min = 0 must be above 0 (set leaks.enabled = false to turn it off)",
+        ),
+        (
+            "[leaks]\nwindow_mins = 0",
+            "leaks.window_mins must be at least 1",
+        ),
+        (
+            "[hung]\nblocked_secs = 0",
+            "hung.blocked_secs must be at least 1 (set hung.enabled = false to turn it off)",
+        ),
+        (
+            "[[views]]\nname = \"busy\"\nfilter = \"cpu>\"",
+            "in [[views]]: view 'busy': bad filter \"cpu>\": col 5: expected a value",
+        ),
+        (
+            "[[views]]\nname = \" \"\nfilter = \"cpu>5\"",
+            "in [[views]]: view with filter \"cpu>5\" has no name",
+        ),
+        (
+            "[[views]]\nname = \"bare\"\ncolumns = []",
+            "view 'bare': needs at least one column",
+        ),
+        (
+            "[[alerts]]\nname = \"x\"\ncpu = 5\nactions = [\"explode\"]",
+            "in [[alerts]]",
+        ),
+        (
+            "[keys]\nfly = [\"f\"]",
+            "unknown action 'fly' in [keys] (known: ",
+        ),
+        (
+            "[keys]\nsearch = [\"j\"]",
+            "'j' is bound to both down and search",
+        ),
+        (
+            "[theme]\nname = \"neon\"",
+            "theme.name = \"neon\" is not a built-in theme",
+        ),
+        (
+            "[theme]\ncolors = \"8\"",
+            "theme.colors = \"8\" must be auto, truecolor, 256 or 16",
+        ),
+        (
+            "[theme]\naccent = \"blurple\"",
+            "theme.accent = \"blurple\" is not a color",
+        ),
+        (
+            "[theme]\nsparkle = \"red\"",
+            "unknown theme color 'sparkle'",
+        ),
+    ] {
+        let err = error(toml).unwrap_or_else(|| panic!("{:?} was accepted", toml));
+        assert!(err.contains(expected), "{:?}: {}", toml, err);
+    }
+
+    // The edges of each range are fine.
+    for toml in [
+        "refresh_ms = 100",
+        "refresh_ms = 60000",
+        "[colors]\ncpu_medium = 0.0\ncpu_high = 0.1",
+        "[tray]\npoll_secs = 1",
+        "[keys]\nsearch = []\ndown = [\"/\"]",
+        "[theme]\nname = \"light\"\ncolors = \"16\"\naccent = \"#ff8800\"",
+    ] {
+        assert_eq!(error(toml), None, "{:?}", toml);
+    }
+}
+
+#[test]
+fn file_errors_name_the_file() {
+    let dir = scratch("config-files");
+    let path = dir.join("config.toml");
+    for (text, expected) in [
+        ("refrsh_ms = 500", "unknown field `refrsh_ms`"),
+        ("[colors]\ncpu_hgh = 1.0", "unknown field `cpu_hgh`"),
+        ("refresh_ms = \"fast\"", "invalid type"),
+        ("refresh_ms = 5", "out of range"),
+    ] {
+        fs::write(&path, text).unwrap();
+        let err = format!("{:#}", Config::load_file(&path).unwrap_err());
+        assert!(
+            err.starts_with(&format!("invalid config in {}", path.display())),
+            "{}",
+            err
+        );
+        assert!(err.contains(expected), "{:?}: {}", text, err);
+    }
+
+    // Asked for by name, so it has to be there.
+    let missing = dir.join("missing.toml");
+    let err = Config::load(Some(&missing)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("config file {} does not exist", missing.display())
+    );
+}
+
+#[test]
+fn protection_and_games() {
+    let config: Config = toml::from_str(
+        "protected = [\"Guardian\"]\n[games]\nindicators = [\"quake\"]\nextra = [\"Doom\"]",
+    )
+    .unwrap();
+    // PIDs 0 and 1 and procsnipe itself whatever the list says.
+    assert!(config.is_protected(0, "anything"));
+    assert!(config.is_protected(1, "anything"));
+    assert!(config.is_protected(std::process::id(), "procsnipe"));
+    assert!(config.is_protected(500, "guardian"));
+    assert!(!config.is_protected(500, "guardian2"));
+    // The list replaces the defaults.
+    assert!(!config.is_protected(500, "systemd"));
+    assert!(Config::default().is_protected(500, "SystemD"));
+
+    assert_eq!(config.game_indicators(), ["quake", "doom"]);
+    let mut processes = vec![
+        process(700, "QuakeLive", "alice", "Running", 1.0, 10),
+        process(701, "gzdoom", "alice", "Running", 1.0, 10),
+        process(702, "steam", "alice", "Running", 1.0, 10),
+        process(703, "Guardian", "alice", "Running", 1.0, 10),
+    ];
+    config.mark(&mut processes);
+    let marked: Vec<(bool, bool)> = processes
+        .iter()
+        .map(|p| (p.is_game, p.is_protected))
+        .collect();
+    assert_eq!(
+        marked,
+        [(true, false), (true, false), (false, false), (false, true)]
+    );
+}
+
+#[test]
+fn watcher_picks_up_edits() {
+    let dir = scratch("config-watch");
+    let path = dir.join("config.toml");
+    write(&path, "refresh_ms = 500", 0);
+    let mut watcher = ConfigWatcher::new(Some(&path));
+    assert_eq!(watcher.path(), Some(path.as_path()));
+    assert!(watcher.poll().is_none(), "nothing changed yet");
+
+    write(&path, "refresh_ms = 250", 10);
+    let config = watcher.poll().unwrap().unwrap();
+    assert_eq!(config.refresh_ms, 250);
+    assert!(watcher.poll().is_none(), "only reported once");
+
+    // A mistake is reported, and reported again only after the next edit.
+    write(&path, "refresh_ms = 5", 20);
+    assert!(watcher.poll().unwrap().is_err());
+    assert!(watcher.poll().is_none());
+
+    // Our own writes (saving a view) aren't news after a sync.
+    write(&path, "refresh_ms = 1000", 30);
+    watcher.sync();
+    assert!(watcher.poll().is_none());
+
+    // Deleting it goes back to the defaults.
+    fs::remove_file(&path).unwrap();
+    let config = watcher.poll().unwrap().unwrap();
+    assert_eq!(config.refresh_ms, Config::default().refresh_ms);
+    assert!(watcher.poll().is_none());
+}
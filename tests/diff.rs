@@ -0,0 +1,261 @@
+//! Comparing two snapshots: the diff itself, loading saved snapshots, and
+//! `:diff` in the TUI.
+
+mod common;
+
+use common::{START_MS, fixture, process, recording, replaying, scratch};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::app::{App, Mode};
+use procsnipe::diff::{self, Diff};
+use procsnipe::output::{self, Format};
+use procsnipe::record::{Recorder, Rotation};
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, ProcessInfo};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use serde_json::Value;
+use std::fs;
+
+fn pids(processes: &[ProcessInfo]) -> Vec<u32> {
+    processes.iter().map(|p| p.pid).collect()
+}
+
+fn started_at(mut p: ProcessInfo, start_time: u64) -> ProcessInfo {
+    p.start_time = start_time;
+    p
+}
+
+/// The fixture after a while: firefox exited, a build started, cs2 calmed
+/// down and rust-analyzer grew.
+fn later() -> Vec<ProcessInfo> {
+    let mut processes = fixture();
+    processes.retain(|p| p.name != "firefox");
+    processes.push(process(5000, "rustc", "bob", "Running", 95.0, 600));
+    for p in &mut processes {
+        match p.name.as_str() {
+            "cs2" => p.cpu_usage = 12.0,
+            "rust-analyzer" => p.memory += 900 * 1024 * 1024,
+            "vim" => p.cpu_usage = 1.4,
+            _ => {}
+        }
+    }
+    processes
+}
+
+#[test]
+fn started_exited_and_changed() {
+    let diff = Diff::new(&fixture(), &later());
+    assert_eq!(pids(&diff.started), [5000]);
+    assert_eq!(pids(&diff.exited), [1012]);
+    assert_eq!(diff.changed.len(), fixture().len() - 1);
+
+    let cpu: Vec<(u32, f32)> = diff
+        .top_cpu(10)
+        .iter()
+        .map(|d| (d.after.pid, d.cpu()))
+        .collect();
+    assert_eq!(cpu, [(2077, -60.0), (4100, 1.0)]);
+    let memory = diff.top_memory(1);
+    assert_eq!(memory.len(), 1);
+    assert_eq!(memory[0].after.name, "rust-analyzer");
+    assert_eq!(memory[0].memory(), 900 * 1024 * 1024);
+}
+
+#[test]
+fn reused_pid_is_an_exit_and_a_start() {
+    let before = vec![
+        started_at(process(10, "old", "root", "Running", 1.0, 10), 1000),
+        started_at(process(11, "same", "root", "Running", 1.0, 10), 1000),
+        // Unknown start times never count as reuse.
+        process(12, "unknown", "root", "Running", 1.0, 10),
+    ];
+    let after = vec![
+        started_at(process(10, "new", "root", "Running", 1.0, 10), 5000),
+        // Start times are rounded to the second; one off is the same process.
+        started_at(process(11, "same", "root", "Running", 1.0, 10), 1001),
+        started_at(process(12, "unknown", "root", "Running", 1.0, 10), 5000),
+    ];
+    let diff = Diff::new(&before, &after);
+    assert_eq!(diff.exited.len(), 1);
+    assert_eq!(diff.exited[0].name, "old");
+    assert_eq!(diff.started.len(), 1);
+    assert_eq!(diff.started[0].name, "new");
+    assert_eq!(diff.changed.len(), 2);
+
+    // Different PIDs are never the same process, whatever the start times.
+    assert!(diff::same_process(&before[1], &after[1]));
+    assert!(!diff::same_process(&before[1], &before[2]));
+}
+
+#[test]
+fn table_and_json_output() {
+    let diff = Diff::new(&fixture(), &later());
+    let mut out = Vec::new();
+    diff::write(&mut out, Format::Table, &diff, 10).unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.starts_with("started: 1\n"), "{}", text);
+    assert!(text.contains("exited: 1\n"), "{}", text);
+    assert!(text.contains("firefox"), "{}", text);
+    assert!(text.contains("72.0% ->      12.0%  -60.0%"), "{}", text);
+    assert!(text.contains("1200 MB ->    2100 MB  +900 MB"), "{}", text);
+
+    let mut out = Vec::new();
+    diff::write(&mut out, Format::Json, &diff, 1).unwrap();
+    let json: Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(json["started"][0]["name"], "rustc");
+    assert_eq!(json["exited"][0]["pid"], 1012);
+    assert_eq!(json["cpu"].as_array().unwrap().len(), 1);
+    assert_eq!(json["cpu"][0]["cpu_after"], 12.0);
+    assert_eq!(json["memory"][0]["name"], "rust-analyzer");
+
+    assert!(diff::write(&mut Vec::new(), Format::Csv, &diff, 10).is_err());
+}
+
+#[test]
+fn loading_saved_snapshots() {
+    let dir = scratch("diff-load");
+    let list = |format: Format, name: &str| {
+        let mut out = Vec::new();
+        output::write(&mut out, format, &fixture(), &[]).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, out).unwrap();
+        path.display().to_string()
+    };
+    let json = list(Format::Json, "list.json");
+    let ndjson = list(Format::Ndjson, "list.ndjson");
+    assert_eq!(diff::load(&json).unwrap().processes, fixture());
+    assert_eq!(diff::load(&ndjson).unwrap().processes, fixture());
+    let err = diff::load(&format!("{}@50%", json)).unwrap_err();
+    assert!(err.to_string().contains("not a recording"), "{}", err);
+
+    let path = dir.join("rec.ndjson");
+    let mut recorder = Recorder::open(&path, Rotation::default()).unwrap();
+    for frame in recording() {
+        recorder.record(&frame).unwrap();
+    }
+    let spec = path.display().to_string();
+    let last = diff::load(&spec).unwrap();
+    assert_eq!(last.time_ms, START_MS + 3000);
+    let first = diff::load(&format!("{}@0%", spec)).unwrap();
+    assert_eq!(first.time_ms, START_MS);
+    assert_eq!(first.processes.len(), fixture().len());
+    assert!(diff::load(&format!("{}@noon", spec)).is_err());
+
+    // An @ in the path itself: the whole name wins when it's a file, and a
+    // point in time still works after it.
+    let host = dir.join("me@host");
+    fs::create_dir_all(&host).unwrap();
+    fs::copy(&path, host.join("rec.ndjson")).unwrap();
+    let spec = host.join("rec.ndjson").display().to_string();
+    assert_eq!(diff::load(&spec).unwrap().time_ms, START_MS + 3000);
+    assert_eq!(
+        diff::load(&format!("{}@0%", spec)).unwrap().time_ms,
+        START_MS
+    );
+    fs::copy(&json, dir.join("list@2.json")).unwrap();
+    let at_sign = dir.join("list@2.json").display().to_string();
+    assert_eq!(diff::load(&at_sign).unwrap().processes, fixture());
+
+    fs::write(dir.join("junk.txt"), "hello\n").unwrap();
+    let err = diff::load(&dir.join("junk.txt").display().to_string()).unwrap_err();
+    assert!(
+        format!("{:#}", err).contains("isn't a recording"),
+        "{:#}",
+        err
+    );
+}
+
+struct Harness {
+    app: App<FakeSource>,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    /// Replaying [`recording`], so every snapshot is known up front.
+    fn new() -> Self {
+        Self {
+            app: replaying(FakeSource::default(), recording()),
+            terminal: Terminal::new(TestBackend::new(100, 20)).unwrap(),
+        }
+    }
+
+    fn key(&mut self, code: KeyCode) {
+        let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        assert!(!self.app.on_event(event).unwrap());
+        self.app.receive_snapshot();
+    }
+
+    fn keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            self.key(KeyCode::Char(c));
+        }
+    }
+
+    fn command(&mut self, line: &str) {
+        self.keys(&format!(":{}", line));
+        self.key(KeyCode::Enter);
+    }
+
+    fn render(&mut self) -> String {
+        self.terminal.draw(|f| ui(f, &mut self.app)).unwrap();
+        self.terminal.backend().to_string()
+    }
+}
+
+#[test]
+fn diff_view_against_a_mark() {
+    let mut h = Harness::new();
+    h.command("diff");
+    assert_eq!(h.app.mode(), Mode::Normal);
+    assert!(
+        h.app
+            .status_message()
+            .unwrap()
+            .contains("nothing to compare")
+    );
+
+    h.command("diff mark");
+    let mark = procsnipe::replay::clock(START_MS);
+    assert_eq!(
+        h.app.status_message().unwrap(),
+        format!("diff baseline set at {}", mark)
+    );
+    h.keys("3l");
+    h.command("diff");
+    assert_eq!(h.app.mode(), Mode::Diff);
+    let screen = h.render();
+    assert!(
+        screen.contains(&format!("Diff: {} -> now", mark)),
+        "{}",
+        screen
+    );
+    assert!(screen.contains("Started (0)"), "{}", screen);
+    assert!(screen.contains("Exited (1)"), "{}", screen);
+    assert!(screen.contains("1012     firefox"), "{}", screen);
+
+    h.key(KeyCode::Esc);
+    assert_eq!(h.app.mode(), Mode::Normal);
+}
+
+#[test]
+fn diff_view_against_a_file() {
+    let dir = scratch("diff-tui");
+    let path = dir.join("before.json");
+    let mut before = fixture();
+    before.push(process(6000, "gone", "root", "Running", 1.0, 1));
+    let mut out = Vec::new();
+    output::write(&mut out, Format::Json, &before, &[]).unwrap();
+    fs::write(&path, out).unwrap();
+
+    let mut h = Harness::new();
+    h.command(&format!("diff {}", path.display()));
+    assert_eq!(h.app.mode(), Mode::Diff);
+    let screen = h.render();
+    assert!(screen.contains("Exited (1)"), "{}", screen);
+    assert!(screen.contains("gone"), "{}", screen);
+
+    h.key(KeyCode::Esc);
+    h.command("diff /nonexistent/before.json");
+    assert_eq!(h.app.mode(), Mode::Normal);
+    assert!(h.app.status_message().unwrap().contains("couldn't read"));
+}
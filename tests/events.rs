@@ -0,0 +1,394 @@
+//! The start/exit log: diffing snapshots, the file, and the TUI pane.
+
+mod common;
+
+use common::{fixture, process, recording, replaying};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::app::App;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::connector::{self, ProcEvent, ProcEventKind};
+use procsnipe::events::{self, EventKind, EventLog, format_runtime};
+use procsnipe::keymap::Keymap;
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, ProcessInfo, view};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MB: u64 = 1024 * 1024;
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn started(mut p: ProcessInfo, start_time: u64, parent: u32) -> ProcessInfo {
+    p.start_time = start_time;
+    p.parent = Some(parent);
+    p
+}
+
+fn kinds(log: &EventLog) -> Vec<(EventKind, u32)> {
+    log.events().iter().map(|e| (e.kind, e.pid)).collect()
+}
+
+#[test]
+fn starts_and_exits_between_snapshots() {
+    let mut log = EventLog::new();
+    let helper = |mem| started(process(500, "helper", "root", "Run", 1.0, mem), 1001, 1);
+
+    log.update(&fixture(), at(1000)).unwrap();
+    assert!(log.events().is_empty(), "what's already running isn't news");
+
+    let mut second = fixture();
+    second.push(helper(40));
+    log.update(&second, at(1002)).unwrap();
+    let mut third = fixture();
+    third.push(helper(90));
+    log.update(&third, at(1003)).unwrap();
+    log.update(&fixture(), at(1005)).unwrap();
+
+    assert_eq!(
+        kinds(&log),
+        [(EventKind::Start, 500), (EventKind::Exit, 500)]
+    );
+    // The start time is known, so that's when it started rather than
+    // when we noticed.
+    let start = &log.events()[0];
+    assert_eq!(start.time_ms, 1_001_000);
+    assert_eq!(start.name, "helper");
+    assert_eq!(start.parent, Some(1));
+    assert_eq!(start.runtime_ms, None);
+    let exit = &log.events()[1];
+    assert_eq!(exit.time_ms, 1_005_000);
+    assert_eq!(exit.runtime_ms, Some(4000));
+    assert_eq!(exit.peak_memory, Some(90 * MB));
+}
+
+#[test]
+fn reused_pid_is_an_exit_then_a_start() {
+    let mut log = EventLog::new();
+    log.update(
+        &[started(process(7, "old", "root", "Run", 0.0, 1), 100, 1)],
+        at(200),
+    )
+    .unwrap();
+    log.update(
+        &[started(process(7, "new", "root", "Run", 0.0, 1), 150, 1)],
+        at(201),
+    )
+    .unwrap();
+    let names: Vec<(EventKind, &str)> = log
+        .events()
+        .iter()
+        .map(|e| (e.kind, e.name.as_str()))
+        .collect();
+    assert_eq!(names, [(EventKind::Exit, "old"), (EventKind::Start, "new")]);
+}
+
+#[test]
+fn going_back_in_time_starts_over() {
+    let mut log = EventLog::new();
+    let mut fewer = fixture();
+    fewer.pop();
+    log.update(&fixture(), at(100)).unwrap();
+    log.update(&fewer, at(50)).unwrap();
+    assert!(log.events().is_empty());
+    log.update(&fixture(), at(60)).unwrap();
+    assert_eq!(kinds(&log), [(EventKind::Start, 4100)]);
+}
+
+#[test]
+fn memory_keeps_the_newest() {
+    let mut log = EventLog::new();
+    log.update(&[], at(0)).unwrap();
+    for i in 0..events::KEEP as u32 + 10 {
+        let p = process(10_000 + i, "blip", "root", "Run", 0.0, 1);
+        log.update(&[p], at(1 + i as u64)).unwrap();
+    }
+    assert_eq!(log.events().len(), events::KEEP);
+    assert_eq!(
+        log.events().back().unwrap().pid,
+        10_000 + events::KEEP as u32 + 9
+    );
+}
+
+#[test]
+fn events_are_appended_to_the_file() {
+    let dir = std::env::temp_dir().join(format!("procsnipe-events-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("events.ndjson");
+
+    let mut log = EventLog::new();
+    log.persist_to(&path).unwrap();
+    log.update(&fixture(), at(100)).unwrap();
+    log.update(&fixture()[1..], at(101)).unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    let lines: Vec<events::Event> = text
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0], log.events()[0]);
+    assert!(text.contains(r#""kind":"exit""#), "{}", text);
+}
+
+fn proc_event(time_ms: u64, pid: u32, name: &str, kind: ProcEventKind) -> ProcEvent {
+    ProcEvent {
+        time_ms,
+        pid,
+        name: Some(name.to_string()),
+        kind,
+    }
+}
+
+fn exit(code: Option<i32>, signal: Option<i32>) -> ProcEventKind {
+    ProcEventKind::Exit { code, signal }
+}
+
+#[test]
+fn connector_events_are_exact() {
+    let mut log = EventLog::new();
+    log.update(&fixture(), at(100)).unwrap();
+    let fork = ProcEventKind::Fork { parent: 3001 };
+    // A build script: forks, execs, gone 5ms later.
+    log.record(proc_event(100_200, 600, "cargo", fork)).unwrap();
+    assert!(log.events().is_empty(), "waits for the exec to name it");
+    log.record(proc_event(100_201, 600, "sh", ProcEventKind::Exec))
+        .unwrap();
+    log.record(proc_event(100_205, 600, "sh", exit(Some(2), None)))
+        .unwrap();
+    // Forked and killed without ever exec'ing.
+    log.record(proc_event(100_300, 601, "cargo", fork)).unwrap();
+    log.record(proc_event(100_310, 601, "cargo", exit(None, Some(9))))
+        .unwrap();
+    // Something that was running before we looked.
+    log.record(proc_event(100_400, 4100, "vim", exit(Some(0), None)))
+        .unwrap();
+
+    let events: Vec<_> = log
+        .events()
+        .iter()
+        .map(|e| (e.kind, e.pid, e.name.as_str(), e.time_ms))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            (EventKind::Start, 600, "sh", 100_200),
+            (EventKind::Exit, 600, "sh", 100_205),
+            (EventKind::Start, 601, "cargo", 100_300),
+            (EventKind::Exit, 601, "cargo", 100_310),
+            (EventKind::Exit, 4100, "vim", 100_400),
+        ]
+    );
+    let sh = &log.events()[1];
+    assert_eq!(sh.parent, Some(3001));
+    assert_eq!(sh.runtime_ms, Some(5));
+    assert_eq!(sh.exit_code, Some(2));
+    assert_eq!(sh.peak_memory, None);
+    assert_eq!(log.events()[3].signal, Some(9));
+    assert_eq!(log.events()[4].runtime_ms, Some(400));
+
+    // The next snapshot doesn't log any of it again.
+    let mut later = fixture();
+    later.retain(|p| p.pid != 4100);
+    log.update(&later, at(101)).unwrap();
+    assert_eq!(log.events().len(), 5);
+}
+
+#[test]
+fn exit_between_the_scan_and_the_update() {
+    let mut log = EventLog::new();
+    log.update(&fixture(), at(100)).unwrap();
+    // Scanned at 101 with vim still there, but vim's exit is logged
+    // before that snapshot gets here.
+    log.record(proc_event(101_500, 4100, "vim", exit(Some(0), None)))
+        .unwrap();
+    log.update(&fixture(), at(101)).unwrap();
+    let mut later = fixture();
+    later.retain(|p| p.pid != 4100);
+    log.update(&later, at(102)).unwrap();
+    assert_eq!(kinds(&log), [(EventKind::Exit, 4100)]);
+
+    // Once a newer snapshot is in, the PID is free for reuse.
+    later.push(started(process(4100, "vi", "dave", "Run", 0.0, 5), 102, 1));
+    log.update(&later, at(103)).unwrap();
+    assert_eq!(
+        kinds(&log),
+        [(EventKind::Exit, 4100), (EventKind::Start, 4100)]
+    );
+}
+
+#[test]
+fn snapshot_names_a_fork_that_never_execs() {
+    let mut log = EventLog::new();
+    log.update(&[], at(100)).unwrap();
+    let fork = ProcEventKind::Fork { parent: 1 };
+    log.record(proc_event(100_500, 700, "worker", fork))
+        .unwrap();
+    let worker = process(700, "worker", "root", "Run", 0.0, 5);
+    log.update(std::slice::from_ref(&worker), at(101)).unwrap();
+    log.update(std::slice::from_ref(&worker), at(102)).unwrap();
+    assert_eq!(kinds(&log), [(EventKind::Start, 700)]);
+    assert_eq!(log.events()[0].time_ms, 100_500);
+}
+
+#[test]
+fn forks_match_the_snapshot_start_time() {
+    let mut log = EventLog::new();
+    log.update(&[], at(100)).unwrap();
+    let fork = ProcEventKind::Fork { parent: 1 };
+    log.record(proc_event(100_999, 700, "sh", fork)).unwrap();
+    log.record(proc_event(100_999, 700, "make", ProcEventKind::Exec))
+        .unwrap();
+    // sysinfo puts the start two seconds later than the event said.
+    let make = started(process(700, "make", "root", "Run", 0.0, 5), 102, 1);
+    log.update(std::slice::from_ref(&make), at(103)).unwrap();
+    log.update(std::slice::from_ref(&make), at(104)).unwrap();
+    assert_eq!(kinds(&log), [(EventKind::Start, 700)]);
+    assert_eq!(log.events()[0].time_ms, 100_999);
+
+    // Once a snapshot has the start time, a reused PID still shows.
+    let again = started(process(700, "make", "root", "Run", 0.0, 5), 110, 1);
+    log.update(&[again], at(111)).unwrap();
+    assert_eq!(
+        kinds(&log),
+        [
+            (EventKind::Start, 700),
+            (EventKind::Exit, 700),
+            (EventKind::Start, 700)
+        ]
+    );
+}
+
+/// One netlink message carrying a `proc_event`, laid out like the kernel's.
+fn datagram(what: u32, timestamp_ns: u64, fields: [u32; 4]) -> Vec<u8> {
+    let mut event = Vec::new();
+    event.extend(what.to_ne_bytes());
+    event.extend(0u32.to_ne_bytes()); // cpu
+    event.extend(timestamp_ns.to_ne_bytes());
+    for field in fields {
+        event.extend(field.to_ne_bytes());
+    }
+    let mut msg = Vec::new();
+    msg.extend(((16 + 20 + event.len()) as u32).to_ne_bytes());
+    msg.extend(3u16.to_ne_bytes()); // NLMSG_DONE
+    msg.extend([0u8; 10]); // flags, seq, port
+    msg.extend(1u32.to_ne_bytes()); // CN_IDX_PROC
+    msg.extend(1u32.to_ne_bytes()); // CN_VAL_PROC
+    msg.extend([0u8; 8]); // seq, ack
+    msg.extend((event.len() as u16).to_ne_bytes());
+    msg.extend(0u16.to_ne_bytes());
+    msg.extend(event);
+    msg
+}
+
+#[test]
+fn parsing_kernel_messages() {
+    const FORK: u32 = 1;
+    const EXEC: u32 = 2;
+    const EXIT: u32 = 0x8000_0000;
+    let boot_ms = 1_000_000;
+    let mut bytes = datagram(FORK, 5_000_000, [10, 10, 42, 42]);
+    // A new thread, not a process.
+    bytes.extend(datagram(FORK, 5_000_000, [42, 42, 43, 42]));
+    bytes.extend(datagram(EXEC, 6_000_000, [42, 42, 0, 0]));
+    bytes.extend(datagram(EXIT, 7_000_000, [42, 42, 3 << 8, 17]));
+    bytes.extend(datagram(EXIT, 8_000_000, [43, 43, 9, 17]));
+    // One thread of many exiting.
+    bytes.extend(datagram(EXIT, 8_000_000, [44, 43, 0, 17]));
+
+    let events = connector::parse(&bytes, boot_ms);
+    let kinds: Vec<(u64, u32, ProcEventKind)> =
+        events.iter().map(|e| (e.time_ms, e.pid, e.kind)).collect();
+    assert_eq!(
+        kinds,
+        [
+            (1_000_005, 42, ProcEventKind::Fork { parent: 10 }),
+            (1_000_006, 42, ProcEventKind::Exec),
+            (1_000_007, 42, exit(Some(3), None)),
+            (1_000_008, 43, exit(None, Some(9))),
+        ]
+    );
+    assert!(connector::parse(&bytes[..20], boot_ms).is_empty());
+}
+
+#[test]
+fn runtimes() {
+    assert_eq!(format_runtime(800), "800ms");
+    assert_eq!(format_runtime(42_500), "42s");
+    assert_eq!(format_runtime(312_000), "5m12s");
+    assert_eq!(format_runtime(3 * 3_600_000 + 4 * 60_000), "3h04m");
+}
+
+#[test]
+fn pane_shows_the_log() {
+    let mut app = replaying(FakeSource::default(), recording());
+    let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+
+    for c in "3le".chars() {
+        let event = Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        assert!(!app.on_event(event).unwrap());
+        app.receive_snapshot();
+    }
+    assert_eq!(app.events().events().len(), 1);
+
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen = terminal.backend().to_string();
+    assert!(screen.contains("Events (1)"), "{}", screen);
+    assert!(screen.contains("exit  1012     firefox"), "{}", screen);
+    assert!(screen.contains("peak 850 MB"), "{}", screen);
+
+    let event = Event::Key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE));
+    app.on_event(event).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert!(!terminal.backend().to_string().contains("Events ("));
+}
+
+/// Keep taking snapshots until `done` or five seconds pass.
+fn wait_for(app: &mut App<FakeSource>, what: &str, done: impl Fn(&App<FakeSource>) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !done(app) {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+}
+
+#[test]
+fn pausing_only_freezes_the_list() {
+    let config = Config {
+        refresh_ms: 100,
+        ..Config::default()
+    };
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let source = FakeSource::new(fixture());
+    let mut app = App::new(source.clone(), config, views, keymap, theme, watcher);
+    app.set_debounce(Duration::ZERO);
+    wait_for(&mut app, "a snapshot", |app| !app.processes().is_empty());
+
+    let space = Event::Key(KeyEvent::new(KeyCode::Char(' '), KeyModifiers::NONE));
+    assert!(!app.on_event(space.clone()).unwrap());
+    let mut processes = fixture();
+    processes.remove(2);
+    source.set_processes(processes);
+    wait_for(&mut app, "the exit", |app| {
+        !app.events().events().is_empty()
+    });
+    assert_eq!(kinds(app.events()), [(EventKind::Exit, 1012)]);
+    assert!(
+        app.processes().iter().any(|p| p.pid == 1012),
+        "the list stays as it was"
+    );
+
+    assert!(!app.on_event(space).unwrap());
+    wait_for(&mut app, "the list to catch up", |app| {
+        app.processes().iter().all(|p| p.pid != 1012)
+    });
+}
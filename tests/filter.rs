@@ -0,0 +1,231 @@
+//! The filter language: precedence, quoting, every field with every
+//! operator, and where errors point.
+
+mod common;
+
+use common::{fixture, process};
+use procsnipe::ProcessInfo;
+use procsnipe::filter::{self, Expr, Field, Op, Value};
+use procsnipe::hung::{Hang, HangKind};
+use std::time::Duration;
+
+/// The fixture plus a name with a space in it, cs2 flagged as a game and
+/// sshd hung on NFS.
+fn processes() -> Vec<ProcessInfo> {
+    let mut processes = fixture();
+    processes[4].is_game = true;
+    processes[1].hung = Some(Hang {
+        kind: HangKind::Blocked,
+        duration: Duration::from_secs(45),
+        wchan: Some("nfs_wait_on_request".to_string()),
+    });
+    processes.push(process(5000, "Web Content", "alice", "Sleeping", 1.0, 200));
+    processes
+}
+
+/// PIDs matching `query`, in fixture order.
+fn pids(query: &str) -> Vec<u32> {
+    let expr = filter::parse(query)
+        .unwrap_or_else(|e| panic!("{}: {}", query, e))
+        .unwrap_or_else(|| panic!("{} is empty", query));
+    processes()
+        .iter()
+        .filter(|p| expr.matches(p))
+        .map(|p| p.pid)
+        .collect()
+}
+
+/// Every PID except `except`.
+fn all_but(except: &[u32]) -> Vec<u32> {
+    processes()
+        .iter()
+        .map(|p| p.pid)
+        .filter(|pid| !except.contains(pid))
+        .collect()
+}
+
+fn text(t: &str) -> Expr {
+    Expr::Text(t.to_string())
+}
+
+#[test]
+fn precedence() {
+    // `!` binds tightest, then `&&` (or just a space), then `||`.
+    assert_eq!(
+        filter::parse("a || b c").unwrap().unwrap(),
+        Expr::Or(Box::new(text("a")), Box::new(text("b").and(text("c"))))
+    );
+    assert_eq!(
+        filter::parse("!a && b").unwrap().unwrap(),
+        Expr::Not(Box::new(text("a"))).and(text("b"))
+    );
+    assert_eq!(pids("user=root || user=bob && cpu>10"), [1, 240, 3001]);
+    assert_eq!(pids("(user=root || user=bob) && cpu>10"), [3001]);
+    assert_eq!(pids("user=bob cpu>10"), [3001]);
+    assert_eq!(pids("!user=root && mem<100"), [4100]);
+    assert_eq!(pids("!(user=root && mem<10)"), all_but(&[240]));
+    assert_eq!(pids("!!cargo"), [3001]);
+    // Left to right within a level.
+    assert_eq!(pids("vim || cargo || steam"), [1013, 3001, 4100]);
+    assert_eq!(pids("user=bob && cpu>1 && mem>1g"), [3002]);
+
+    // The words work too, in any case.
+    assert_eq!(
+        pids("not (user=root OR user=alice) and status=running"),
+        [2077, 3001]
+    );
+    assert_eq!(pids("((((cargo))))"), [3001]);
+}
+
+#[test]
+fn quoting() {
+    // A bare word matches anywhere in the name, a quoted one can hold spaces.
+    assert_eq!(pids("\"web content\""), [5000]);
+    assert_eq!(pids("'Web Content'"), [5000]);
+    assert_eq!(pids("name=\"web content\""), [5000]);
+    assert_eq!(pids("web content"), [5000]);
+    assert!(pids("\"web  content\"").is_empty());
+
+    // Quotes make keywords and operators plain text.
+    for (query, expected) in [
+        ("\"or\"", "or"),
+        ("'&&'", "&&"),
+        ("\"cpu>5\"", "cpu>5"),
+        ("\"it's\"", "it's"),
+        ("'say \"hi\"'", "say \"hi\""),
+        ("\"a \\\"b\\\" c\"", "a \"b\" c"),
+        ("'back\\\\slash'", "back\\slash"),
+    ] {
+        assert_eq!(
+            filter::parse(query).unwrap(),
+            Some(text(expected)),
+            "{}",
+            query
+        );
+    }
+
+    // Whatever `quote` makes comes back out as the same string.
+    for value in ["plain", "with space", "\"quoted\"", "back\\slash", "it's"] {
+        let expr = filter::parse(&format!("name={}", filter::quote(value)))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            expr,
+            Expr::Compare {
+                field: Field::Name,
+                op: Op::Eq,
+                value: Value::Text(value.to_lowercase()),
+            }
+        );
+    }
+
+    assert_eq!(filter::parse("").unwrap(), None);
+    assert_eq!(filter::parse("  \t ").unwrap(), None);
+}
+
+#[test]
+fn every_field_and_operator() {
+    for (query, expected) in [
+        ("pid=1012", vec![1012]),
+        ("pid==1012", vec![1012]),
+        ("pid!=1", all_but(&[1])),
+        ("pid>3001", vec![3002, 4100, 5000]),
+        ("pid>=3001", vec![3001, 3002, 4100, 5000]),
+        ("pid<240", vec![1]),
+        ("pid<=240", vec![1, 240]),
+        ("cpu=0", vec![240]),
+        ("cpu!=0", all_but(&[240])),
+        ("cpu>35.5", vec![2077]),
+        ("cpu>=35.5", vec![1012, 2077]),
+        ("cpu<1", vec![1, 240, 4100]),
+        ("cpu<=1", vec![1, 240, 4100, 5000]),
+        ("cpu>50%", vec![2077]),
+        ("mem=850", vec![1012]),
+        ("mem!=8mb", all_but(&[240])),
+        ("mem>1g", vec![2077, 3002]),
+        ("mem>=1200", vec![2077, 3002]),
+        ("mem<12m", vec![240]),
+        ("mem<=12MiB", vec![1, 240]),
+        ("memory>2gib", vec![2077]),
+        ("mem<26000k", vec![1, 240, 4100]),
+        ("mem>1t", vec![]),
+        ("name=vim", vec![4100]),
+        ("name=VIM", vec![4100]),
+        ("name!=vim", all_but(&[4100])),
+        ("name~rust", vec![3002]),
+        ("name!~s", vec![1012, 3001, 4100, 5000]),
+        ("user=ALICE", vec![1012, 1013, 5000]),
+        ("user!=root", all_but(&[1, 240])),
+        ("user~o", vec![1, 240, 2077, 3001, 3002]),
+        ("user!~a", vec![1, 240, 3001, 3002]),
+        ("status=running", vec![1012, 2077, 3001]),
+        ("status!=running", all_but(&[1012, 2077, 3001])),
+        ("status~sleep", vec![1, 1013, 3002, 5000]),
+        ("status!~ing", vec![240, 4100]),
+        ("wchan=nfs_wait_on_request", vec![240]),
+        ("wchan!=nfs_wait_on_request", all_but(&[240])),
+        ("wchan~nfs", vec![240]),
+        ("wchan!~nfs", all_but(&[240])),
+        ("game", vec![2077]),
+        ("!game", all_but(&[2077])),
+        ("hung", vec![240]),
+        ("!hung", all_but(&[240])),
+        ("leak", vec![]),
+        ("CPU>50 && Name~CS", vec![2077]),
+    ] {
+        assert_eq!(pids(query), expected, "{}", query);
+    }
+}
+
+#[test]
+fn error_positions() {
+    for (query, col, message) in [
+        ("cpu~5", 0, "'~' only works on name, user, status and wchan"),
+        (
+            "vim || mem!~5",
+            7,
+            "'~' only works on name, user, status and wchan",
+        ),
+        ("name>vim", 0, "text fields only support =, !=, ~ and !~"),
+        (
+            "cargo status<=x",
+            6,
+            "text fields only support =, !=, ~ and !~",
+        ),
+        ("game=1", 0, "'game' is a flag, use 'game' or '!game'"),
+        (
+            "x && hung!=no",
+            5,
+            "'hung' is a flag, use 'hung' or '!hung'",
+        ),
+        ("cpu>", 4, "expected a value"),
+        ("cpu>)", 4, "expected a value"),
+        ("cpu>\"5\"", 4, "expected a number"),
+        ("cpu>abc", 4, "'abc' is not a number"),
+        ("cpu>5x", 4, "unknown cpu unit 'x'"),
+        ("mem > 5zb", 6, "unknown memory unit 'zb'"),
+        ("pid>1k", 4, "unexpected unit 'k'"),
+        ("(cargo", 0, "unclosed '('"),
+        ("vim (cargo || (x)", 4, "unclosed '('"),
+        ("cargo)", 5, "unexpected ')'"),
+        ("cargo ||", 8, "expected a filter term"),
+        ("&& cargo", 0, "unexpected '&&'"),
+        ("cargo & vim", 6, "expected '&&'"),
+        ("cargo | vim", 6, "expected '||'"),
+        ("name=\"vim", 5, "unterminated string"),
+        ("'", 0, "unterminated string"),
+        ("ünï)", 3, "unexpected ')'"),
+    ] {
+        let err = filter::parse(query).unwrap_err();
+        assert_eq!((err.pos, err.message.as_str()), (col, message), "{}", query);
+    }
+
+    let err = filter::parse("bogus=1").unwrap_err();
+    assert_eq!(err.pos, 0);
+    assert!(err.message.starts_with("unknown field 'bogus'"), "{}", err);
+    // Shown one-based.
+    assert_eq!(
+        filter::parse("cpu>").unwrap_err().to_string(),
+        "col 5: expected a value"
+    );
+}
@@ -0,0 +1,84 @@
+//! ASCII mode and fitting names into columns. The mode is one switch for
+//! the whole process, so everything that flips it is a single test.
+
+mod common;
+
+use common::{fixture, process};
+use procsnipe::app::App;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::Keymap;
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, glyphs, view};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
+
+/// The fixture plus a process with a double-width name, drawn once.
+fn screen() -> String {
+    let mut processes = fixture();
+    processes.push(process(6000, "ゲーム", "alice", "Running", 90.0, 100));
+    let config = Config {
+        refresh_ms: 60_000,
+        ..Config::default()
+    };
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let expected = processes.len();
+    let mut app = App::new(
+        FakeSource::new(processes),
+        config,
+        views,
+        keymap,
+        theme,
+        watcher,
+    );
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while app.processes().len() != expected {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for a snapshot"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+    let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    terminal.backend().to_string()
+}
+
+#[test]
+fn ascii_and_unicode() {
+    glyphs::set_ascii(false);
+    assert_eq!(glyphs::fit("firefox", 10), "firefox   ");
+    assert_eq!(glyphs::fit("rust-analyzer", 8), "rust-an…");
+    assert_eq!(glyphs::fit("", 3), "   ");
+    assert_eq!(glyphs::fit("abc", 0), "…");
+    // Double-width characters count twice, and one that won't fit whole
+    // is left out rather than split.
+    assert_eq!(glyphs::fit("ゲーム", 6), "ゲーム");
+    assert_eq!(glyphs::fit("ゲーム", 7), "ゲーム ");
+    assert_eq!(glyphs::fit("ゲーム", 5), "ゲー…");
+    assert_eq!(glyphs::fit("ゲーム", 4), "ゲ… ");
+    for width in 0..10 {
+        assert_eq!(glyphs::fit("ゲーム!", width).width(), width.max(1));
+    }
+    let unicode = screen();
+    assert!(unicode.contains("ゲーム"), "{}", unicode);
+    assert!(unicode.contains('┌'), "{}", unicode);
+
+    glyphs::set_ascii(true);
+    assert!(glyphs::is_ascii());
+    assert_eq!(glyphs::get().ellipsis, "~");
+    assert_eq!(glyphs::get().border.top_left, "+");
+    assert_eq!(glyphs::fit("ゲーム", 5), "???  ");
+    assert_eq!(glyphs::fit("naïve café", 6), "na?ve~");
+    assert_eq!(glyphs::fit("rust-analyzer", 8), "rust-an~");
+    let ascii = screen();
+    assert!(ascii.is_ascii(), "{}", ascii);
+    assert!(ascii.contains("???"), "{}", ascii);
+    assert!(ascii.contains("+-"), "{}", ascii);
+}
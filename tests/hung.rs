@@ -0,0 +1,317 @@
+//! The hung monitor: which states count and for how long, what it says,
+//! the `hung` flag, `wchan` field, column and view, and alerts on it.
+
+mod common;
+
+use common::{fixture, process, replaying};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::alerts::Alerts;
+use procsnipe::app::App;
+use procsnipe::config::Config;
+use procsnipe::hung::{Hang, HangKind, HungConfig, HungMonitor};
+use procsnipe::record::Frame;
+use procsnipe::ui::ui;
+use procsnipe::{
+    FakeSource, ProcessInfo, ProcessSource, Signal, SortMode, Totals, filter, glyphs, process,
+};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const START: u64 = 1_700_000_000;
+
+fn second(s: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(START + s)
+}
+
+/// Check a process whose status and CPU at each second are `state(second)`
+/// every 5 seconds, and return the seconds at which it got flagged as new.
+fn flagged_at(
+    config: HungConfig,
+    secs: u64,
+    state: impl Fn(u64) -> (&'static str, f32),
+) -> Vec<u64> {
+    let mut monitor = HungMonitor::new(config);
+    (0..=secs)
+        .step_by(5)
+        .filter(|&s| {
+            let (status, cpu) = state(s);
+            let p = process(500, "worker", "alice", status, cpu, 10);
+            !monitor.update(&mut [p], second(s), |_| None).is_empty()
+        })
+        .collect()
+}
+
+#[test]
+fn limits_per_kind() {
+    let config = HungConfig::default;
+    let always = |status: &'static str, cpu: f32| move |_| (status, cpu);
+    assert_eq!(
+        flagged_at(config(), 120, always("UninterruptibleDiskSleep", 0.0)),
+        [30]
+    );
+    assert_eq!(flagged_at(config(), 120, always("Zombie", 0.0)), [60]);
+    assert_eq!(flagged_at(config(), 600, always("Tracing", 0.0)), [300]);
+    assert_eq!(flagged_at(config(), 600, always("LockBlocked", 0.0)), [300]);
+    // Busy, just asleep or on the way out isn't hung.
+    assert!(flagged_at(config(), 600, always("Tracing", 0.5)).is_empty());
+    assert!(flagged_at(config(), 600, always("Dead", 0.0)).is_empty());
+    assert!(flagged_at(config(), 600, always("Sleeping", 0.0)).is_empty());
+    assert!(flagged_at(config(), 600, always("Runnable", 0.0)).is_empty());
+
+    // Waking up starts the clock over, and it's reported again next time.
+    let flaky = |s: u64| match s {
+        0..40 | 50..100 => ("UninterruptibleDiskSleep", 0.0),
+        _ => ("Sleeping", 0.0),
+    };
+    assert_eq!(flagged_at(config(), 120, flaky), [30, 80]);
+
+    let custom = HungConfig {
+        blocked_secs: 10,
+        ..HungConfig::default()
+    };
+    assert_eq!(
+        flagged_at(custom, 60, always("UninterruptibleDiskSleep", 0.0)),
+        [10]
+    );
+    let off = HungConfig {
+        enabled: false,
+        ..HungConfig::default()
+    };
+    assert!(flagged_at(off, 600, always("Zombie", 0.0)).is_empty());
+
+    let err = toml::from_str::<Config>("[hung]\nzombie_secs = 0")
+        .unwrap()
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("hung.zombie_secs"), "{}", err);
+}
+
+#[test]
+fn stopping_a_process_isnt_hanging() {
+    // Ctrl-Z'd vim sits at 0% CPU all afternoon.
+    let ctrl_z = flagged_at(HungConfig::default(), 3600, |_| ("Stopped", 0.0));
+    assert!(ctrl_z.is_empty(), "{:?}", ctrl_z);
+
+    // Nor after a suspend from procsnipe, and resuming doesn't either.
+    let mut source = FakeSource::new(vec![process(500, "worker", "alice", "Runnable", 0.0, 10)]);
+    source.signal(500, Signal::Stop).unwrap();
+    let mut monitor = HungMonitor::new(HungConfig::default());
+    for s in (0..=900).step_by(30) {
+        if s == 600 {
+            source.signal(500, Signal::Cont).unwrap();
+        }
+        let mut processes = source.processes();
+        assert!(
+            monitor
+                .update(&mut processes, second(s), |_| None)
+                .is_empty()
+        );
+        assert_eq!(processes[0].hung, None);
+    }
+}
+
+/// The fixture with vim sat at a breakpoint at 0% CPU, plus a zombie
+/// that cargo never reaps and an rsync stuck on a network filesystem.
+fn stuck() -> Vec<ProcessInfo> {
+    let mut processes = fixture();
+    processes[7].status = "Tracing".to_string();
+    processes[7].cpu_usage = 0.0;
+    let mut defunct = process(5001, "defunct-helper", "bob", "Zombie", 0.0, 0);
+    defunct.parent = Some(3001);
+    let nfs = process(5002, "rsync", "bob", "UninterruptibleDiskSleep", 0.0, 40);
+    processes.extend([defunct, nfs]);
+    processes
+}
+
+#[test]
+fn what_it_says() {
+    let source = FakeSource::default();
+    source.set_wchan(5002, "rpc_wait_bit_killable");
+    source.set_wchan(4100, "ptrace_stop");
+    let mut monitor = HungMonitor::new(HungConfig::default());
+    let mut said = Vec::new();
+    let mut processes = Vec::new();
+    for s in (0..=300).step_by(30) {
+        processes = stuck();
+        let mut wchan = source.clone();
+        said.extend(
+            monitor
+                .update(&mut processes, second(s), |pid| wchan.wchan(pid))
+                .iter()
+                .map(|hung| hung.to_string()),
+        );
+    }
+    assert_eq!(
+        said,
+        [
+            "hung: rsync (5002) in uninterruptible sleep for 30s, waiting in rpc_wait_bit_killable",
+            "hung: defunct-helper (5001) zombie for 1m00s, cargo (3001) isn't reaping it",
+            "hung: vim (4100) tracing with no cpu for 5m00s, waiting in ptrace_stop",
+        ]
+    );
+    let vim = processes.iter().find(|p| p.pid == 4100).unwrap();
+    assert_eq!(
+        vim.hung,
+        Some(Hang {
+            kind: HangKind::Stuck,
+            duration: Duration::from_secs(300),
+            wchan: Some("ptrace_stop".to_string()),
+        })
+    );
+    assert_eq!(
+        processes
+            .iter()
+            .filter(|p| p.hung.is_some())
+            .map(|p| p.pid)
+            .collect::<Vec<_>>(),
+        [4100, 5001, 5002]
+    );
+
+    // A new process with the same PID starts from scratch, and so does
+    // a replay jumping back.
+    let mut reused = stuck();
+    reused[9].start_time = START + 320;
+    monitor.update(&mut reused, second(330), |_| None);
+    assert_eq!(reused[9].hung, None);
+    let mut earlier = stuck();
+    monitor.update(&mut earlier, second(0), |_| None);
+    assert!(earlier.iter().all(|p| p.hung.is_none()));
+}
+
+#[test]
+fn hung_flag_wchan_and_sort() {
+    let mut processes = fixture();
+    let hang = |kind, secs, wchan: Option<&str>| {
+        Some(Hang {
+            kind,
+            duration: Duration::from_secs(secs),
+            wchan: wchan.map(str::to_string),
+        })
+    };
+    processes[1].hung = hang(HangKind::Blocked, 45, Some("nfs_wait_on_request"));
+    processes[7].hung = hang(HangKind::Stuck, 600, None);
+
+    let hung = filter::parse("hung").unwrap();
+    let selected = process::select(&processes, hung.as_ref(), SortMode::Hung, false);
+    let names: Vec<&str> = selected.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["vim", "sshd"]);
+    let nfs = filter::parse("wchan~nfs").unwrap().unwrap();
+    let matched: Vec<u32> = processes
+        .iter()
+        .filter(|p| nfs.matches(p))
+        .map(|p| p.pid)
+        .collect();
+    assert_eq!(matched, [240]);
+    let not = filter::parse("!hung && user=root").unwrap().unwrap();
+    assert!(not.matches(&processes[0]));
+    assert!(!not.matches(&processes[1]));
+    assert!(
+        filter::parse("hung=yes")
+            .unwrap_err()
+            .message
+            .contains("flag")
+    );
+
+    let mut all = processes.clone();
+    SortMode::Hung.sort(&mut all);
+    assert_eq!(all[0].name, "vim");
+    assert!(all[2..].iter().all(|p| p.hung.is_none()));
+}
+
+#[test]
+fn alerting_on_hangs() {
+    let config: Config = toml::from_str(
+        "[[alerts]]\nname = \"wedged\"\nhung = true\nprocess = \"rsync\"\nactions = [\"kill\"]",
+    )
+    .unwrap();
+    config.validate().unwrap();
+    let rules = procsnipe::alerts::load_rules(&config.alerts).unwrap();
+    let mut alerts = Alerts::new(rules);
+    let mut monitor = HungMonitor::new(HungConfig::default());
+    let mut processes = stuck();
+    monitor.update(&mut processes, second(0), |_| None);
+    let source = FakeSource::new(processes.clone());
+    assert!(
+        alerts
+            .check(&processes, second(0), &mut source.clone())
+            .is_empty()
+    );
+
+    let mut processes = stuck();
+    monitor.update(&mut processes, second(40), |_| {
+        Some("rpc_wait_bit_killable".to_string())
+    });
+    let fired = alerts.check(&processes, second(40), &mut source.clone());
+    let text: Vec<String> = fired.iter().map(|a| a.to_string()).collect();
+    assert_eq!(
+        text,
+        ["wedged: rsync (5002) blocked for 40s in rpc_wait_bit_killable, killed"]
+    );
+    assert!(
+        procsnipe::alerts::load_rules(&[procsnipe::alerts::AlertConfig {
+            name: "x".to_string(),
+            ..Default::default()
+        }])
+        .unwrap_err()
+        .to_string()
+        .contains("leak or hung")
+    );
+}
+
+/// Five minutes of `stuck`, a frame every thirty seconds.
+fn recording() -> Vec<Frame> {
+    (0..=10)
+        .map(|i| {
+            Frame::new(
+                second(i * 30),
+                Duration::from_millis(5),
+                Totals::default(),
+                stuck(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn hung_view_in_the_tui() {
+    // The source knows a wchan, but a replay never asks it.
+    let source = FakeSource::default();
+    source.set_wchan(5002, "rpc_wait_bit_killable");
+    let mut app = replaying(source, recording());
+    let key = |app: &mut App<FakeSource>, code: KeyCode| {
+        assert!(
+            !app.on_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+                .unwrap()
+        );
+        app.receive_snapshot();
+    };
+    key(&mut app, KeyCode::Char('l'));
+    let warning = glyphs::get().warning;
+    assert_eq!(
+        app.status_message().unwrap(),
+        format!("{warning} hung: rsync (5002) in uninterruptible sleep for 30s")
+    );
+    key(&mut app, KeyCode::Char('l'));
+    assert_eq!(
+        app.status_message().unwrap(),
+        format!(
+            "{warning} hung: defunct-helper (5001) zombie for 1m00s, cargo (3001) isn't reaping it"
+        )
+    );
+
+    for c in ":view hung".chars() {
+        key(&mut app, KeyCode::Char(c));
+    }
+    key(&mut app, KeyCode::Enter);
+    let mut terminal = Terminal::new(TestBackend::new(110, 12)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen = terminal.backend().to_string();
+    assert!(screen.contains("HUNG"), "{}", screen);
+    assert!(screen.contains("WCHAN"), "{}", screen);
+    assert!(screen.contains("zombie 1m00s"), "{}", screen);
+    assert!(screen.contains("blocked 1m00s"), "{}", screen);
+    assert!(!screen.contains("rpc_wait"), "{}", screen);
+    // Not stuck for five minutes yet.
+    assert!(!screen.contains("vim"), "{}", screen);
+}
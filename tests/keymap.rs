@@ -0,0 +1,280 @@
+//! Key bindings: parsing them from the config, sequences, counts and the
+//! sequence timeout.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::config::Config;
+use procsnipe::keymap::{Action, Chord, KeySeq, Keymap, Outcome, SEQUENCE_TIMEOUT};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+fn chord(code: KeyCode, modifiers: KeyModifiers) -> Chord {
+    Chord { code, modifiers }
+}
+
+fn plain(c: char) -> Chord {
+    chord(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+/// The defaults with `[keys]` entries applied.
+fn keymap(keys: &[(&str, &[&str])]) -> Keymap {
+    let keys: BTreeMap<String, Vec<String>> = keys
+        .iter()
+        .map(|(action, keys)| {
+            (
+                action.to_string(),
+                keys.iter().map(|k| k.to_string()).collect(),
+            )
+        })
+        .collect();
+    Keymap::from_config(&keys).unwrap()
+}
+
+/// Type each character of `keys`, returning what the last one did.
+fn press(keymap: &mut Keymap, keys: &str) -> Outcome {
+    let mut outcome = Outcome::Unbound;
+    for c in keys.chars() {
+        let event = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        outcome = keymap.feed(Chord::from_event(event));
+    }
+    outcome
+}
+
+/// The validation error for a `[keys]` table, if any.
+fn keys_error(keys: &str) -> Option<String> {
+    let config: Config = toml::from_str(&format!("[keys]\n{}", keys)).unwrap();
+    config.validate().err().map(|e| format!("{:#}", e))
+}
+
+#[test]
+fn misspelled_key_names_are_errors() {
+    for (keys, error) in [
+        (
+            "down = [\"pagdown\"]",
+            "unknown key 'pagdown' (did you mean 'pagedown'?)",
+        ),
+        (
+            "down = [\"backspase\"]",
+            "unknown key 'backspase' (did you mean 'backspace'?)",
+        ),
+        ("down = [\"Entr\"]", "did you mean 'enter'?"),
+        (
+            "down = [\"f13\"]",
+            "unknown key 'f13' (function keys go from f1 to f12)",
+        ),
+        ("down = [\"ctrl-pagdown\"]", "unknown key 'pagdown'"),
+    ] {
+        let err = keys_error(keys).unwrap_or_else(|| panic!("{} was accepted", keys));
+        assert!(err.contains(error), "{}: {}", keys, err);
+        assert!(err.contains("keys.down"), "{}", err);
+    }
+
+    // Real names and plain runs of characters are still fine.
+    for key in [
+        "pagedown",
+        "escape",
+        "f12",
+        "gg",
+        "zz",
+        "g?",
+        "jjj",
+        "ctrl-x ctrl-c",
+    ] {
+        assert!(KeySeq::parse(key).is_ok(), "{}", key);
+    }
+    assert_eq!(KeySeq::parse("gj").unwrap().0.len(), 2);
+    assert_eq!(keys_error("search = [\"zz\"]"), None);
+}
+
+#[test]
+fn parsing_bindings() {
+    let ctrl = KeyModifiers::CONTROL;
+    for (key, chords) in [
+        ("j", vec![plain('j')]),
+        ("G", vec![plain('G')]),
+        ("shift-g", vec![plain('G')]),
+        ("-", vec![plain('-')]),
+        ("+", vec![plain('+')]),
+        ("space", vec![plain(' ')]),
+        ("gg", vec![plain('g'), plain('g')]),
+        ("Enter", vec![chord(KeyCode::Enter, KeyModifiers::NONE)]),
+        ("esc", vec![chord(KeyCode::Esc, KeyModifiers::NONE)]),
+        ("pgdn", vec![chord(KeyCode::PageDown, KeyModifiers::NONE)]),
+        ("F5", vec![chord(KeyCode::F(5), KeyModifiers::NONE)]),
+        // Terminals send ctrl-d as a lower-case d.
+        ("ctrl-D", vec![chord(KeyCode::Char('d'), ctrl)]),
+        ("CTRL+x", vec![chord(KeyCode::Char('x'), ctrl)]),
+        (
+            "ctrl-alt-delete",
+            vec![chord(KeyCode::Delete, ctrl | KeyModifiers::ALT)],
+        ),
+        ("shift-tab", vec![chord(KeyCode::Tab, KeyModifiers::SHIFT)]),
+        (
+            "ctrl-x ctrl-c",
+            vec![
+                chord(KeyCode::Char('x'), ctrl),
+                chord(KeyCode::Char('c'), ctrl),
+            ],
+        ),
+        (
+            "g home",
+            vec![plain('g'), chord(KeyCode::Home, KeyModifiers::NONE)],
+        ),
+    ] {
+        assert_eq!(KeySeq::parse(key).unwrap().0, chords, "{}", key);
+    }
+
+    for (key, error) in [
+        ("", "empty key binding"),
+        ("   ", "empty key binding"),
+        ("ctrl-", "unknown key 'ctrl-'"),
+        ("ctrl-jj", "unknown key 'jj'"),
+        ("f0", "unknown key 'f0' (function keys go from f1 to f12)"),
+    ] {
+        let err = KeySeq::parse(key).unwrap_err().to_string();
+        assert_eq!(err, error, "{:?}", key);
+    }
+
+    // Shown the way the help screen lists them.
+    for (key, shown) in [
+        ("gg", "gg"),
+        ("ctrl-x ctrl-c", "ctrl-x ctrl-c"),
+        ("shift-g", "G"),
+        ("space", "space"),
+        ("g space", "g space"),
+        ("pagedown", "pgdn"),
+        ("alt+F4", "alt-f4"),
+    ] {
+        assert_eq!(KeySeq::parse(key).unwrap().to_string(), shown, "{}", key);
+    }
+}
+
+#[test]
+fn remapping_and_unbinding() {
+    let mut keys = keymap(&[("kill", &["dd", "delete"]), ("search", &[])]);
+    assert_eq!(keys.keys_for(Action::Kill), "dd/del");
+    // A custom list replaces the defaults rather than adding to them.
+    assert_eq!(press(&mut keys, "d"), Outcome::Pending);
+    assert_eq!(press(&mut keys, "d"), Outcome::Action(Action::Kill));
+    let delete = Chord::from_event(KeyEvent::new(KeyCode::Delete, KeyModifiers::NONE));
+    assert_eq!(keys.feed(delete), Outcome::Action(Action::Kill));
+    assert_eq!(press(&mut keys, "/"), Outcome::Unbound);
+    assert_eq!(keys.keys_for(Action::Search), "");
+
+    // The help screen follows the bindings.
+    let help = keys.help_entries();
+    assert!(
+        help.iter()
+            .any(|(k, d)| k == "dd/del" && d.contains("Kill")),
+        "{:?}",
+        help
+    );
+    assert!(
+        !help.iter().any(|(_, d)| d.contains("Search")),
+        "{:?}",
+        help
+    );
+
+    // Keys the user didn't touch keep their defaults, shift included.
+    assert_eq!(press(&mut keys, "j"), Outcome::Action(Action::Down));
+    let shifted = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+    assert_eq!(
+        keys.feed(Chord::from_event(shifted)),
+        Outcome::Action(Action::Bottom)
+    );
+
+    // Rebinding a default key elsewhere has to free it first.
+    assert_eq!(
+        keys_error("quit = [\"d\"]").unwrap(),
+        "'d' is bound to both kill and quit"
+    );
+    assert_eq!(keys_error("quit = [\"d\"]\nkill = [\"x\"]"), None);
+    assert!(
+        keys_error("nuke = [\"x\"]")
+            .unwrap()
+            .starts_with("unknown action 'nuke' in [keys]")
+    );
+}
+
+#[test]
+fn sequences() {
+    let mut keys = keymap(&[]);
+    // `g` alone toggles games, but `gg` goes to the top, so it waits.
+    assert_eq!(press(&mut keys, "g"), Outcome::Pending);
+    assert!(keys.is_pending());
+    assert_eq!(keys.pending_keys(), "g");
+    assert_eq!(press(&mut keys, "g"), Outcome::Action(Action::Top));
+    assert!(!keys.is_pending());
+
+    // A key that doesn't continue the sequence runs on its own.
+    assert_eq!(press(&mut keys, "gj"), Outcome::Action(Action::Down));
+    assert_eq!(press(&mut keys, "gx"), Outcome::Unbound);
+    assert!(!keys.is_pending());
+
+    // Switching modes drops what was typed.
+    press(&mut keys, "g");
+    keys.reset();
+    assert!(!keys.is_pending());
+    assert_eq!(keys.timeout(), None);
+
+    let mut keys = keymap(&[("quit", &["ctrl-x ctrl-c"])]);
+    let ctrl = |c| Chord::from_event(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+    assert_eq!(keys.feed(ctrl('x')), Outcome::Pending);
+    assert_eq!(keys.pending_keys(), "ctrl-x");
+    assert_eq!(keys.feed(ctrl('c')), Outcome::Action(Action::Quit));
+    assert_eq!(press(&mut keys, "q"), Outcome::Unbound);
+}
+
+#[test]
+fn counts() {
+    let mut keys = keymap(&[]);
+    assert_eq!(press(&mut keys, "10"), Outcome::Pending);
+    assert_eq!(keys.pending_keys(), "10");
+    assert_eq!(press(&mut keys, "j"), Outcome::Action(Action::Down));
+    assert_eq!(keys.take_count(), Some(10));
+    assert_eq!(keys.take_count(), None, "taken once");
+
+    // Counts go in front of sequences too.
+    assert_eq!(press(&mut keys, "3g"), Outcome::Pending);
+    assert_eq!(keys.pending_keys(), "3g");
+    assert_eq!(press(&mut keys, "g"), Outcome::Action(Action::Top));
+    assert_eq!(keys.take_count(), Some(3));
+
+    // Only motions take one; anything else just drops it.
+    assert_eq!(press(&mut keys, "5d"), Outcome::Action(Action::Kill));
+    assert_eq!(keys.take_count(), None);
+    assert_eq!(press(&mut keys, "5x"), Outcome::Unbound);
+    assert!(!keys.is_pending());
+    assert_eq!(press(&mut keys, "j"), Outcome::Action(Action::Down));
+    assert_eq!(keys.take_count(), None);
+
+    // A leading 0 isn't a count.
+    assert_eq!(press(&mut keys, "0"), Outcome::Unbound);
+    assert!(!keys.is_pending());
+}
+
+#[test]
+fn timeouts() {
+    // `g` waiting to see if it's `gg`, a lone view key that could be a
+    // count, and a count that never got its motion.
+    let mut games = keymap(&[]);
+    let mut view = keymap(&[]);
+    let mut count = keymap(&[]);
+    press(&mut games, "g");
+    press(&mut view, "2");
+    press(&mut count, "12");
+    assert_eq!(games.timeout(), None, "too early");
+    assert!(games.is_pending());
+
+    std::thread::sleep(SEQUENCE_TIMEOUT + Duration::from_millis(20));
+    assert_eq!(games.timeout(), Some(Action::ToggleGames));
+    assert_eq!(view.timeout(), Some(Action::View(2)));
+    assert_eq!(count.timeout(), None);
+    for keys in [&mut games, &mut view, &mut count] {
+        assert!(!keys.is_pending());
+        assert_eq!(keys.take_count(), None);
+        assert_eq!(keys.timeout(), None, "fires once");
+    }
+    // The next key starts fresh.
+    assert_eq!(press(&mut count, "j"), Outcome::Action(Action::Down));
+    assert_eq!(count.take_count(), None);
+}
@@ -0,0 +1,207 @@
+//! Target matching, tree ordering and exit codes of `procsnipe kill`.
+
+mod common;
+
+use common::process;
+use procsnipe::kill::{self, Outcome, Selector, Target};
+use procsnipe::{FakeSource, ProcessInfo, Signal, filter};
+use std::time::Duration;
+
+const NOW: u64 = 1_000_000;
+
+/// A small tree: shell 10 -> make 11 -> cc 12 and cc 13; game 20 alone.
+fn tree() -> Vec<ProcessInfo> {
+    let child = |pid, name: &str, parent, age_secs: u64| ProcessInfo {
+        parent: Some(parent),
+        start_time: NOW - age_secs,
+        ..process(pid, name, "bob", "Running", 1.0, 10)
+    };
+    vec![
+        child(10, "bash", 1, 7200),
+        child(11, "make", 10, 600),
+        child(12, "cc", 11, 5),
+        child(13, "cc", 11, 5),
+        ProcessInfo {
+            user: "alice".to_string(),
+            ..child(20, "Game.exe", 1, 60)
+        },
+    ]
+}
+
+fn pids(selector: &Selector) -> Vec<u32> {
+    selector
+        .select(&tree(), NOW)
+        .iter()
+        .map(|p| p.pid)
+        .collect()
+}
+
+fn targets(names: &[&str]) -> Selector {
+    Selector {
+        targets: names.iter().map(|t| Target::parse(t)).collect(),
+        ..Selector::default()
+    }
+}
+
+#[test]
+fn pids_and_names() {
+    assert_eq!(pids(&targets(&["12"])), [12]);
+    assert_eq!(pids(&targets(&["cc"])), [12, 13]);
+    // Case and `.exe` don't matter.
+    assert_eq!(pids(&targets(&["game"])), [20]);
+    assert_eq!(pids(&targets(&["GAME.EXE"])), [20]);
+    assert_eq!(pids(&targets(&["ma*", "?ash"])), [10, 11]);
+    assert!(pids(&targets(&["c"])).is_empty());
+}
+
+#[test]
+fn regex_and_filter() {
+    let selector = Selector {
+        targets: vec![Target::regex("^(make|cc)$").unwrap()],
+        ..Selector::default()
+    };
+    assert_eq!(pids(&selector), [11, 12, 13]);
+    assert!(Target::regex("(").is_err());
+
+    let selector = Selector {
+        filter: filter::parse("user=alice").unwrap(),
+        ..Selector::default()
+    };
+    assert_eq!(pids(&selector), [20]);
+}
+
+#[test]
+fn user_and_age_narrow_targets() {
+    let selector = Selector {
+        user: Some("BOB".to_string()),
+        older_than: Some(Duration::from_secs(300)),
+        ..targets(&["*"])
+    };
+    assert_eq!(pids(&selector), [10, 11]);
+}
+
+#[test]
+fn tree_kills_children_first() {
+    let selector = Selector {
+        tree: true,
+        ..targets(&["bash"])
+    };
+    assert_eq!(pids(&selector), [12, 13, 11, 10]);
+
+    // A descendant that matched on its own isn't listed twice.
+    let selector = Selector {
+        tree: true,
+        ..targets(&["make", "cc"])
+    };
+    assert_eq!(pids(&selector), [12, 13, 11]);
+}
+
+#[test]
+fn spares_itself_and_its_ancestors() {
+    // Running as cc 12, started from make 11 in bash 10.
+    let everything = Selector {
+        own_pid: Some(12),
+        ..Selector::default()
+    };
+    assert_eq!(pids(&everything), [13, 20]);
+    let selector = Selector {
+        own_pid: Some(12),
+        ..targets(&["*"])
+    };
+    assert_eq!(pids(&selector), [13, 20]);
+
+    // Not even by asking for the shell's tree, which would take us along.
+    let selector = Selector {
+        tree: true,
+        own_pid: Some(12),
+        ..targets(&["bash", "game"])
+    };
+    assert_eq!(pids(&selector), [20]);
+
+    // Gone from the snapshot already: nothing above it is known.
+    let selector = Selector {
+        own_pid: Some(99),
+        ..Selector::default()
+    };
+    assert_eq!(pids(&selector), [10, 11, 12, 13, 20]);
+}
+
+#[test]
+fn exit_codes() {
+    let mut processes = tree();
+    processes[0].is_protected = true;
+    let source = FakeSource::new(processes.clone());
+    source.deny(11);
+
+    let report = kill::kill_all(&mut source.clone(), &processes[2..4], None);
+    assert_eq!(report.exit_code(), kill::EXIT_KILLED_ALL);
+    assert_eq!(source.signals(), [(12, Signal::Kill), (13, Signal::Kill)]);
+
+    // 12 is gone now, which is fine for a kill.
+    let report = kill::kill_all(&mut source.clone(), &processes[2..3], None);
+    assert_eq!(report.results[0].1, Outcome::Exited);
+    assert_eq!(report.exit_code(), kill::EXIT_KILLED_ALL);
+
+    let report = kill::kill_all(&mut source.clone(), &processes[..2], Some(Signal::Term));
+    assert_eq!(report.results[0].1, Outcome::Protected);
+    assert_eq!(report.results[1].1, Outcome::Denied);
+    assert_eq!(report.exit_code(), kill::EXIT_PERMISSION_DENIED);
+
+    let report = kill::kill_all(&mut source.clone(), &processes[..], Some(Signal::Term));
+    assert_eq!(report.exit_code(), kill::EXIT_PARTIAL);
+    assert!(
+        source
+            .processes()
+            .iter()
+            .all(|p| p.pid == 10 || p.pid == 11)
+    );
+
+    let report = kill::kill_all(&mut source.clone(), &[], None);
+    assert_eq!(report.exit_code(), kill::EXIT_NOTHING_MATCHED);
+}
+
+#[test]
+fn stopping_a_vanished_process_fails() {
+    let source = FakeSource::new(Vec::new());
+    let report = kill::kill_all(&mut source.clone(), &tree()[..1], Some(Signal::Stop));
+    assert!(matches!(report.results[0].1, Outcome::Failed(_)));
+    assert_eq!(report.exit_code(), kill::EXIT_ERROR);
+}
+
+#[test]
+fn dry_run_exits_like_the_real_thing() {
+    let mut processes = tree();
+    for p in &mut processes {
+        p.is_protected = true;
+    }
+    // All protected: a real run would be refused, so the plan says so too.
+    let plan = kill::plan(&processes);
+    assert!(plan.results.iter().all(|(_, o)| *o == Outcome::Protected));
+    assert_eq!(plan.exit_code(), kill::EXIT_PERMISSION_DENIED);
+    let real = kill::kill_all(&mut FakeSource::new(processes.clone()), &processes, None);
+    assert_eq!(real.exit_code(), plan.exit_code());
+
+    processes[1].is_protected = false;
+    assert_eq!(kill::plan(&processes).exit_code(), kill::EXIT_PARTIAL);
+    assert_eq!(
+        kill::plan(&processes[1..2]).exit_code(),
+        kill::EXIT_KILLED_ALL
+    );
+    assert_eq!(kill::plan(&[]).exit_code(), kill::EXIT_NOTHING_MATCHED);
+}
+
+#[test]
+fn ages_default_to_seconds() {
+    assert_eq!(kill::parse_age("30").unwrap(), Duration::from_secs(30));
+    assert_eq!(
+        kill::parse_age(" 1.5 ").unwrap(),
+        Duration::from_millis(1500)
+    );
+    assert_eq!(kill::parse_age("10m").unwrap(), Duration::from_secs(600));
+    assert_eq!(
+        kill::parse_age("500ms").unwrap(),
+        Duration::from_millis(500)
+    );
+    assert!(kill::parse_age("-5").is_err());
+    assert!(kill::parse_age("old").is_err());
+}
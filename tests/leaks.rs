@@ -0,0 +1,211 @@
+//! The leak monitor: which growth counts, the `leak` flag, column and view,
+//! and alerts on it.
+
+mod common;
+
+use common::{fixture, process, replaying};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::alerts::{Alerts, Rule};
+use procsnipe::config::Config;
+use procsnipe::leaks::{LeakConfig, LeakMonitor};
+use procsnipe::record::Frame;
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, ProcessInfo, SortMode, Totals, filter, glyphs, process};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MB: u64 = 1024 * 1024;
+const START: u64 = 1_700_000_000;
+
+fn minute(m: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(START + m * 60)
+}
+
+/// Feed `memory(minute)` in MB for `minutes` minutes, one sample a minute,
+/// and return the minutes at which it got flagged as new.
+fn flagged_at(config: LeakConfig, minutes: u64, memory: impl Fn(u64) -> f64) -> Vec<u64> {
+    let mut monitor = LeakMonitor::new(config);
+    (0..=minutes)
+        .filter(|&m| {
+            let mut p = process(500, "server", "game", "Sleeping", 1.0, 0);
+            p.memory = (memory(m) * MB as f64) as u64;
+            !monitor.update(&mut [p], minute(m)).is_empty()
+        })
+        .collect()
+}
+
+#[test]
+fn steady_growth_over_the_window() {
+    let config = LeakConfig::default;
+    // 2 MB a minute from 100 MB: flagged once the ten minutes are covered,
+    // and only reported once.
+    assert_eq!(flagged_at(config(), 30, |m| 100.0 + 2.0 * m as f64), [10]);
+    // Too slow.
+    assert!(flagged_at(config(), 30, |m| 100.0 + 0.5 * m as f64).is_empty());
+    // Flat.
+    assert!(flagged_at(config(), 30, |_| 100.0).is_empty());
+    // Big, but it gives it back: a sawtooth isn't a leak.
+    assert!(flagged_at(config(), 30, |m| 100.0 + 20.0 * (m % 3) as f64).is_empty());
+    // The odd dip along the way doesn't hide a leak.
+    let dips = |m: u64| 100.0 + 3.0 * m as f64 - if m.is_multiple_of(7) { 4.0 } else { 0.0 };
+    assert_eq!(flagged_at(config(), 30, dips), [10]);
+    // Grew for a while then levelled off: flagged, then not, and reported
+    // again once it's been growing for half a window.
+    let steps = |m: u64| match m {
+        0..15 => 100.0 + 5.0 * m as f64,
+        15..40 => 175.0,
+        _ => 175.0 + 5.0 * (m - 40) as f64,
+    };
+    assert_eq!(flagged_at(config(), 60, steps), [10, 46]);
+
+    let custom = LeakConfig {
+        mb_per_min: 0.25,
+        window_mins: 5,
+        ..LeakConfig::default()
+    };
+    assert_eq!(flagged_at(custom, 30, |m| 100.0 + 0.5 * m as f64), [5]);
+    let off = LeakConfig {
+        enabled: false,
+        ..LeakConfig::default()
+    };
+    assert!(flagged_at(off, 30, |m| 100.0 + 2.0 * m as f64).is_empty());
+}
+
+#[test]
+fn rate_and_message() {
+    let mut monitor = LeakMonitor::new(LeakConfig::default());
+    let mut leaks = Vec::new();
+    let mut processes = Vec::new();
+    for m in 0..=10 {
+        processes = fixture();
+        processes[2].memory += m * 3 * MB;
+        leaks = monitor.update(&mut processes, minute(m));
+    }
+    assert_eq!(leaks.len(), 1);
+    assert_eq!(
+        leaks[0].to_string(),
+        "leak: firefox (1012) growing 3.0 MB/min for 10m, now 880 MB"
+    );
+    let flagged: Vec<(u32, Option<f32>)> = processes
+        .iter()
+        .filter(|p| p.leak.is_some())
+        .map(|p| (p.pid, p.leak))
+        .collect();
+    assert_eq!(flagged, [(1012, Some(3.0))]);
+
+    // A new process with the same PID starts from scratch.
+    let mut reused = processes.clone();
+    reused[2].start_time = START + 700;
+    assert!(monitor.update(&mut reused, minute(11)).is_empty());
+    assert_eq!(reused[2].leak, None);
+}
+
+#[test]
+fn leak_flag_and_sort() {
+    let mut processes = fixture();
+    processes[4].leak = Some(0.5);
+    processes[6].leak = Some(4.0);
+    let leaking = filter::parse("leak").unwrap();
+    let selected = process::select(&processes, leaking.as_ref(), SortMode::Leak, false);
+    let names: Vec<&str> = selected.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["rust-analyzer", "cs2"]);
+    let not = filter::parse("!leak && cpu>50").unwrap().unwrap();
+    assert!(!not.matches(&processes[4]));
+    assert!(
+        filter::parse("leak>1")
+            .unwrap_err()
+            .message
+            .contains("flag")
+    );
+
+    let mut all = processes.clone();
+    SortMode::Leak.sort(&mut all);
+    assert_eq!(all[0].name, "rust-analyzer");
+    assert!(all[2..].iter().all(|p| p.leak.is_none()));
+}
+
+#[test]
+fn alerting_on_leaks() {
+    let config: Config =
+        toml::from_str("[[alerts]]\nname = \"leaky\"\nleak = true\nactions = [\"kill\"]").unwrap();
+    config.validate().unwrap();
+    let rules = procsnipe::alerts::load_rules(&config.alerts).unwrap();
+    let mut alerts = Alerts::new(rules);
+    let mut processes = fixture();
+    processes[6].leak = Some(4.0);
+    let source = FakeSource::new(processes.clone());
+    let fired = alerts.check(&processes, minute(0), &mut source.clone());
+    let text: Vec<String> = fired.iter().map(|a| a.to_string()).collect();
+    assert_eq!(
+        text,
+        ["leaky: rust-analyzer (3002) leaking 4.0 MB/min, killed"]
+    );
+
+    // The tray's fallback rule still works without any [[alerts]].
+    let mut fallback = Alerts::new(vec![Rule::cpu_above(50.0, Duration::from_secs(60))]);
+    let fired = fallback.check(&fixture(), minute(0), &mut FakeSource::default());
+    assert_eq!(fired[0].to_string(), "high cpu: cs2 (2077) cpu 72.0%");
+}
+
+/// Twelve minutes of the fixture, a frame a minute, with steam leaking
+/// 2 MB a minute.
+fn recording() -> Vec<Frame> {
+    (0..=12)
+        .map(|m| {
+            let mut processes: Vec<ProcessInfo> = fixture();
+            processes[3].memory += m * 2 * MB;
+            Frame::new(
+                minute(m),
+                Duration::from_millis(5),
+                Totals::default(),
+                processes,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn leaks_view_in_the_tui() {
+    let mut app = replaying(FakeSource::default(), recording());
+    let mut key = |c: char| {
+        let event = Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        assert!(!app.on_event(event).unwrap());
+        app.receive_snapshot();
+    };
+    // One frame at a time, up to the one it's first flagged on: jumping
+    // ahead skips the history in between.
+    for _ in 0..10 {
+        key('l');
+    }
+    let warning = glyphs::get().warning;
+    assert_eq!(
+        app.status_message().unwrap(),
+        format!("{warning} leak: steam (1013) growing 2.0 MB/min for 10m, now 320 MB")
+    );
+
+    app.on_event(Event::Key(KeyEvent::new(
+        KeyCode::Char(':'),
+        KeyModifiers::NONE,
+    )))
+    .unwrap();
+    for c in "view leaks".chars() {
+        app.on_event(Event::Key(KeyEvent::new(
+            KeyCode::Char(c),
+            KeyModifiers::NONE,
+        )))
+        .unwrap();
+    }
+    app.on_event(Event::Key(KeyEvent::new(
+        KeyCode::Enter,
+        KeyModifiers::NONE,
+    )))
+    .unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen = terminal.backend().to_string();
+    assert!(screen.contains("LEAK"), "{}", screen);
+    assert!(screen.contains("steam"), "{}", screen);
+    assert!(screen.contains("+2.0/min"), "{}", screen);
+    assert!(!screen.contains("firefox"), "{}", screen);
+}
@@ -0,0 +1,65 @@
+//! `procsnipe list` formats and the filter/sort pipeline behind them.
+
+mod common;
+
+use common::fixture;
+use insta::assert_snapshot;
+use procsnipe::output::{self, Format};
+use procsnipe::view::Column;
+use procsnipe::{SortMode, filter, glyphs, process};
+
+fn render(format: Format, query: &str, sort: SortMode, reversed: bool) -> String {
+    glyphs::set_ascii(true);
+    let filter = filter::parse(query).unwrap();
+    let processes = process::select(&fixture(), filter.as_ref(), sort, reversed);
+    let mut out = Vec::new();
+    output::write(&mut out, format, &processes, &Column::DEFAULT).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn table() {
+    assert_snapshot!(render(Format::Table, "cpu>2", SortMode::Cpu, false), @r"
+    PID      NAME                           USER            CPU%        MEM
+    2077     cs2                            carol          72.0%    2100 MB
+    1012     firefox                        alice          35.5%     850 MB
+    3001     cargo                          bob            18.3%     400 MB
+    1013     steam                          alice           4.2%     300 MB
+    3002     rust-analyzer                  bob             2.5%    1200 MB
+    ");
+}
+
+#[test]
+fn csv() {
+    assert_snapshot!(render(Format::Csv, "user=bob", SortMode::Memory, true), @r"
+    pid,name,user,status,cpu,memory_bytes,game,protected,parent,started,threads,io_bytes
+    3001,cargo,bob,Running,18.3,419430400,false,false,,0,0,0
+    3002,rust-analyzer,bob,Sleeping,2.5,1258291200,false,false,,0,0,0
+    ");
+}
+
+#[test]
+fn ndjson() {
+    assert_snapshot!(render(Format::Ndjson, "name~ss", SortMode::Pid, false), @r#"
+    {"pid":240,"name":"sshd","user":"root","status":"Idle","cpu":0.0,"memory_bytes":8388608,"game":false,"protected":false,"parent":null,"started":0,"threads":0,"io_bytes":0}
+    "#);
+}
+
+#[test]
+fn json_is_an_array() {
+    let json = render(Format::Json, "pid<1000", SortMode::Pid, false);
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let pids: Vec<u64> = parsed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["pid"].as_u64().unwrap())
+        .collect();
+    assert_eq!(pids, [1, 240]);
+}
+
+#[test]
+fn format_names() {
+    assert_eq!("NDJSON".parse::<Format>().unwrap(), Format::Ndjson);
+    assert!("xml".parse::<Format>().is_err());
+}
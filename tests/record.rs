@@ -0,0 +1,142 @@
+//! Recording snapshots to disk and rotating the files.
+
+mod common;
+
+use common::{fixture, scratch};
+use procsnipe::Totals;
+use procsnipe::record::{self, Frame, Recorder, Rotation, parse_size, rotated};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn frame(second: u64) -> Frame {
+    Frame::new(
+        UNIX_EPOCH + Duration::from_secs(second),
+        Duration::from_millis(3),
+        Totals {
+            cpu: 12.5,
+            memory_used: 1024,
+            memory_total: 4096,
+        },
+        fixture(),
+    )
+}
+
+fn lines(path: &PathBuf) -> Vec<Value> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+#[test]
+fn frames_are_appended_one_per_line() {
+    let dir = scratch("append");
+    let path = dir.join("rec.ndjson");
+    let mut recorder = Recorder::open(&path, Rotation::default()).unwrap();
+    recorder.record(&frame(100)).unwrap();
+    recorder.record(&frame(101)).unwrap();
+    drop(recorder);
+
+    // Reopening keeps what's there.
+    let mut recorder = Recorder::open(&path, Rotation::default()).unwrap();
+    recorder.record(&frame(102)).unwrap();
+
+    let frames = lines(&path);
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0]["time_ms"], 100_000);
+    assert_eq!(frames[2]["time_ms"], 102_000);
+    assert_eq!(frames[0]["took_ms"], 3);
+    assert_eq!(frames[0]["totals"]["memory_total"], 4096);
+    let processes = frames[0]["processes"].as_array().unwrap();
+    assert_eq!(processes.len(), fixture().len());
+    assert_eq!(processes[0]["name"], "systemd");
+}
+
+#[test]
+fn rotates_by_size_and_keeps_the_newest() {
+    let dir = scratch("size");
+    let path = dir.join("rec.ndjson");
+    let line = serde_json::to_vec(&frame(1000)).unwrap().len() as u64 + 1;
+    let rotation = Rotation {
+        // Two frames per file.
+        max_size: Some(line * 2),
+        max_age: None,
+        keep: 2,
+    };
+    let mut recorder = Recorder::open(&path, rotation).unwrap();
+    for second in 1000..1007 {
+        recorder.record(&frame(second)).unwrap();
+    }
+
+    let first = |p: &PathBuf| lines(p)[0]["time_ms"].as_u64().unwrap() / 1000;
+    assert_eq!(lines(&path).len(), 1);
+    assert_eq!(first(&path), 1006);
+    assert_eq!(first(&rotated(&path, 1)), 1004);
+    assert_eq!(first(&rotated(&path, 2)), 1002);
+    assert!(!rotated(&path, 3).exists());
+}
+
+#[test]
+fn rotates_by_age() {
+    let dir = scratch("age");
+    let path = dir.join("rec.ndjson");
+    let rotation = Rotation {
+        max_size: None,
+        max_age: Some(Duration::ZERO),
+        keep: 1,
+    };
+    let mut recorder = Recorder::open(&path, rotation).unwrap();
+    for second in 0..3 {
+        recorder.record(&frame(second)).unwrap();
+    }
+    assert_eq!(lines(&path).len(), 1);
+    assert_eq!(lines(&rotated(&path, 1)).len(), 1);
+    assert!(!rotated(&path, 2).exists());
+}
+
+#[test]
+fn reading_skips_a_cut_off_last_line() {
+    let dir = scratch("read");
+    let path = dir.join("rec.ndjson");
+    let mut recorder = Recorder::open(&path, Rotation::default()).unwrap();
+    recorder.record(&frame(100)).unwrap();
+    recorder.record(&frame(101)).unwrap();
+    let mut text = fs::read_to_string(&path).unwrap();
+    text.push_str(r#"{"time_ms":102000,"took_ms":3,"proc"#);
+    fs::write(&path, &text).unwrap();
+
+    let frames = record::read(&path).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].time_ms, 101_000);
+    assert_eq!(frames[1].processes, fixture());
+
+    fs::write(&path, format!("garbage\n{}", text)).unwrap();
+    let err = record::read(&path).unwrap_err().to_string();
+    assert!(
+        err.contains("rec.ndjson:1: not a recorded snapshot"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn sizes() {
+    assert_eq!(parse_size("100MB").unwrap(), 100 * 1024 * 1024);
+    assert_eq!(parse_size("1.5g").unwrap(), 1536 * 1024 * 1024);
+    assert_eq!(parse_size("512k").unwrap(), 512 * 1024);
+    assert_eq!(parse_size("7").unwrap(), 7 * 1024 * 1024);
+    assert!(parse_size("0").is_err());
+    assert!(parse_size("lots").is_err());
+    assert!(parse_size("1PB").is_err());
+}
+
+#[test]
+fn frame_time_is_unix_millis() {
+    let now = SystemTime::now();
+    let frame = Frame::new(now, Duration::ZERO, Totals::default(), Vec::new());
+    let expected = now.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    assert_eq!(frame.time_ms, expected);
+}
@@ -0,0 +1,166 @@
+//! Playing recordings back: the player on its own, and through the TUI.
+
+mod common;
+
+use common::{START_MS, fixture, recording, replaying};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::FakeSource;
+use procsnipe::app::App;
+use procsnipe::command::{Seek, parse_seek};
+use procsnipe::replay::{Player, clock};
+use procsnipe::ui::ui;
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::time::{Duration, Instant};
+
+#[test]
+fn stepping_and_scrubbing() {
+    let mut player = Player::new(recording()).unwrap();
+    assert_eq!(player.position(), 0);
+    assert!(player.latest().is_some());
+    assert!(player.latest().is_none(), "nothing new until it moves");
+
+    player.step(1);
+    assert_eq!(player.position(), 1);
+    assert_eq!(player.latest().unwrap().processes.len(), fixture().len());
+    player.step(-5);
+    assert_eq!(player.position(), 0);
+    player.step(100);
+    assert_eq!(player.position(), 3);
+    assert_eq!(player.progress(), 1.0);
+
+    player.scrub(0.5);
+    assert_eq!(player.position(), 1);
+    player.scrub(0.7);
+    assert_eq!(player.position(), 2);
+    assert_eq!(
+        player.latest().unwrap().processes.len(),
+        fixture().len() - 1
+    );
+}
+
+#[test]
+fn seeking_by_percent_and_time() {
+    let mut player = Player::new(recording()).unwrap();
+    player.seek(Seek::Percent(100.0)).unwrap();
+    assert_eq!(player.position(), 3);
+
+    let third = clock(START_MS + 2000);
+    player.seek(parse_seek(&third).unwrap()).unwrap();
+    assert_eq!(player.position(), 2);
+
+    let after = clock(START_MS + 3 * 3600 * 1000);
+    assert!(player.seek(parse_seek(&after).unwrap()).is_err());
+}
+
+#[test]
+fn playing_follows_the_recorded_pace() {
+    let mut player = Player::new(recording()).unwrap();
+    while player.change_speed(true) < 64.0 {}
+    player.toggle();
+    assert!(player.is_playing());
+
+    // Three seconds of recording at x64 is about 50ms.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while player.is_playing() {
+        assert!(Instant::now() < deadline, "playback never finished");
+        player.latest();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(player.position(), 3);
+
+    // Playing again from the end starts over.
+    player.toggle();
+    assert_eq!(player.position(), 0);
+}
+
+#[test]
+fn empty_recording_is_an_error() {
+    assert!(Player::new(Vec::new()).is_err());
+}
+
+struct Replay {
+    app: App<FakeSource>,
+    source: FakeSource,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Replay {
+    fn new() -> Self {
+        // The live source has one process the recording doesn't; it must
+        // never show up.
+        let source = FakeSource::new(vec![common::process(9999, "live", "root", "Run", 1.0, 1)]);
+        Self {
+            app: replaying(source.clone(), recording()),
+            source,
+            terminal: Terminal::new(TestBackend::new(100, 16)).unwrap(),
+        }
+    }
+
+    fn keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            self.key(KeyCode::Char(c));
+        }
+    }
+
+    fn key(&mut self, code: KeyCode) {
+        let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        assert!(!self.app.on_event(event).unwrap());
+        self.app.receive_snapshot();
+    }
+
+    fn render(&mut self) -> String {
+        self.terminal.draw(|f| ui(f, &mut self.app)).unwrap();
+        self.terminal.backend().to_string()
+    }
+}
+
+#[test]
+fn app_shows_the_recording() {
+    let mut r = Replay::new();
+    assert_eq!(r.app.processes().len(), fixture().len());
+    let screen = r.render();
+    assert!(screen.contains("REPLAY || x1"), "{}", screen);
+    assert!(screen.contains(&clock(START_MS)), "{}", screen);
+    assert!(screen.contains(" 1/4 "), "{}", screen);
+    assert!(!screen.contains("live"));
+
+    r.keys("2l");
+    assert_eq!(r.app.player().unwrap().position(), 2);
+    assert!(!r.app.processes().iter().any(|p| p.name == "firefox"));
+    let screen = r.render();
+    assert!(screen.contains(" 3/4 "), "{}", screen);
+    assert!(screen.contains(&clock(START_MS + 2000)), "{}", screen);
+
+    r.key(KeyCode::Left);
+    assert_eq!(r.app.player().unwrap().position(), 1);
+    r.keys("[");
+    assert_eq!(r.app.player().unwrap().position(), 0);
+    r.keys("+");
+    assert_eq!(r.app.player().unwrap().speed(), 2.0);
+}
+
+#[test]
+fn seek_command() {
+    let mut r = Replay::new();
+    r.keys(":seek 100%");
+    r.key(KeyCode::Enter);
+    assert_eq!(r.app.player().unwrap().position(), 3);
+    r.keys(&format!(":seek {}", clock(START_MS + 1000)));
+    r.key(KeyCode::Enter);
+    assert_eq!(r.app.player().unwrap().position(), 1);
+}
+
+#[test]
+fn nothing_can_be_killed() {
+    let mut r = Replay::new();
+    r.keys("d");
+    assert!(r.app.status_message().unwrap().contains("read-only"));
+    r.keys(":kill cs2");
+    r.key(KeyCode::Enter);
+    r.keys(":renice 5");
+    r.key(KeyCode::Enter);
+    assert!(r.source.signals().is_empty());
+    assert_eq!(r.source.nice(2077), None);
+    assert!(r.app.status_message().unwrap().contains("read-only"));
+}
@@ -0,0 +1,216 @@
+//! Themes: the built-ins, per-slot overrides, fitting colors to what the
+//! terminal can show, and the colors actually drawn.
+
+mod common;
+
+use common::fixture;
+use procsnipe::app::App;
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::Keymap;
+use procsnipe::theme::{BUILTIN_THEMES, ColorDepth, Theme, ThemeConfig};
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, glyphs, view};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use ratatui::style::Color;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+fn theme(name: &str, colors: &str, overrides: &[(&str, &str)]) -> anyhow::Result<Theme> {
+    ThemeConfig {
+        name: name.to_string(),
+        colors: colors.to_string(),
+        overrides: overrides
+            .iter()
+            .map(|(slot, color)| (slot.to_string(), color.to_string()))
+            .collect::<BTreeMap<_, _>>(),
+    }
+    .resolve()
+}
+
+/// Every slot, for checking a whole theme at once.
+fn slots(t: &Theme) -> [Color; 14] {
+    [
+        t.accent,
+        t.muted,
+        t.info,
+        t.highlight,
+        t.secondary,
+        t.normal,
+        t.game,
+        t.cpu_high,
+        t.cpu_medium,
+        t.protected,
+        t.suspended,
+        t.selected_bg,
+        t.selected_fg,
+        t.error,
+    ]
+}
+
+#[test]
+fn builtins_and_aliases() {
+    let themes: Vec<Theme> = BUILTIN_THEMES
+        .iter()
+        .map(|name| Theme::builtin(name).unwrap())
+        .collect();
+    for (i, a) in themes.iter().enumerate() {
+        for b in &themes[i + 1..] {
+            assert_ne!(a, b, "two built-ins look the same");
+        }
+    }
+    assert_eq!(Theme::default(), Theme::dark());
+    assert_eq!(
+        Theme::builtin("high_contrast"),
+        Theme::builtin("high-contrast")
+    );
+    assert_eq!(
+        Theme::builtin("colorblind-safe"),
+        Theme::builtin("colorblind")
+    );
+    assert_eq!(Theme::builtin("Dark"), None);
+    for name in BUILTIN_THEMES {
+        assert_eq!(
+            theme(name, "truecolor", &[]).unwrap(),
+            Theme::builtin(name).unwrap()
+        );
+    }
+}
+
+#[test]
+fn overrides() {
+    let t = theme(
+        "dark",
+        "truecolor",
+        &[
+            ("accent", "#ff8800"),
+            ("game", "lightblue"),
+            ("error", "196"),
+        ],
+    )
+    .unwrap();
+    assert_eq!(t.accent, Color::Rgb(0xff, 0x88, 0x00));
+    assert_eq!(t.game, Color::LightBlue);
+    assert_eq!(t.error, Color::Indexed(196));
+    // The rest is left alone.
+    assert_eq!(t.info, Theme::dark().info);
+
+    let err = theme("dark", "auto", &[("accent", "blurple")]).unwrap_err();
+    assert!(
+        err.to_string().contains("theme.accent = \"blurple\""),
+        "{}",
+        err
+    );
+    let err = theme("dark", "auto", &[("sparkle", "red")]).unwrap_err();
+    assert!(
+        err.to_string().starts_with("unknown theme color 'sparkle'"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn fitting_to_the_terminal() {
+    let fit = |depth: ColorDepth, color: Color| {
+        let mut t = Theme::dark();
+        t.accent = color;
+        t.downgrade(depth).accent
+    };
+    assert_eq!(
+        fit(ColorDepth::TrueColor, Color::Rgb(1, 2, 3)),
+        Color::Rgb(1, 2, 3)
+    );
+    // The 6x6x6 cube, or the gray ramp when that's closer.
+    assert_eq!(
+        fit(ColorDepth::Ansi256, Color::Rgb(255, 0, 0)),
+        Color::Indexed(196)
+    );
+    assert_eq!(
+        fit(ColorDepth::Ansi256, Color::Rgb(0, 0, 0)),
+        Color::Indexed(16)
+    );
+    assert_eq!(
+        fit(ColorDepth::Ansi256, Color::Rgb(128, 128, 128)),
+        Color::Indexed(244)
+    );
+    assert_eq!(
+        fit(ColorDepth::Ansi256, Color::Indexed(33)),
+        Color::Indexed(33)
+    );
+    assert_eq!(
+        fit(ColorDepth::Ansi16, Color::Rgb(250, 5, 5)),
+        Color::LightRed
+    );
+    assert_eq!(fit(ColorDepth::Ansi16, Color::Indexed(21)), Color::Blue);
+    assert_eq!(fit(ColorDepth::Ansi16, Color::Indexed(250)), Color::Gray);
+    // Named colors already fit everywhere.
+    assert_eq!(fit(ColorDepth::Ansi16, Color::Cyan), Color::Cyan);
+    assert_eq!(fit(ColorDepth::Ansi16, Color::Reset), Color::Reset);
+
+    for name in BUILTIN_THEMES {
+        let basic = theme(name, "16", &[]).unwrap();
+        assert!(
+            slots(&basic)
+                .iter()
+                .all(|c| !matches!(c, Color::Rgb(..) | Color::Indexed(_))),
+            "{}: {:?}",
+            name,
+            basic
+        );
+        let indexed = theme(name, "256", &[]).unwrap();
+        assert!(
+            slots(&indexed).iter().all(|c| !matches!(c, Color::Rgb(..))),
+            "{}: {:?}",
+            name,
+            indexed
+        );
+    }
+}
+
+#[test]
+fn rows_are_drawn_in_theme_colors() {
+    glyphs::set_ascii(true);
+    let config: Config = toml::from_str(
+        "refresh_ms = 60000\n[theme]\ncolors = \"truecolor\"\ngame = \"#00ff00\"\nprotected = \"#0000ff\"",
+    )
+    .unwrap();
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+    let mut app = App::new(
+        FakeSource::new(fixture()),
+        config,
+        views,
+        keymap,
+        theme,
+        watcher,
+    );
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while app.processes().len() != fixture().len() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for a snapshot"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+    let mut terminal = Terminal::new(TestBackend::new(100, 16)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+
+    // The color of the first letter of `name` in the list.
+    let color_of = |name: &str| {
+        let buffer = terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        let text: String = buffer.content.iter().map(|c| c.symbol()).collect();
+        let at = text
+            .match_indices(&format!(" {} ", name))
+            .map(|(i, _)| i + 1)
+            .find(|i| i / width > 2)
+            .unwrap_or_else(|| panic!("no {} row", name));
+        buffer.content[at].fg
+    };
+    assert_eq!(color_of("steam"), Color::Rgb(0, 255, 0));
+    assert_eq!(color_of("systemd"), Color::Rgb(0, 0, 255));
+}
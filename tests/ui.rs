@@ -0,0 +1,513 @@
+//! Drives `App` and `ui()` against a `TestBackend` with a fixed process
+//! list. Rendered screens are compared with the snapshots in
+//! `tests/snapshots`; run with `INSTA_UPDATE=always` (or `cargo insta
+//! review`) to accept intentional changes.
+
+mod common;
+
+use common::fixture;
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use insta::assert_snapshot;
+use procsnipe::app::{App, Mode};
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::{Keymap, SEQUENCE_TIMEOUT};
+use procsnipe::ui::ui;
+use procsnipe::{FakeSource, Signal, SortMode, glyphs, view};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+struct Harness {
+    app: App<FakeSource>,
+    source: FakeSource,
+    terminal: Terminal<TestBackend>,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let mut harness = Self::with_default_debounce();
+        harness.app.set_debounce(Duration::ZERO);
+        harness
+    }
+
+    /// Like a real terminal: repeated keys close together are dropped
+    /// where the app debounces them.
+    fn with_default_debounce() -> Self {
+        glyphs::set_ascii(true);
+        let config = Config {
+            // Long enough that the collector never rescans mid-test and the
+            // list never goes stale.
+            refresh_ms: 60_000,
+            ..Config::default()
+        };
+        let views = view::load_views(&config.views).unwrap();
+        let keymap = Keymap::from_config(&config.keys).unwrap();
+        let theme = config.theme.resolve().unwrap();
+        let watcher = ConfigWatcher::new(Some(Path::new("/nonexistent/procsnipe.toml")));
+        let source = FakeSource::new(fixture());
+
+        let app = App::new(source.clone(), config, views, keymap, theme, watcher);
+        let mut harness = Self {
+            app,
+            source,
+            terminal: Terminal::new(TestBackend::new(100, 16)).unwrap(),
+        };
+        harness.wait_for(|app| app.processes().len() == fixture().len());
+        harness
+    }
+
+    /// Pull snapshots from the collector until `done` holds.
+    fn wait_for(&mut self, done: impl Fn(&App<FakeSource>) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(&self.app) {
+            assert!(
+                Instant::now() < deadline,
+                "timed out waiting for a snapshot"
+            );
+            std::thread::sleep(Duration::from_millis(5));
+            self.app.receive_snapshot();
+        }
+    }
+
+    fn key(&mut self, code: KeyCode) -> bool {
+        let event = Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        self.app.on_event(event).unwrap()
+    }
+
+    /// Type each character of `keys` as its own key press.
+    fn keys(&mut self, keys: &str) {
+        for c in keys.chars() {
+            assert!(!self.key(KeyCode::Char(c)), "'{}' quit the app", c);
+        }
+    }
+
+    /// Let a pending key sequence (like a lone `g`) fire.
+    fn wait_out_sequence(&mut self) -> bool {
+        std::thread::sleep(SEQUENCE_TIMEOUT + Duration::from_millis(20));
+        self.app.on_timeout().unwrap()
+    }
+
+    fn render(&mut self) -> String {
+        self.terminal.draw(|f| ui(f, &mut self.app)).unwrap();
+        self.terminal.backend().to_string()
+    }
+
+    fn click(&mut self, button: MouseButton, (column, row): (u16, u16)) {
+        let event = Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(button),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        });
+        assert!(!self.app.on_event(event).unwrap());
+    }
+
+    /// Draw, then find where `text` first starts on screen.
+    fn find(&mut self, text: &str) -> (u16, u16) {
+        self.render();
+        let buffer = self.terminal.backend().buffer();
+        let width = buffer.area.width as usize;
+        let screen: Vec<&str> = buffer.content.iter().map(|c| c.symbol()).collect();
+        screen
+            .chunks(width)
+            .enumerate()
+            .find_map(|(y, row)| {
+                let x = row.concat().find(text)?;
+                Some((x as u16, y as u16))
+            })
+            .unwrap_or_else(|| panic!("{:?} isn't on screen:\n{}", text, self.terminal.backend()))
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.app
+            .processes()
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect()
+    }
+}
+
+#[test]
+fn normal() {
+    let mut h = Harness::new();
+    assert_snapshot!(h.render());
+}
+
+#[test]
+fn search() {
+    let mut h = Harness::new();
+    h.keys("/");
+    assert_eq!(h.app.mode(), Mode::Search);
+    h.keys("user=bob");
+    assert_eq!(h.names(), ["cargo", "rust-analyzer"]);
+    assert_snapshot!(h.render());
+}
+
+#[test]
+fn search_with_bad_filter() {
+    let mut h = Harness::new();
+    h.keys("/cpu>");
+    assert_snapshot!(h.render());
+}
+
+#[test]
+fn search_enter_keeps_filter_and_esc_clears_it() {
+    let mut h = Harness::new();
+    h.keys("/fire");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.app.mode(), Mode::Normal);
+    assert_eq!(h.names(), ["firefox"]);
+
+    h.keys("/");
+    h.key(KeyCode::Esc);
+    assert_eq!(h.app.processes().len(), fixture().len());
+}
+
+#[test]
+fn typing_fast_keeps_doubled_characters() {
+    let mut h = Harness::with_default_debounce();
+    h.keys("/cpu>20 && mem>1G");
+    assert_eq!(h.names(), ["cs2"]);
+    assert!(!h.render().contains("expected"));
+    h.key(KeyCode::Enter);
+
+    // Keys that do things are still debounced, only typing isn't.
+    h.keys("v");
+    assert_eq!(h.app.mode(), Mode::Normal);
+    for key in "vn".chars() {
+        std::thread::sleep(Duration::from_millis(200));
+        h.keys(&key.to_string());
+    }
+    assert_eq!(h.app.mode(), Mode::SaveView);
+    h.keys("all");
+    assert!(h.render().contains("view name: all_"));
+}
+
+#[test]
+fn every_keystroke_refilters_the_last_snapshot() {
+    // The collector won't scan again during the test, so all of this
+    // comes from the one snapshot.
+    let mut h = Harness::new();
+    let selected = |h: &Harness| h.app.selected_process().unwrap().name.clone();
+    h.keys("/r");
+    assert_eq!(h.names(), ["firefox", "cargo", "rust-analyzer"]);
+    // A name starting with what's typed beats one that only contains it.
+    assert_eq!(selected(&h), "rust-analyzer");
+    h.keys("u");
+    assert_eq!(h.names(), ["rust-analyzer"]);
+    h.key(KeyCode::Esc);
+
+    // A half-typed query keeps the last list that parsed.
+    h.keys("/cpu>3");
+    assert_eq!(h.names(), ["cs2", "firefox", "cargo", "steam"]);
+    h.key(KeyCode::Backspace);
+    assert_eq!(h.names(), ["cs2", "firefox", "cargo", "steam"]);
+    h.keys("30");
+    assert_eq!(h.names(), ["cs2", "firefox"]);
+    h.key(KeyCode::Esc);
+
+    // Re-sorting keeps the same process selected.
+    h.keys("p1013");
+    h.key(KeyCode::Enter);
+    assert_eq!(selected(&h), "steam");
+    h.keys(":sort name");
+    h.key(KeyCode::Enter);
+    assert_eq!(selected(&h), "steam");
+    assert_eq!(h.names().first(), Some(&"cargo"));
+}
+
+#[test]
+fn help() {
+    let mut h = Harness::new();
+    h.keys("?");
+    assert_eq!(h.app.mode(), Mode::Help);
+    assert_snapshot!(h.render());
+
+    h.key(KeyCode::Esc);
+    assert_eq!(h.app.mode(), Mode::Normal);
+}
+
+#[test]
+fn games_only() {
+    let mut h = Harness::new();
+    // `g` waits to see whether it's the start of `gg`.
+    h.keys("g");
+    assert_eq!(h.names().len(), fixture().len());
+    assert!(!h.wait_out_sequence());
+    assert_eq!(h.names(), ["cs2", "steam"]);
+    assert_snapshot!(h.render());
+
+    h.keys("g");
+    h.wait_out_sequence();
+    assert_eq!(h.names().len(), fixture().len());
+}
+
+#[test]
+fn each_sort_mode() {
+    let modes = [
+        SortMode::Pid,
+        SortMode::Name,
+        SortMode::User,
+        SortMode::Status,
+        SortMode::Cpu,
+        SortMode::Memory,
+    ];
+    for mode in modes {
+        let mut h = Harness::new();
+        h.keys(&format!(":sort {}", mode.as_str()));
+        h.key(KeyCode::Enter);
+        let screen = h.render();
+        assert!(screen.contains(&format!("sort: {:?}", mode)), "{}", screen);
+        assert_snapshot!(format!("sort_{}", mode.as_str()), screen);
+    }
+}
+
+#[test]
+fn sort_key_cycles_cpu_memory_name() {
+    let mut h = Harness::new();
+    let mut seen = Vec::new();
+    for _ in 0..4 {
+        h.keys("s");
+        seen.push(h.names()[0].to_string());
+    }
+    assert_eq!(seen, ["cs2", "cargo", "cs2", "cs2"]);
+    assert!(h.render().contains("sort: Memory"));
+}
+
+#[test]
+fn movement_keys_and_counts() {
+    let mut h = Harness::new();
+    h.keys("j");
+    assert_eq!(h.app.selected_process().unwrap().name, "firefox");
+    h.keys("3j");
+    assert_eq!(h.app.selected_process().unwrap().name, "rust-analyzer");
+    h.keys("G");
+    assert_eq!(h.app.selected_process().unwrap().name, "sshd");
+    h.keys("gg");
+    assert_eq!(h.app.selected_process().unwrap().name, "cs2");
+    // Moving up from the top wraps around.
+    h.keys("k");
+    assert_eq!(h.app.selected_process().unwrap().name, "sshd");
+    assert_snapshot!(h.render());
+}
+
+#[test]
+fn paging_jumps_and_pids() {
+    let mut h = Harness::new();
+    let selected = |h: &Harness| h.app.selected_process().unwrap().name.clone();
+    // With a count, j/k stop at the ends instead of wrapping.
+    h.keys("20j");
+    assert_eq!(selected(&h), "sshd");
+    h.keys("20k");
+    assert_eq!(selected(&h), "cs2");
+    h.keys("3G");
+    assert_eq!(selected(&h), "cargo");
+    h.keys("5gg");
+    assert_eq!(selected(&h), "rust-analyzer");
+
+    // Paging goes by what's on screen, so it needs a frame drawn first.
+    h.render();
+    h.key(KeyCode::PageUp);
+    assert_eq!(selected(&h), "cs2");
+    h.key(KeyCode::PageDown);
+    assert_eq!(selected(&h), "sshd");
+
+    // n/N come back to a search after it's been closed, wrapping around.
+    h.keys("n");
+    assert_eq!(h.app.status_message(), Some("no previous search"));
+    h.keys("/user=bob");
+    h.key(KeyCode::Esc);
+    h.keys("ggn");
+    assert_eq!(selected(&h), "cargo");
+    h.keys("n");
+    assert_eq!(selected(&h), "rust-analyzer");
+    h.keys("n");
+    assert_eq!(selected(&h), "cargo");
+    h.keys("N");
+    assert_eq!(selected(&h), "rust-analyzer");
+
+    h.keys("p1013");
+    assert_eq!(h.app.mode(), Mode::Pid);
+    h.key(KeyCode::Enter);
+    assert_eq!(selected(&h), "steam");
+    h.keys("p9999");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.app.status_message(), Some("no process with PID 9999"));
+    h.keys("/fire");
+    h.key(KeyCode::Enter);
+    h.keys("p1013");
+    h.key(KeyCode::Enter);
+    assert_eq!(
+        h.app.status_message(),
+        Some("PID 1013 is hidden by the current filter")
+    );
+}
+
+#[test]
+fn huge_counts_stop_at_the_ends() {
+    let mut h = Harness::new();
+    let selected = |h: &Harness| h.app.selected_process().unwrap().name.clone();
+    h.render();
+    let huge = usize::MAX.to_string();
+    h.keys(&format!("{huge}j"));
+    assert_eq!(selected(&h), "sshd");
+    h.keys(&format!("{huge}k"));
+    assert_eq!(selected(&h), "cs2");
+    h.keys(&huge);
+    h.key(KeyCode::PageDown);
+    assert_eq!(selected(&h), "sshd");
+    h.keys(&huge);
+    h.key(KeyCode::PageUp);
+    assert_eq!(selected(&h), "cs2");
+    h.keys("/user=bob");
+    h.key(KeyCode::Esc);
+    h.keys(&format!("{huge}n"));
+    assert!(h.app.status_message().unwrap().starts_with("match "));
+    h.keys(&format!("{huge}N"));
+    assert!(h.app.status_message().unwrap().starts_with("match "));
+}
+
+#[test]
+fn command_line_sorts() {
+    let mut h = Harness::new();
+    h.keys(":sort mem asc");
+    assert_eq!(h.app.mode(), Mode::Command);
+    assert_snapshot!("command_line_typing", h.render());
+
+    h.key(KeyCode::Enter);
+    assert_eq!(h.app.mode(), Mode::Normal);
+    assert_eq!(h.names().first(), Some(&"sshd"));
+    assert_eq!(h.names().last(), Some(&"cs2"));
+}
+
+#[test]
+fn mouse_selects_sorts_and_opens_menus() {
+    let mut h = Harness::new();
+    let cargo = h.find("cargo");
+    h.click(MouseButton::Left, cargo);
+    assert_eq!(h.app.selected_process().unwrap().name, "cargo");
+    // Outside the list changes nothing.
+    h.click(MouseButton::Left, (0, 0));
+    assert_eq!(h.app.selected_process().unwrap().name, "cargo");
+
+    // Twice on the same row opens the details.
+    h.click(MouseButton::Left, cargo);
+    assert_eq!(h.app.mode(), Mode::Details);
+    h.click(MouseButton::Left, (0, 0));
+    assert_eq!(h.app.mode(), Mode::Normal);
+
+    // A header sorts by that column.
+    let user = h.find("USER");
+    h.click(MouseButton::Left, user);
+    assert!(h.render().contains("sort: User"));
+    assert_eq!(h.names()[..3], ["firefox", "steam", "cargo"]);
+
+    // Right-click selects the row and opens its menu.
+    let vim = h.find("vim");
+    h.click(MouseButton::Right, vim);
+    assert_eq!(h.app.mode(), Mode::Menu);
+    assert_eq!(h.app.selected_process().unwrap().name, "vim");
+    let hide = h.find("Hide this name");
+    h.click(MouseButton::Left, hide);
+    assert_eq!(h.app.mode(), Mode::Normal);
+    assert!(!h.names().contains(&"vim"));
+    assert_eq!(h.names().len(), fixture().len() - 1);
+
+    // Clicking away closes a menu without doing anything.
+    let cs2 = h.find("cs2");
+    h.click(MouseButton::Right, cs2);
+    assert_eq!(h.app.mode(), Mode::Menu);
+    h.click(MouseButton::Left, (0, 0));
+    assert_eq!(h.app.mode(), Mode::Normal);
+    assert!(h.source.signals().is_empty());
+}
+
+#[test]
+fn refresh_steps_and_pause() {
+    let mut h = Harness::new();
+    // Already as slow as it goes.
+    h.keys("-");
+    assert_eq!(h.app.status_message(), Some("refreshing every 60s"));
+    h.keys("+=");
+    assert_eq!(h.app.status_message(), Some("refreshing every 10s"));
+    assert!(h.render().contains("every 10s"));
+
+    // Off the steps, the next one either way.
+    h.keys(":set refresh 750ms");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.app.status_message(), Some("refreshing every 750ms"));
+    h.keys("-");
+    assert_eq!(h.app.status_message(), Some("refreshing every 1s"));
+    h.keys("+++++");
+    assert_eq!(h.app.status_message(), Some("refreshing every 100ms"));
+    h.keys(":set refresh 2m");
+    h.key(KeyCode::Enter);
+    assert_eq!(
+        h.app.status_message(),
+        Some("refresh 2m is out of range (100ms to 60s)")
+    );
+
+    // Paused, the list holds still however fast it's collected.
+    h.keys(" ");
+    assert!(h.render().contains("PAUSED"));
+    let vim = fixture().pop().unwrap();
+    h.source.set_processes(vec![vim]);
+    std::thread::sleep(Duration::from_millis(300));
+    h.app.receive_snapshot();
+    assert_eq!(h.names().len(), fixture().len());
+    h.keys("f");
+    h.wait_for(|app| app.processes().len() == 1);
+    assert!(h.render().contains("every 100ms"));
+}
+
+#[test]
+fn kill_selected_removes_row() {
+    let mut h = Harness::new();
+    h.keys("jd");
+    assert_eq!(h.source.signals(), [(1012, Signal::Kill)]);
+    h.wait_for(|app| app.processes().len() == fixture().len() - 1);
+    assert!(!h.names().contains(&"firefox"));
+    assert_snapshot!(h.render());
+}
+
+#[test]
+fn protected_process_is_not_killed() {
+    let mut h = Harness::new();
+    h.keys(":pid 1");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.app.selected_process().unwrap().name, "systemd");
+    h.keys("d");
+    assert!(h.source.signals().is_empty());
+    assert!(h.app.status_message().is_some());
+}
+
+#[test]
+fn protected_process_is_not_reniced() {
+    let mut h = Harness::new();
+    h.keys(":pid 1");
+    h.key(KeyCode::Enter);
+    h.keys(":renice 10");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.source.nice(1), None);
+    assert_eq!(
+        h.app.status_message(),
+        Some("systemd (1) is protected, not renicing it")
+    );
+    // Nor by PID, from anywhere in the list.
+    h.keys(":renice 10 1");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.source.nice(1), None);
+    h.keys(":renice 10 1012");
+    h.key(KeyCode::Enter);
+    assert_eq!(h.source.nice(1012), Some(10));
+    assert_eq!(h.app.status_message(), Some("reniced 1012 to 10"));
+}
+
+#[test]
+fn quit() {
+    let mut h = Harness::new();
+    assert!(h.key(KeyCode::Char('q')));
+}
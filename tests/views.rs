@@ -0,0 +1,168 @@
+//! Named views: the built-ins, ones from the config, switching between
+//! them and saving the current one.
+
+mod common;
+
+use common::{fixture, scratch};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use procsnipe::app::{App, Mode};
+use procsnipe::config::{Config, ConfigWatcher};
+use procsnipe::keymap::{Keymap, SEQUENCE_TIMEOUT};
+use procsnipe::ui::ui;
+use procsnipe::view::{self, Column, View};
+use procsnipe::{FakeSource, SortMode, glyphs};
+use ratatui::Terminal;
+use ratatui::backend::TestBackend;
+use std::fs;
+use std::time::{Duration, Instant};
+
+fn config(toml: &str) -> Config {
+    let config: Config = toml::from_str(toml).unwrap();
+    config.validate().unwrap();
+    config
+}
+
+#[test]
+fn builtin_and_configured_views() {
+    let config = config(
+        "[[views]]\nname = \"hogs\"\nfilter = \"cpu>50\"\n\n\
+         [[views]]\nname = \"rust\"\nfilter = \"name~rust\"\nsort = \"mem\"\ncolumns = [\"pid\", \"name\", \"memory\"]",
+    );
+    let views = view::load_views(&config.views).unwrap();
+    let names: Vec<&str> = views.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(&names[..3], ["all", "games", "hogs"]);
+    assert_eq!(names.last(), Some(&"rust"));
+    // Same name as a built-in: replaced where it was.
+    assert_eq!(views[2].query, "cpu>50");
+    let rust = views.last().unwrap();
+    assert_eq!(rust.sort, SortMode::Memory);
+    assert_eq!(rust.columns, [Column::Pid, Column::Name, Column::Mem]);
+    let all = &views[0];
+    assert_eq!(all.filter, None);
+    assert_eq!(all.columns, Column::DEFAULT);
+
+    assert_eq!(view::find(&views, "1"), Some(0));
+    assert_eq!(view::find(&views, "RUST"), Some(views.len() - 1));
+    assert_eq!(view::find(&views, "0"), None);
+    assert_eq!(view::find(&views, "99"), None);
+    assert_eq!(view::find(&views, "nope"), None);
+
+    let unknown = toml::from_str::<Config>("[[views]]\nname = \"x\"\ncolumns = [\"cpu\", \"gpu\"]");
+    assert!(unknown.is_err());
+}
+
+#[test]
+fn saved_views_read_back_the_same() {
+    let view = View::new(
+        "it's \"mine\"",
+        "name=\"web content\" || user=bob",
+        SortMode::Name,
+        &[Column::Pid, Column::Name, Column::Status],
+    )
+    .unwrap();
+    let config = config(&view.to_toml());
+    let read = view::load_views(&config.views).unwrap().pop().unwrap();
+    assert_eq!(read.name, view.name);
+    assert_eq!(read.query, view.query);
+    assert_eq!(read.filter, view.filter);
+    assert_eq!(read.sort, view.sort);
+    assert_eq!(read.columns, view.columns);
+}
+
+fn key(app: &mut App<FakeSource>, code: KeyCode) {
+    let quit = app
+        .on_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+        .unwrap();
+    assert!(!quit);
+}
+
+fn keys(app: &mut App<FakeSource>, keys: &str) {
+    for c in keys.chars() {
+        key(app, KeyCode::Char(c));
+    }
+}
+
+fn names(app: &App<FakeSource>) -> Vec<&str> {
+    app.processes().iter().map(|p| p.name.as_str()).collect()
+}
+
+#[test]
+fn switching_and_saving() {
+    glyphs::set_ascii(true);
+    let dir = scratch("views-save");
+    let path = dir.join("config.toml");
+    fs::write(&path, "# mine\nrefresh_ms = 60000\n").unwrap();
+    let config = Config::load_file(&path).unwrap();
+    let views = view::load_views(&config.views).unwrap();
+    let keymap = Keymap::from_config(&config.keys).unwrap();
+    let theme = config.theme.resolve().unwrap();
+    let watcher = ConfigWatcher::new(Some(&path));
+    let mut app = App::new(
+        FakeSource::new(fixture()),
+        config,
+        views,
+        keymap,
+        theme,
+        watcher,
+    );
+    app.set_debounce(Duration::ZERO);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while app.processes().len() != fixture().len() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting for a snapshot"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        app.receive_snapshot();
+    }
+
+    keys(&mut app, "v");
+    assert_eq!(app.mode(), Mode::Views);
+    keys(&mut app, "2");
+    assert_eq!(app.mode(), Mode::Normal);
+    assert_eq!(names(&app), ["cs2", "steam"]);
+    // A lone `g` (once it's clearly not `gg`) flips back to everything.
+    keys(&mut app, "g");
+    std::thread::sleep(SEQUENCE_TIMEOUT + Duration::from_millis(20));
+    assert!(!app.on_timeout().unwrap());
+    assert_eq!(app.processes().len(), fixture().len());
+
+    // By name from the command line, with its own columns and sort.
+    keys(&mut app, ":view zombies");
+    key(&mut app, KeyCode::Enter);
+    assert!(app.processes().is_empty());
+    let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen = terminal.backend().to_string();
+    assert!(screen.contains("STATUS"), "{}", screen);
+    assert!(!screen.contains("CPU%"), "{}", screen);
+    keys(&mut app, ":view hogs");
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(names(&app), ["cs2", "firefox", "rust-analyzer"]);
+
+    // Saving combines the view's filter with the search on top of it.
+    keys(&mut app, "/user=bob");
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(names(&app), ["rust-analyzer"]);
+    keys(&mut app, "vnbig bob");
+    assert_eq!(app.mode(), Mode::SaveView);
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(
+        app.status_message(),
+        Some(format!("saved view 'big bob' to {}", path.display()).as_str())
+    );
+    assert_eq!(names(&app), ["rust-analyzer"]);
+
+    // Appended, so what was in the file is still there.
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.starts_with("# mine\nrefresh_ms = 60000\n"), "{}", text);
+    let saved = Config::load_file(&path).unwrap();
+    assert_eq!(saved.views.len(), 1);
+    assert_eq!(saved.views[0].name, "big bob");
+    assert_eq!(saved.views[0].filter, "(cpu>20 || mem>1GB) && (user=bob)");
+
+    // An empty name saves nothing.
+    keys(&mut app, "vn");
+    key(&mut app, KeyCode::Enter);
+    assert_eq!(Config::load_file(&path).unwrap().views.len(), 1);
+}